@@ -0,0 +1,220 @@
+use super::common_funcs as cf;
+use super::math::{Mat4, Vec3};
+use js_sys::{Array, Object, Reflect, JSON};
+use wasm_bindgen::prelude::*;
+
+// a labelled pin in the graph's box. on_surface pins only keep their x and z, and sit on the height
+// field wherever it is at the moment
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub id: u32,
+    pub label: String,
+    pub position: [f32; 3],
+    pub on_surface: bool,
+    pub color: [f32; 3],
+}
+
+pub struct AnnotationStore {
+    pub annotations: Vec<Annotation>,
+    next_id: u32,
+}
+
+impl AnnotationStore {
+    pub fn new() -> Self {
+        Self {
+            annotations: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        label: &str,
+        position: [f32; 3],
+        on_surface: bool,
+        color: [f32; 3],
+    ) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.annotations.push(Annotation {
+            id: id,
+            label: String::from(label),
+            position: position,
+            on_surface: on_surface,
+            color: color,
+        });
+        id
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Annotation> {
+        self.annotations
+            .iter_mut()
+            .find(|annotation| annotation.id == id)
+    }
+
+    // false if there was no such annotation
+    pub fn remove(&mut self, id: u32) -> bool {
+        let count = self.annotations.len();
+        self.annotations.retain(|annotation| annotation.id != id);
+        self.annotations.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.annotations.clear();
+    }
+
+    // where each pin's tip is right now. heights are the height field's y values, if it's showing
+    pub fn get_anchors(&self, heights: Option<&Vec<f32>>, n: usize) -> Vec<[f32; 3]> {
+        self.annotations
+            .iter()
+            .map(|annotation| {
+                let [x, y, z] = annotation.position;
+                match heights {
+                    Some(heights) if annotation.on_surface => {
+                        [x, cf::get_height_at(heights, n, x, z), z]
+                    }
+                    _ => [x, y, z],
+                }
+            })
+            .collect()
+    }
+
+    // ==== to and from json ==== //
+    // [{ "id": 1, "label": "peak", "position": [x, y, z], "onSurface": true, "color": [r, g, b] }, ...]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        let list = Array::new();
+        for annotation in self.annotations.iter() {
+            let entry = Object::new();
+            Reflect::set(&entry, &"id".into(), &annotation.id.into())?;
+            Reflect::set(&entry, &"label".into(), &annotation.label.as_str().into())?;
+            Reflect::set(
+                &entry,
+                &"position".into(),
+                &to_js_array(&annotation.position),
+            )?;
+            Reflect::set(&entry, &"onSurface".into(), &annotation.on_surface.into())?;
+            Reflect::set(&entry, &"color".into(), &to_js_array(&annotation.color))?;
+            list.push(&entry);
+        }
+        Ok(String::from(JSON::stringify(&list)?))
+    }
+
+    // replaces everything with what's in the json. ids are kept, so links to them from outside still work
+    pub fn load_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let list = JSON::parse(json)?;
+        if !Array::is_array(&list) {
+            return Err(JsValue::from("expected a list of annotations"));
+        }
+        let mut annotations: Vec<Annotation> = Vec::new();
+        for entry in Array::from(&list).iter() {
+            let id = get_id(Reflect::get(&entry, &"id".into())?.as_f64())?;
+            if annotations.iter().any(|annotation| annotation.id == id) {
+                return Err(JsValue::from(format!(
+                    "there's more than one annotation {}",
+                    id
+                )));
+            }
+            let label = Reflect::get(&entry, &"label".into())?
+                .as_string()
+                .unwrap_or_default();
+            let position = from_js_array(&Reflect::get(&entry, &"position".into())?)
+                .ok_or("every annotation needs a position [x, y, z]")?;
+            let on_surface = Reflect::get(&entry, &"onSurface".into())?
+                .as_bool()
+                .unwrap_or(false);
+            let color = from_js_array(&Reflect::get(&entry, &"color".into())?)
+                .unwrap_or(super::constants::ANNOTATION_COLOR);
+            annotations.push(Annotation {
+                id: id,
+                label: label,
+                position: position,
+                on_surface: on_surface,
+                color: color,
+            });
+        }
+
+        self.next_id = annotations
+            .iter()
+            .map(|annotation| annotation.id + 1)
+            .max()
+            .unwrap_or(1);
+        self.annotations = annotations;
+        Ok(())
+    }
+}
+
+// a whole number that fits a u32. u32::MAX itself is out too, next_id would have nowhere to go after it
+fn get_id(value: Option<f64>) -> Result<u32, String> {
+    match value {
+        Some(id) if id.fract() == 0. && id >= 0. && id < std::u32::MAX as f64 => Ok(id as u32),
+        Some(id) => Err(format!("{} can't be an annotation id", id)),
+        None => Err(String::from("every annotation needs a numeric id")),
+    }
+}
+
+pub fn to_js_array(values: &[f32; 3]) -> JsValue {
+    let protag = Array::new();
+    for &value in values.iter() {
+        protag.push(&(value as f64).into());
+    }
+    protag.into()
+}
+
+pub fn from_js_array(value: &JsValue) -> Option<[f32; 3]> {
+    if !Array::is_array(value) {
+        return None;
+    }
+    let array = Array::from(value);
+    let mut protag = [0.; 3];
+    for i in 0..3 {
+        protag[i] = array.get(i as u32).as_f64()? as f32;
+    }
+    Some(protag)
+}
+
+// where a point in the graph's box lands on the canvas, in px with y going up like in webgl.
+// none if it's behind the camera
+pub fn get_canvas_point(
+    projection: &Mat4,
+    point: &[f32; 3],
+    canvas_height: f32,
+    canvas_width: f32,
+) -> Option<(f32, f32)> {
+    let [x, y, _, w] = projection.transform_homogeneous(Vec3::from_array(*point));
+    if w <= 0. {
+        return None;
+    }
+    Some((
+        (x / w + 1.) / 2. * canvas_width,
+        (y / w + 1.) / 2. * canvas_height,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_have_to_be_whole_and_leave_room_for_the_next() {
+        assert_eq!(get_id(Some(0.)), Ok(0));
+        assert_eq!(get_id(Some(41.)), Ok(41));
+        assert_eq!(
+            get_id(Some(std::u32::MAX as f64 - 1.)),
+            Ok(std::u32::MAX - 1)
+        );
+
+        for &id in [
+            1.5,
+            -1.,
+            std::u32::MAX as f64,
+            1e12,
+            std::f64::INFINITY,
+            std::f64::NAN,
+        ]
+        .iter()
+        {
+            assert!(get_id(Some(id)).is_err());
+        }
+        assert!(get_id(None).is_err());
+    }
+}
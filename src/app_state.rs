@@ -1,33 +1,51 @@
+use super::camera::{self, Lens, Projection, ViewPreset, ViewTransition};
+use super::constants::*;
+use super::parametric::ParametricSurface;
+use super::programs::RenderMode;
 use std::sync::Arc;
 use std::sync::Mutex;
+use wasm_bindgen::prelude::*;
 
 lazy_static! {
     static ref APP_STATE: Mutex<Arc<AppState>> = Mutex::new(Arc::new(AppState::new()));
 }
 
-pub fn update_dynamic_data(time: f32, canvas_height: f32, canvas_width: f32) {
-    // some logic here to center our render
-    let min_height_width = canvas_height.min(canvas_width); // min = get smaller of the two
-    let display_size = 0.9 * min_height_width;
-    let half_display_size = display_size / 2.;
-    let half_canvas_height = canvas_height / 2.;
-    let half_canvas_width = canvas_width / 2.;
-
+// wall_time is the clock handed to Client::update, time is the timeline's (see timeline.rs)
+pub fn update_dynamic_data(wall_time: f32, time: f32, canvas_height: f32, canvas_width: f32) {
     // the app state is modified here
     let mut data = APP_STATE.lock().unwrap();
 
-    *data = Arc::new(AppState {
-        canvas_height: canvas_height,
-        canvas_width: canvas_width,
-
-        control_bottom: half_canvas_height - half_display_size,
-        control_top: half_canvas_height + half_display_size,
-        control_left: half_canvas_width - half_display_size,
-        control_right: half_canvas_width + half_display_size,
+    // the cells follow the canvas around, the cameras in them stay as they are
+    let mut viewports = data.viewports;
+    let cells = get_viewport_cells(data.viewport_layout, canvas_height, canvas_width);
+    for (viewport, cell) in viewports.iter_mut().zip(cells.iter()) {
+        viewport.bottom = cell.0;
+        viewport.top = cell.1;
+        viewport.left = cell.2;
+        viewport.right = cell.3;
+    }
+    for viewport in viewports.iter_mut() {
+        if let Some(transition) = viewport.transition {
+            let ((rotation_x_axis, rotation_y_axis), done) = transition.get_rotation(wall_time);
+            viewport.rotation_x_axis = rotation_x_axis;
+            viewport.rotation_y_axis = rotation_y_axis;
+            if done {
+                viewport.transition = None;
+            }
+        }
+    }
 
-        time: time,
-        ..*data.clone() // wtf is going on here?
-    });
+    *data = Arc::new(with_viewport(
+        &AppState {
+            canvas_height: canvas_height,
+            canvas_width: canvas_width,
+            viewports: viewports,
+            time: time,
+            wall_time: wall_time,
+            ..*data.clone() // wtf is going on here?
+        },
+        data.current_viewport,
+    ));
 }
 
 pub fn get_cur_state() -> Arc<AppState> {
@@ -42,11 +60,67 @@ pub struct AppState {
     pub control_left: f32,
     pub control_right: f32,
     pub mouse_down: bool,
+    pub mouse_down_x: f32,
+    pub mouse_down_y: f32,
     pub mouse_x: f32,
     pub mouse_y: f32,
+    pub pending_click: Option<(f32, f32)>,
     pub rotation_x_axis: f32,
     pub rotation_y_axis: f32,
-    pub time: f32,
+    pub lens: Lens,
+    pub surface_mode: SurfaceMode,
+    pub parametric_surface: ParametricSurface,
+    pub render_mode: RenderMode,
+    pub time: f32, // ms, where the timeline's playhead is. the surfaces animate by this
+    pub wall_time: f32, // ms, the clock handed to Client::update. view transitions keep to it while paused
+    pub viewport_layout: ViewportLayout,
+    pub viewports: [Viewport; MAX_VIEWPORTS],
+    pub viewport_count: usize,
+    // the viewport control_* and rotation_* are copied from: the one under the mouse (or being dragged in),
+    // or the one being drawn while Client::render goes through them
+    pub current_viewport: usize,
+}
+
+// ==== viewports: cells of the canvas with a camera each ==== //
+pub const MAX_VIEWPORTS: usize = 4;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewportLayout {
+    Single,     // the whole canvas, one camera
+    SideBySide, // looking down from the top on the left, free camera on the right
+    Quad,       // top, front, side and a free camera, like cad software
+}
+
+// px, with y going up like in webgl. the graph's display box sits in the middle of the cell
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    pub bottom: f32,
+    pub top: f32,
+    pub left: f32,
+    pub right: f32,
+    pub rotation_x_axis: f32,
+    pub rotation_y_axis: f32,
+    pub lens: Lens,
+    pub transition: Option<ViewTransition>, // swinging over to a preset view
+}
+
+// which function drives the y values of the 3d graph
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceMode {
+    Analytic,
+    Wave,
+    HeatExplicit,
+    HeatImplicit,
+    ReactionDiffusion,
+    Terrain,
+    Parametric,
+    Isosurface,
+    Complex,
+    Bars,
+    VectorField,
+    Ode,
 }
 
 impl AppState {
@@ -59,21 +133,92 @@ impl AppState {
             control_left: 0.,
             control_right: 0.,
             mouse_down: false,
+            mouse_down_x: -1.,
+            mouse_down_y: -1.,
             mouse_x: -1.,
             mouse_y: -1.,
+            pending_click: None,
             rotation_x_axis: -0.5,
             rotation_y_axis: -0.5,
+            lens: Lens::new(),
+            surface_mode: SurfaceMode::Analytic,
+            parametric_surface: ParametricSurface::Torus,
+            render_mode: RenderMode::Solid,
             time: 0.,
+            wall_time: 0.,
+            viewport_layout: ViewportLayout::Single,
+            viewports: [Viewport {
+                bottom: 0.,
+                top: 0.,
+                left: 0.,
+                right: 0.,
+                rotation_x_axis: -0.5,
+                rotation_y_axis: -0.5,
+                lens: Lens::new(),
+                transition: None,
+            }; MAX_VIEWPORTS],
+            viewport_count: 1,
+            current_viewport: 0,
         }
     }
 }
 
 pub fn update_mouse_down(x: f32, y: f32, is_down: bool) {
     let mut data = APP_STATE.lock().unwrap();
+    let inverted_y = data.canvas_height - y; // inverting y because in browserland, y is 0 at top, and increases as your mouse moves down
+
+    // pressing picks the viewport that gets the drag, and the click
+    if is_down {
+        if let Some(index) = get_viewport_at(&data, x, inverted_y) {
+            *data = Arc::new(with_viewport(&data, index));
+        }
+    }
+
+    // a press and release without much movement in between is a click, not a drag
+    let pending_click = if !is_down && data.mouse_down {
+        let drag_distance =
+            ((x - data.mouse_down_x).powi(2) + (inverted_y - data.mouse_down_y).powi(2)).sqrt();
+        if drag_distance <= CLICK_DRAG_TOLERANCE {
+            Some((x, inverted_y))
+        } else {
+            data.pending_click
+        }
+    } else {
+        data.pending_click
+    };
+
     *data = Arc::new(AppState {
         mouse_down: is_down,
+        mouse_down_x: if is_down { x } else { data.mouse_down_x },
+        mouse_down_y: if is_down {
+            inverted_y
+        } else {
+            data.mouse_down_y
+        },
         mouse_x: x,
-        mouse_y: data.canvas_height - y, // inverting y because in browserland, y is 0 at top, and increases as your mouse moves down
+        mouse_y: inverted_y,
+        pending_click: pending_click,
+        ..*data.clone()
+    })
+}
+
+// hands the last click over to whoever asks, and forgets it so it is only handled once
+pub fn take_pending_click() -> Option<(f32, f32)> {
+    let mut data = APP_STATE.lock().unwrap();
+    let click = data.pending_click;
+    if click.is_some() {
+        *data = Arc::new(AppState {
+            pending_click: None,
+            ..*data.clone()
+        });
+    }
+    click
+}
+
+pub fn update_surface_mode(surface_mode: SurfaceMode) {
+    let mut data = APP_STATE.lock().unwrap();
+    *data = Arc::new(AppState {
+        surface_mode: surface_mode,
         ..*data.clone()
     })
 }
@@ -81,6 +226,16 @@ pub fn update_mouse_down(x: f32, y: f32, is_down: bool) {
 pub fn update_mouse_position(x: f32, y: f32) {
     let mut data = APP_STATE.lock().unwrap();
     let inverted_y = data.canvas_height - y;
+
+    // hovering moves between viewports, a drag stays in the one it started in
+    if !data.mouse_down {
+        if let Some(index) = get_viewport_at(&data, x, inverted_y) {
+            if index != data.current_viewport {
+                *data = Arc::new(with_viewport(&data, index));
+            }
+        }
+    }
+
     let x_delta = x - data.mouse_x;
     let y_delta = inverted_y - data.mouse_y;
     let rotation_x_delta = if data.mouse_down {
@@ -93,12 +248,196 @@ pub fn update_mouse_position(x: f32, y: f32) {
     } else {
         0.
     };
+    let rotation_x_axis = data.rotation_x_axis + rotation_x_delta;
+    let rotation_y_axis = data.rotation_y_axis - rotation_y_delta;
+    let mut viewports = data.viewports;
+    viewports[data.current_viewport].rotation_x_axis = rotation_x_axis;
+    viewports[data.current_viewport].rotation_y_axis = rotation_y_axis;
+    if data.mouse_down {
+        viewports[data.current_viewport].transition = None; // grabbing the camera stops it swinging
+    }
 
     *data = Arc::new(AppState {
         mouse_x: x,
         mouse_y: inverted_y, // inverting y because in browserland, y is 0 at top, and increases as your mouse moves down
-        rotation_x_axis: data.rotation_x_axis + rotation_x_delta,
-        rotation_y_axis: data.rotation_y_axis - rotation_y_delta,
+        rotation_x_axis: rotation_x_axis,
+        rotation_y_axis: rotation_y_axis,
+        viewports: viewports,
+        ..*data.clone()
+    })
+}
+
+pub fn update_parametric_surface(parametric_surface: ParametricSurface) {
+    let mut data = APP_STATE.lock().unwrap();
+    *data = Arc::new(AppState {
+        parametric_surface: parametric_surface,
+        ..*data.clone()
+    })
+}
+
+pub fn update_render_mode(render_mode: RenderMode) {
+    let mut data = APP_STATE.lock().unwrap();
+    *data = Arc::new(AppState {
+        render_mode: render_mode,
+        ..*data.clone()
+    })
+}
+
+// every layout starts its viewports on their standard views. the free camera keeps where it was
+pub fn update_viewport_layout(viewport_layout: ViewportLayout) {
+    let mut data = APP_STATE.lock().unwrap();
+    let free_camera = data.viewports[data.viewport_count - 1]; // always the last one
+    let standard_view = |preset: ViewPreset| {
+        let (rotation_x_axis, rotation_y_axis) = camera::get_preset_rotation(preset);
+        Viewport {
+            rotation_x_axis: rotation_x_axis,
+            rotation_y_axis: rotation_y_axis,
+            // the flat views are for reading off sizes, so no foreshortening
+            lens: Lens {
+                projection: Projection::Orthographic,
+                ..free_camera.lens
+            },
+            transition: None,
+            ..free_camera
+        }
+    };
+    let cameras = match viewport_layout {
+        ViewportLayout::Single => vec![free_camera],
+        ViewportLayout::SideBySide => vec![standard_view(ViewPreset::Top), free_camera],
+        ViewportLayout::Quad => vec![
+            standard_view(ViewPreset::Top),
+            standard_view(ViewPreset::Front),
+            standard_view(ViewPreset::Side),
+            free_camera,
+        ],
+    };
+
+    let mut viewports = data.viewports;
+    let cells = get_viewport_cells(viewport_layout, data.canvas_height, data.canvas_width);
+    for (i, (camera, cell)) in cameras.iter().zip(cells.iter()).enumerate() {
+        viewports[i] = Viewport {
+            bottom: cell.0,
+            top: cell.1,
+            left: cell.2,
+            right: cell.3,
+            ..*camera
+        };
+    }
+
+    *data = Arc::new(with_viewport(
+        &AppState {
+            viewport_layout: viewport_layout,
+            viewports: viewports,
+            viewport_count: cameras.len(),
+            ..*data.clone()
+        },
+        cameras.len() - 1,
+    ));
+}
+
+// ---- the current viewport's camera ---- //
+// swings it over to a standard view, starting now
+pub fn update_view_preset(preset: ViewPreset) {
+    let mut data = APP_STATE.lock().unwrap();
+    let mut viewports = data.viewports;
+    let viewport = &mut viewports[data.current_viewport];
+    viewport.transition = Some(ViewTransition::new(
+        (viewport.rotation_x_axis, viewport.rotation_y_axis),
+        camera::get_preset_rotation(preset),
+        data.wall_time,
+        VIEW_TRANSITION_DURATION,
+    ));
+    *data = Arc::new(AppState {
+        viewports: viewports,
         ..*data.clone()
     })
 }
+
+pub fn update_lens(lens: Lens) {
+    let mut data = APP_STATE.lock().unwrap();
+    let mut viewports = data.viewports;
+    viewports[data.current_viewport].lens = lens;
+    *data = Arc::new(AppState {
+        viewports: viewports,
+        lens: lens,
+        ..*data.clone()
+    })
+}
+
+// puts viewport index's camera somewhere outright (a camera path does this every frame), calling off
+// any transition it was in the middle of
+pub fn update_viewport_camera(index: usize, rotation: (f32, f32), lens: Lens) {
+    let mut data = APP_STATE.lock().unwrap();
+    let mut viewports = data.viewports;
+    let viewport = &mut viewports[index];
+    viewport.rotation_x_axis = rotation.0;
+    viewport.rotation_y_axis = rotation.1;
+    viewport.lens = lens;
+    viewport.transition = None;
+    *data = Arc::new(with_viewport(
+        &AppState {
+            viewports: viewports,
+            ..*data.clone()
+        },
+        data.current_viewport,
+    ));
+}
+
+// a copy of the state with control_*, rotation_* and lens from viewport index, for drawing it.
+// the global state stays as it is
+pub fn with_viewport(data: &AppState, index: usize) -> AppState {
+    let viewport = data.viewports[index];
+
+    // some logic here to center our render
+    let height = viewport.top - viewport.bottom;
+    let width = viewport.right - viewport.left;
+    let min_height_width = height.min(width); // min = get smaller of the two
+    let display_size = 0.9 * min_height_width;
+    let half_display_size = display_size / 2.;
+    let center_y = viewport.bottom + height / 2.;
+    let center_x = viewport.left + width / 2.;
+
+    AppState {
+        control_bottom: center_y - half_display_size,
+        control_top: center_y + half_display_size,
+        control_left: center_x - half_display_size,
+        control_right: center_x + half_display_size,
+        rotation_x_axis: viewport.rotation_x_axis,
+        rotation_y_axis: viewport.rotation_y_axis,
+        lens: viewport.lens,
+        current_viewport: index,
+        ..*data
+    }
+}
+
+// (bottom, top, left, right) of each viewport, in px with y going up
+fn get_viewport_cells(
+    viewport_layout: ViewportLayout,
+    canvas_height: f32,
+    canvas_width: f32,
+) -> Vec<(f32, f32, f32, f32)> {
+    let half_height = canvas_height / 2.;
+    let half_width = canvas_width / 2.;
+    match viewport_layout {
+        ViewportLayout::Single => vec![(0., canvas_height, 0., canvas_width)],
+        ViewportLayout::SideBySide => vec![
+            (0., canvas_height, 0., half_width),
+            (0., canvas_height, half_width, canvas_width),
+        ],
+        ViewportLayout::Quad => vec![
+            (half_height, canvas_height, 0., half_width), // top left
+            (half_height, canvas_height, half_width, canvas_width), // top right
+            (0., half_height, 0., half_width),            // bottom left
+            (0., half_height, half_width, canvas_width),  // bottom right
+        ],
+    }
+}
+
+// which viewport the point (px, y going up) is in, if any
+fn get_viewport_at(data: &AppState, x: f32, y: f32) -> Option<usize> {
+    data.viewports[..data.viewport_count]
+        .iter()
+        .position(|viewport| {
+            x >= viewport.left && x < viewport.right && y >= viewport.bottom && y < viewport.top
+        })
+}
@@ -0,0 +1,103 @@
+use super::constants::*;
+
+// offset xyz, scale xyz, rgb. one per bar, for the instanced unit cube of the Bars3d program
+pub const FLOATS_PER_BAR: usize = 9;
+
+// ==== lays a table of values out as bars on the floor of the graph's box ==== //
+// rows go along z, columns along x, so each row is a group of bars side by side.
+// values are row by row. gap is the fraction of each cell left empty between bars (0 to 1).
+// bars grow from the baseline value: up for values above it, down for values below it, and the
+// one furthest from the baseline is BARS_3D_MAX_HEIGHT tall. colors are rgb per bar, or empty to
+// colour the bars by column
+pub fn get_bar_instances(
+    values: &Vec<f32>,
+    rows: usize,
+    columns: usize,
+    colors: &Vec<f32>,
+    gap: f32,
+    baseline: f32,
+) -> Result<Vec<f32>, String> {
+    if rows == 0 || columns == 0 || values.len() != rows * columns {
+        return Err(format!(
+            "expected {} values ({} rows of {} columns), got {}",
+            rows * columns,
+            rows,
+            columns,
+            values.len()
+        ));
+    }
+    if !colors.is_empty() && colors.len() != 3 * values.len() {
+        return Err(format!(
+            "expected {} colour values (rgb per bar), got {}",
+            3 * values.len(),
+            colors.len()
+        ));
+    }
+    let gap = gap.max(0.).min(0.95);
+
+    let largest = values
+        .iter()
+        .map(|value| (value - baseline).abs())
+        .fold(0., f32::max);
+    let height_scale = if largest > 0. {
+        BARS_3D_MAX_HEIGHT / largest
+    } else {
+        0.
+    };
+
+    let cell_width = 2. / columns as f32; // the box goes from -1 to +1
+    let cell_depth = 2. / rows as f32;
+    let mut protag: Vec<f32> = Vec::with_capacity(FLOATS_PER_BAR * values.len());
+    for row in 0..rows {
+        for column in 0..columns {
+            let i = row * columns + column;
+            let color = if colors.is_empty() {
+                BARS_3D_PALETTE[column % BARS_3D_PALETTE.len()]
+            } else {
+                [colors[3 * i], colors[3 * i + 1], colors[3 * i + 2]]
+            };
+            protag.extend_from_slice(&[
+                -1. + (column as f32 + 0.5 * gap) * cell_width,
+                0.,
+                -1. + (row as f32 + 0.5 * gap) * cell_depth,
+                (1. - gap) * cell_width,
+                (values[i] - baseline) * height_scale, // negative, and the cube hangs down
+                (1. - gap) * cell_depth,
+                color[0],
+                color[1],
+                color[2],
+            ]);
+        }
+    }
+
+    Ok(protag)
+}
+
+// the edges of the box the bars stand in, as pairs of points for GL::LINES
+pub fn get_bounding_box_segments(instances: &Vec<f32>) -> Vec<f32> {
+    let hangs_down = instances
+        .chunks(FLOATS_PER_BAR)
+        .any(|instance| instance[4] < 0.);
+    let min = [-1., if hangs_down { -BARS_3D_MAX_HEIGHT } else { 0. }, -1.];
+    let max = [1., BARS_3D_MAX_HEIGHT, 1.];
+
+    let corner = |i: usize| -> [f32; 3] {
+        [
+            if i & 1 == 0 { min[0] } else { max[0] },
+            if i & 2 == 0 { min[1] } else { max[1] },
+            if i & 4 == 0 { min[2] } else { max[2] },
+        ]
+    };
+    let mut protag: Vec<f32> = Vec::with_capacity(12 * 2 * 3);
+    // corners that differ in exactly one bit share an edge
+    for a in 0..8 {
+        for &bit in [1, 2, 4].iter() {
+            if a & bit == 0 {
+                protag.extend_from_slice(&corner(a));
+                protag.extend_from_slice(&corner(a | bit));
+            }
+        }
+    }
+
+    protag
+}
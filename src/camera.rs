@@ -0,0 +1,99 @@
+use super::constants::*;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,  // nearer is bigger
+    Orthographic, // no foreshortening, so heights compare straight off the screen
+}
+
+// how a viewport's camera frames the graph, on top of where it's looking from (its rotation)
+#[derive(Clone, Copy, Debug)]
+pub struct Lens {
+    pub projection: Projection,
+    pub field_of_view: f32, // radians, top to bottom. orthographic ignores it
+    pub z_near: f32,        // clip things nearer than this to the camera
+    pub z_far: f32,         // how far you can see before things are clipped
+    pub zoom: f32,          // 2 = everything twice the size on screen
+    pub target: [f32; 3], // the point in the graph's box that sits in the middle of the display box
+}
+
+impl Lens {
+    pub fn new() -> Self {
+        Self {
+            projection: Projection::Perspective,
+            field_of_view: FIELD_OF_VIEW,
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+            zoom: 1.,
+            target: [0., 0., 0.],
+        }
+    }
+
+    // how far from the camera the middle of the graph's box sits. at that distance the perspective
+    // and the orthographic projection agree, so the box keeps its size when switching between them
+    pub fn get_focal_distance(&self) -> f32 {
+        1. / (self.field_of_view / 2.).tan()
+    }
+}
+
+// ==== standard views ==== //
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewPreset {
+    Top,       // looking straight down, x to the right
+    Front,     // from the +z side, y up
+    Side,      // from the -x side, y up
+    Isometric, // all three axes equally foreshortened
+}
+
+// (rotation about x, rotation about y), like app_state's
+pub fn get_preset_rotation(preset: ViewPreset) -> (f32, f32) {
+    let half_pi = std::f32::consts::FRAC_PI_2;
+    match preset {
+        ViewPreset::Top => (-half_pi, 0.),
+        ViewPreset::Front => (0., 0.),
+        ViewPreset::Side => (0., -half_pi),
+        // the camera tilts about x first, then turns about the screen's vertical. tilting by 45 deg lays
+        // the (-1, 1, 1) diagonal flat, and turning by atan(1 / sqrt(2)) points it straight at us
+        ViewPreset::Isometric => (-half_pi / 2., -(1. / 2f32.sqrt()).atan()),
+    }
+}
+
+// a camera swinging from one rotation to another, eased in and out
+#[derive(Clone, Copy, Debug)]
+pub struct ViewTransition {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    pub start_time: f32, // ms, like app_state's wall_time
+    pub duration: f32,   // ms
+}
+
+impl ViewTransition {
+    // goes the short way round, whatever the angles have wound up to from dragging
+    pub fn new(from: (f32, f32), to: (f32, f32), start_time: f32, duration: f32) -> Self {
+        let nearest = |from: f32, to: f32| {
+            let two_pi = 2. * std::f32::consts::PI;
+            let turn = (to - from) / two_pi;
+            from + (turn - turn.round()) * two_pi
+        };
+        Self {
+            from: from,
+            to: (nearest(from.0, to.0), nearest(from.1, to.1)),
+            start_time: start_time,
+            duration: duration,
+        }
+    }
+
+    // the rotation at time, and whether the transition is over
+    pub fn get_rotation(&self, time: f32) -> ((f32, f32), bool) {
+        let t = ((time - self.start_time) / self.duration).max(0.).min(1.);
+        let eased = t * t * (3. - 2. * t); // smoothstep: starts and stops gently
+        let lerp = |a: f32, b: f32| a + eased * (b - a);
+        (
+            (lerp(self.from.0, self.to.0), lerp(self.from.1, self.to.1)),
+            t >= 1.,
+        )
+    }
+}
@@ -0,0 +1,306 @@
+use super::annotations::{from_js_array, to_js_array};
+use super::constants::*;
+use super::math::{Mat4, Quat, Vec3};
+use js_sys::{Array, Object, Reflect, JSON};
+use wasm_bindgen::prelude::*;
+
+// how the camera speeds up and slows down on its way into a keyframe
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,    // starts slow
+    EaseOut,   // arrives slow
+    EaseInOut, // both
+}
+
+impl Easing {
+    // t from 0 to 1, in and out
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t) * (1. - t) * (1. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseIn => "easeIn",
+            Easing::EaseOut => "easeOut",
+            Easing::EaseInOut => "easeInOut",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "easeIn" => Some(Easing::EaseIn),
+            "easeOut" => Some(Easing::EaseOut),
+            "easeInOut" => Some(Easing::EaseInOut),
+            _ => None,
+        }
+    }
+}
+
+// where the camera is: like a viewport's rotation_* and its lens's zoom and target
+#[derive(Clone, Copy, Debug)]
+pub struct CameraPose {
+    pub rotation: (f32, f32), // (about x, about y), like app_state's
+    pub zoom: f32,
+    pub target: [f32; 3],
+}
+
+impl CameraPose {
+    // the same limits Client::set_zoom and set_camera_target hold the live camera to
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.rotation.0.is_finite() && self.rotation.1.is_finite()) {
+            return Err(String::from("the rotation has to be two angles"));
+        }
+        if !(self.zoom >= CAMERA_MIN_ZOOM && self.zoom <= CAMERA_MAX_ZOOM) {
+            return Err(format!(
+                "the zoom should be between {} and {}",
+                CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM
+            ));
+        }
+        if !self.target.iter().all(|coord| coord.is_finite()) {
+            return Err(String::from("the target has to be a point"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    pub time: f32, // ms, on the timeline
+    pub pose: CameraPose,
+    pub easing: Easing, // on the way in from the keyframe before
+}
+
+// ==== keyframes for flying the camera through the graph ==== //
+// in between two keyframes, the rotation goes along the shortest arc (slerp), the zoom changes by the
+// same factor every ms and the target moves in a straight line, all eased by the later keyframe's easing
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>, // by time
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self {
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn get_keyframes(&self) -> &Vec<CameraKeyframe> {
+        &self.keyframes
+    }
+
+    // a keyframe at the same time as one already there replaces it
+    pub fn add(&mut self, keyframe: CameraKeyframe) {
+        self.keyframes
+            .retain(|existing| (existing.time - keyframe.time).abs() > 1e-3);
+        let index = self
+            .keyframes
+            .iter()
+            .position(|existing| existing.time > keyframe.time)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, keyframe);
+    }
+
+    // index is in time order. returns whether there was one to remove
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.keyframes.len() {
+            return false;
+        }
+        self.keyframes.remove(index);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    // when the last keyframe is
+    pub fn get_end_time(&self) -> Option<f32> {
+        self.keyframes.last().map(|keyframe| keyframe.time)
+    }
+
+    // before the first keyframe the camera waits at it, and after the last it stays put
+    pub fn get_pose(&self, time: f32) -> Option<CameraPose> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.pose);
+        }
+        let next_index = match self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+        {
+            Some(next_index) => next_index,
+            None => return Some(self.keyframes.last()?.pose),
+        };
+        let from = &self.keyframes[next_index - 1];
+        let to = &self.keyframes[next_index];
+        let t = to.easing.apply((time - from.time) / (to.time - from.time));
+
+        let from_rotation = get_rotation_quat(from.pose.rotation);
+        let to_rotation = get_rotation_quat(to.pose.rotation);
+        let rotation =
+            get_rotation_angles(&Mat4::from_quat(Quat::slerp(from_rotation, to_rotation, t)));
+        // the angles come back between -pi and pi. keep them near the keyframes' own angles, which can
+        // have wound round any number of turns from dragging
+        let lerp = |a: f32, b: f32| a + t * (b - a);
+        let nearest = |near: f32, angle: f32| {
+            let two_pi = 2. * std::f32::consts::PI;
+            angle + ((near - angle) / two_pi).round() * two_pi
+        };
+
+        Some(CameraPose {
+            rotation: (
+                nearest(lerp(from.pose.rotation.0, to.pose.rotation.0), rotation.0),
+                nearest(lerp(from.pose.rotation.1, to.pose.rotation.1), rotation.1),
+            ),
+            // the same factor every step of the way, so zooming from 1 to 4 reaches 2 halfway
+            zoom: from.pose.zoom * (to.pose.zoom / from.pose.zoom).powf(t),
+            target: Vec3::from_array(from.pose.target)
+                .lerp(Vec3::from_array(to.pose.target), t)
+                .to_array(),
+        })
+    }
+
+    // ==== to and from json ==== //
+    // [{ "time": 0, "rotation": [x, y], "zoom": 1, "target": [x, y, z], "easing": "easeInOut" }, ...]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        let list = Array::new();
+        for keyframe in self.keyframes.iter() {
+            let entry = Object::new();
+            let rotation = Array::new();
+            rotation.push(&(keyframe.pose.rotation.0 as f64).into());
+            rotation.push(&(keyframe.pose.rotation.1 as f64).into());
+            Reflect::set(&entry, &"time".into(), &(keyframe.time as f64).into())?;
+            Reflect::set(&entry, &"rotation".into(), &rotation)?;
+            Reflect::set(&entry, &"zoom".into(), &(keyframe.pose.zoom as f64).into())?;
+            Reflect::set(
+                &entry,
+                &"target".into(),
+                &to_js_array(&keyframe.pose.target),
+            )?;
+            Reflect::set(&entry, &"easing".into(), &keyframe.easing.get_name().into())?;
+            list.push(&entry);
+        }
+        Ok(String::from(JSON::stringify(&list)?))
+    }
+
+    // replaces the whole path with what's in the json
+    pub fn load_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let list = JSON::parse(json)?;
+        if !Array::is_array(&list) {
+            return Err(JsValue::from("expected a list of camera keyframes"));
+        }
+        let mut path = CameraPath::new();
+        for entry in Array::from(&list).iter() {
+            let time = Reflect::get(&entry, &"time".into())?
+                .as_f64()
+                .map(|time| time as f32)
+                .filter(|time| time.is_finite() && *time >= 0.)
+                .ok_or("every keyframe needs a time, 0 or later")?;
+            let rotation = Reflect::get(&entry, &"rotation".into())?;
+            let rotation = if Array::is_array(&rotation) {
+                let rotation = Array::from(&rotation);
+                rotation.get(0).as_f64().zip(rotation.get(1).as_f64())
+            } else {
+                None
+            }
+            .ok_or("every keyframe needs a rotation [x, y]")?;
+            let zoom = Reflect::get(&entry, &"zoom".into())?.as_f64().unwrap_or(1.) as f32;
+            // left out, the target is the middle of the graph. there but not a point is a mistake
+            let target = Reflect::get(&entry, &"target".into())?;
+            let target = if target.is_undefined() {
+                [0., 0., 0.]
+            } else {
+                from_js_array(&target).ok_or("the target has to be [x, y, z]")?
+            };
+            let easing = match Reflect::get(&entry, &"easing".into())?.as_string() {
+                Some(name) => {
+                    Easing::from_name(&name).ok_or(format!("there's no easing called {}", name))?
+                }
+                None => Easing::EaseInOut,
+            };
+            let pose = CameraPose {
+                rotation: (rotation.0 as f32, rotation.1 as f32),
+                zoom: zoom,
+                target: target,
+            };
+            pose.check()?;
+            path.add(CameraKeyframe {
+                time: time,
+                pose: pose,
+                easing: easing,
+            });
+        }
+        *self = path;
+        Ok(())
+    }
+}
+
+// the camera's rotation (see common_funcs::get_3d_projection_matrix_and_rotation) as a quaternion
+fn get_rotation_quat(rotation: (f32, f32)) -> Quat {
+    Quat::from_axis_angle(Vec3::new(0., 1., 0.), -rotation.1)
+        * Quat::from_axis_angle(Vec3::new(1., 0., 0.), -rotation.0)
+}
+
+// and back. the camera tilts about x, then turns about the screen's vertical, and can't roll.
+// a slerp between two such rotations can pick up a little roll on the way, which gets dropped
+// here: that keeps the x axis level on screen all the way along
+fn get_rotation_angles(rotation: &Mat4) -> (f32, f32) {
+    // the rotation is Ry(a) * Rx(b), with a = -(about y) and b = -(about x):
+    //   row 0: ( cos a, sin a sin b,  sin a cos b)
+    //   row 1: (     0,       cos b,      -sin b)
+    //   row 2: (-sin a, cos a sin b,  cos a cos b)
+    let m = |row: usize, column: usize| rotation.get(row, column);
+    let b = (-m(1, 2)).atan2(m(1, 1));
+    let a = (-m(2, 0)).atan2(m(0, 0));
+    (-b, -a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poses_are_held_to_the_live_camera_limits() {
+        let pose = CameraPose {
+            rotation: (0.5, -0.3),
+            zoom: 1.,
+            target: [0., 0.2, 0.],
+        };
+        assert!(pose.check().is_ok());
+        for &zoom in [CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM].iter() {
+            assert!(CameraPose { zoom: zoom, ..pose }.check().is_ok());
+        }
+
+        let inf = std::f32::INFINITY;
+        let nan = std::f32::NAN;
+        for &zoom in [0., CAMERA_MIN_ZOOM / 2., CAMERA_MAX_ZOOM * 2., inf, nan].iter() {
+            assert!(CameraPose { zoom: zoom, ..pose }.check().is_err());
+        }
+        for &rotation in [(inf, 0.), (0., nan)].iter() {
+            assert!(CameraPose {
+                rotation: rotation,
+                ..pose
+            }
+            .check()
+            .is_err());
+        }
+        for &target in [[inf, 0., 0.], [0., 0., nan]].iter() {
+            assert!(CameraPose {
+                target: target,
+                ..pose
+            }
+            .check()
+            .is_err());
+        }
+    }
+}
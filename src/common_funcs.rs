@@ -1,5 +1,7 @@
+use super::camera::{Lens, Projection};
 use super::constants::*;
-use nalgebra::{Matrix4, Perspective3};
+use super::math::{Mat4, Vec3};
+use std::collections::HashSet;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::*;
 
@@ -7,6 +9,18 @@ pub fn link_program(
     gl: &WebGlRenderingContext,
     vertex_shader_source: &str,
     fragment_shader_source: &str,
+) -> Result<WebGlProgram, String> {
+    link_program_with_attributes(gl, vertex_shader_source, fragment_shader_source, &[])
+}
+
+// same as link_program, but attribute_names[i] is pinned to attribute location i.
+// without this, which attribute ends up where is the driver's call, which gets risky once
+// a program has more than a couple of them
+pub fn link_program_with_attributes(
+    gl: &WebGlRenderingContext,
+    vertex_shader_source: &str,
+    fragment_shader_source: &str,
+    attribute_names: &[&str],
 ) -> Result<WebGlProgram, String> {
     let program = gl
         .create_program()
@@ -16,6 +30,9 @@ pub fn link_program(
 
     gl.attach_shader(&program, &vertex_shader);
     gl.attach_shader(&program, &fragment_shader);
+    for (location, name) in attribute_names.iter().enumerate() {
+        gl.bind_attrib_location(&program, location as u32, name);
+    }
     gl.link_program(&program);
 
     if gl
@@ -55,57 +72,27 @@ fn compile_shader(
     }
 }
 
-// matrix! returns 1d array that acts as a 4x4 matrix. this can be used for 3d later
-pub fn translation_matrix(tx: f32, ty: f32, tz: f32) -> [f32; 16] {
-    let mut protag = [0.; 16];
-
-    protag[0] = 1.;
-    protag[5] = 1.;
-    protag[10] = 1.;
-    protag[15] = 1.;
-
-    protag[12] = tx;
-    protag[13] = ty;
-    protag[14] = tz;
-
-    protag
-}
-
-pub fn scaling_matrix(sx: f32, sy: f32, sz: f32) -> [f32; 16] {
-    let mut protag = [0.; 16];
-
-    protag[0] = sx;
-    protag[5] = sy;
-    protag[10] = sz;
-    protag[15] = 1.;
-
-    protag
-}
-
-pub fn cross_multiply_matrix(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
-    let mut protag = [0.; 16];
-
-    protag[0] = a[0] * b[0] + a[1] * b[4] + a[2] * b[8] + a[3] * b[12];
-    protag[1] = a[0] * b[1] + a[1] * b[5] + a[2] * b[9] + a[3] * b[13];
-    protag[2] = a[0] * b[2] + a[1] * b[6] + a[2] * b[10] + a[3] * b[14];
-    protag[3] = a[0] * b[3] + a[1] * b[7] + a[2] * b[11] + a[3] * b[15];
-
-    protag[4] = a[4] * b[0] + a[5] * b[4] + a[6] * b[8] + a[7] * b[12];
-    protag[5] = a[4] * b[1] + a[5] * b[5] + a[6] * b[9] + a[7] * b[13];
-    protag[6] = a[4] * b[2] + a[5] * b[6] + a[6] * b[10] + a[7] * b[14];
-    protag[7] = a[4] * b[3] + a[5] * b[7] + a[6] * b[11] + a[7] * b[15];
-
-    protag[8] = a[8] * b[0] + a[9] * b[4] + a[10] * b[8] + a[11] * b[12];
-    protag[9] = a[8] * b[1] + a[9] * b[5] + a[10] * b[9] + a[11] * b[13];
-    protag[10] = a[8] * b[2] + a[9] * b[6] + a[10] * b[10] + a[11] * b[14];
-    protag[11] = a[8] * b[3] + a[9] * b[7] + a[10] * b[11] + a[11] * b[15];
-
-    protag[12] = a[12] * b[0] + a[13] * b[4] + a[14] * b[8] + a[15] * b[12];
-    protag[13] = a[12] * b[1] + a[13] * b[5] + a[14] * b[9] + a[15] * b[13];
-    protag[14] = a[12] * b[2] + a[13] * b[6] + a[14] * b[10] + a[15] * b[14];
-    protag[15] = a[12] * b[3] + a[13] * b[7] + a[14] * b[11] + a[15] * b[15];
-
-    protag
+// ==== transform that puts the 0 to 1 square on the canvas rectangle from (left, bottom) to (right, top) ==== //
+// the same placement the 2d programs use for their rectangles
+pub fn get_2d_panel_transform(
+    bottom: f32,
+    top: f32,
+    left: f32,
+    right: f32,
+    canvas_height: f32,
+    canvas_width: f32,
+) -> Mat4 {
+    let translation = Mat4::translation(Vec3::new(
+        2. * left / canvas_width - 1.,
+        2. * bottom / canvas_height - 1.,
+        0.,
+    ));
+    let scale = Mat4::scaling(Vec3::new(
+        2. * (right - left) / canvas_width,
+        2. * (top - bottom) / canvas_height,
+        0.,
+    ));
+    translation * scale
 }
 
 // ==== this is for grid creation ==== //
@@ -161,10 +148,67 @@ pub fn get_position_grid_n_by_n(n: usize) -> (Vec<f32>, Vec<u16>) {
     (positions, indices)
 }
 
-// ==== function to get 3D perspective projection matrix ==== //
+// ==== edges of a triangle mesh, for drawing it as a wireframe with GL::LINES ==== //
+// every triangle has 3 edges, but neighbouring triangles share theirs. each edge is kept only once,
+// whichever way round its triangle listed it, so the lines are not drawn twice on top of each other
+pub fn get_edge_indices(triangle_indices: &Vec<u16>) -> Vec<u16> {
+    let mut seen: HashSet<(u16, u16)> = HashSet::new();
+    let mut protag: Vec<u16> = Vec::new();
+
+    for triangle in triangle_indices.chunks(3) {
+        for &(a, b) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ]
+        .iter()
+        {
+            if seen.insert((a.min(b), a.max(b))) {
+                protag.push(a);
+                protag.push(b);
+            }
+        }
+    }
+
+    protag
+}
+
+// a webgl extension, or an error saying what can't be drawn without it
+pub fn get_extension(
+    gl: &WebGlRenderingContext,
+    name: &str,
+    needed_for: &str,
+) -> Result<js_sys::Object, String> {
+    gl.get_extension(name)
+        .ok()
+        .and_then(|extension| extension)
+        .ok_or(format!(
+            "{} is needed to draw {}, and this browser doesn't have it",
+            name, needed_for
+        ))
+}
+
+// ==== the matrices that take the 3d graph's box to the screen ==== //
+// passed to the shaders separately, as projection * view * model:
+//   - model: where something sits in the graph's box (see scene.rs). identity for the graph itself
+//   - view: the camera's rotation about the box's middle. lighting is worked out in view space, so
+//     the light stays put over the viewer's shoulder while the graph turns
+//   - projection: brings the lens's target to the middle, sizes the box to the display, pushes it
+//     away from the camera, applies the lens and its zoom and moves it to the display box on the canvas
+//   - normal_matrix: the inverse transpose of view * model, so normals stay perpendicular to their
+//     surface when the model scales unevenly
 pub struct Matrices3D {
-    pub projection: [f32; 16],
-    pub normals_rotation: [f32; 16],
+    pub model: Mat4,
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub normal_matrix: Mat4,
+}
+
+impl Matrices3D {
+    // all three in one, for working out where things land on the canvas
+    pub fn get_model_view_projection(&self) -> Mat4 {
+        self.projection * self.view * self.model
+    }
 }
 
 pub fn get_3d_projection_matrix_and_rotation(
@@ -176,80 +220,387 @@ pub fn get_3d_projection_matrix_and_rotation(
     canvas_width: f32,
     rotation_angle_x_axis: f32,
     rotation_angle_y_axis: f32,
+    lens: &Lens,
 ) -> Matrices3D {
-    let mut protag = Matrices3D {
-        projection: [0.; 16],
-        normals_rotation: [0.; 16],
+    // ---- rotation matrix ---- //
+    // tilt about x first, then turn about the screen's vertical
+    let view = Mat4::rotation_y(-rotation_angle_y_axis) * Mat4::rotation_x(-rotation_angle_x_axis);
+
+    // ---- calculate aspect ratio ---- //
+    let aspect: f32 = canvas_width / canvas_height;
+    let scale = (top - bottom) / canvas_height;
+
+    // the box is scaled to the display's height (in all 3 directions, so the perspective has depth
+    // to work with) and pushed away from the camera to where both projections agree on its size
+    let scale_matrix = Mat4::scaling(Vec3::new(scale, scale, scale));
+    // the target moves to the middle after the rotation, so the camera still turns about the box's
+    // middle and the box stays about the focal distance in front of it
+    let to_target = Mat4::translation(-view.transform_vector(Vec3::from_array(lens.target)));
+    let push_away = Mat4::translation(Vec3::new(0., 0., -lens.get_focal_distance()));
+
+    let lens_projection = match lens.projection {
+        Projection::Perspective => {
+            Mat4::perspective(lens.field_of_view, aspect, lens.z_near, lens.z_far)
+        }
+        Projection::Orthographic => {
+            Mat4::orthographic(-aspect, aspect, -1., 1., lens.z_near, lens.z_far)
+        }
     };
 
-    // ---- rotation matrix ---- //
-    #[rustfmt::skip]
-    let rotate_x_axis: [f32;16] = [
-        1., 0., 0., 0., //
-        0., rotation_angle_x_axis.cos(), -rotation_angle_x_axis.sin(), 0., //
-        0., rotation_angle_x_axis.sin(), rotation_angle_x_axis.cos(), 0., //
-        0., 0., 0., 1. //
+    // zooming scales the picture, rather than moving the camera in, so nothing ends up behind it
+    let zoom = Mat4::scaling(Vec3::new(lens.zoom, lens.zoom, 1.));
+
+    // last, move the middle of the screen to the middle of the display box. in clip space (before the
+    // divide by w) a translation adds w times itself, which is what keeps the perspective centered on the box
+    let to_box = Mat4::translation(Vec3::new(
+        -1. + (left + right) / canvas_width,
+        -1. + (bottom + top) / canvas_height,
+        0.,
+    ));
+
+    Matrices3D {
+        model: Mat4::identity(),
+        view: view,
+        projection: to_box * zoom * lens_projection * push_away * scale_matrix * to_target,
+        normal_matrix: view.normal_matrix(),
+    }
+}
+
+// the same matrices for something placed in the graph's box by a model matrix (applied first)
+pub fn apply_model_matrix(matrices: &Matrices3D, model: &Mat4) -> Matrices3D {
+    let model = matrices.model * *model;
+    Matrices3D {
+        model: model,
+        view: matrices.view,
+        projection: matrices.projection,
+        normal_matrix: (matrices.view * model).normal_matrix(),
+    }
+}
+
+// where a 3d program's shader takes the matrices: uModel, uView, uProjection and (if it does any
+// lighting) uNormalMatrix
+pub struct MatrixUniforms {
+    u_model: WebGlUniformLocation,
+    u_view: WebGlUniformLocation,
+    u_projection: WebGlUniformLocation,
+    u_normal_matrix: Option<WebGlUniformLocation>,
+}
+
+impl MatrixUniforms {
+    pub fn new(gl: &WebGlRenderingContext, program: &WebGlProgram) -> Self {
+        Self {
+            u_model: gl.get_uniform_location(program, "uModel").unwrap(),
+            u_view: gl.get_uniform_location(program, "uView").unwrap(),
+            u_projection: gl.get_uniform_location(program, "uProjection").unwrap(),
+            u_normal_matrix: gl.get_uniform_location(program, "uNormalMatrix"),
+        }
+    }
+
+    // the program has to be in use
+    pub fn set(&self, gl: &WebGlRenderingContext, matrices: &Matrices3D) {
+        gl.uniform_matrix4fv_with_f32_array(Some(&self.u_model), false, matrices.model.as_slice());
+        gl.uniform_matrix4fv_with_f32_array(Some(&self.u_view), false, matrices.view.as_slice());
+        gl.uniform_matrix4fv_with_f32_array(
+            Some(&self.u_projection),
+            false,
+            matrices.projection.as_slice(),
+        );
+        if let Some(u_normal_matrix) = &self.u_normal_matrix {
+            gl.uniform_matrix4fv_with_f32_array(
+                Some(u_normal_matrix),
+                false,
+                matrices.normal_matrix.as_slice(),
+            );
+        }
+    }
+}
+
+// ==== picking: from a point on the canvas back into the grid's coordinates ==== //
+// projection * (x, y, z, 1) lands on the canvas point (ndc_x, ndc_y) when
+//   (row0 - ndc_x * row3) . (x, y, z, 1) = 0  and  (row1 - ndc_y * row3) . (x, y, z, 1) = 0
+// two planes. all points under the cursor are where they meet, which is a line (our ray).
+// this also works while the projection squashes depth and can't be inverted.
+// returns the ray's origin (in front of everything in the grid's box) and its normalized direction, pointing into the screen
+pub fn get_3d_pick_ray(
+    bottom: f32,
+    top: f32,
+    left: f32,
+    right: f32,
+    canvas_height: f32,
+    canvas_width: f32,
+    rotation_angle_x_axis: f32,
+    rotation_angle_y_axis: f32,
+    lens: &Lens,
+    mouse_x: f32,
+    mouse_y: f32,
+) -> Option<([f32; 3], [f32; 3])> {
+    let matrices = get_3d_projection_matrix_and_rotation(
+        bottom,
+        top,
+        left,
+        right,
+        canvas_height,
+        canvas_width,
+        rotation_angle_x_axis,
+        rotation_angle_y_axis,
+        lens,
+    );
+    let model_view_projection = matrices.get_model_view_projection();
+    let row_0 = model_view_projection.row(0);
+    let row_1 = model_view_projection.row(1);
+    let row_3 = model_view_projection.row(3);
+    let ndc_x = 2. * mouse_x / canvas_width - 1.;
+    let ndc_y = 2. * mouse_y / canvas_height - 1.;
+
+    let mut plane_1 = [0.; 4];
+    let mut plane_2 = [0.; 4];
+    for i in 0..4 {
+        plane_1[i] = row_0[i] - ndc_x * row_3[i];
+        plane_2[i] = row_1[i] - ndc_y * row_3[i];
+    }
+
+    let dot = |a: &[f32], b: &[f32]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let mut direction = [
+        plane_1[1] * plane_2[2] - plane_1[2] * plane_2[1],
+        plane_1[2] * plane_2[0] - plane_1[0] * plane_2[2],
+        plane_1[0] * plane_2[1] - plane_1[1] * plane_2[0],
     ];
+    let direction_size_squared = dot(&direction, &direction);
+    if direction_size_squared < 1e-12 {
+        return None; // planes are parallel, the cursor is somewhere the projection never reaches
+    }
 
-    #[rustfmt::skip]
-    let rotate_y_axis: [f32;16] = [
-        rotation_angle_y_axis.cos(), 0., rotation_angle_y_axis.sin(), 0.,
-        0., 1., 0., 0.,
-        -rotation_angle_y_axis.sin(), 0., rotation_angle_y_axis.cos(), 0.,
-        0., 0., 0., 1.,
+    // point on both planes that is closest to the grid's center
+    let n1_n1 = dot(&plane_1, &plane_1);
+    let n2_n2 = dot(&plane_2, &plane_2);
+    let n1_n2 = dot(&plane_1, &plane_2);
+    let determinant = n1_n1 * n2_n2 - n1_n2 * n1_n2;
+    let h1 = -plane_1[3];
+    let h2 = -plane_2[3];
+    let c1 = (h1 * n2_n2 - h2 * n1_n2) / determinant;
+    let c2 = (h2 * n1_n1 - h1 * n1_n2) / determinant;
+    let closest = [
+        c1 * plane_1[0] + c2 * plane_2[0],
+        c1 * plane_1[1] + c2 * plane_2[1],
+        c1 * plane_1[2] + c2 * plane_2[2],
     ];
 
-    let rotation_matrix = cross_multiply_matrix(&rotate_x_axis, &rotate_y_axis);
+    // the line has no "into the screen" by itself. the camera looks down -z after rotation,
+    // which in the grid's coordinates is the rotation's third row, negated
+    let r = matrices.view.row(2);
+    let into_screen = [-r[0], -r[1], -r[2]];
+    let direction_size = direction_size_squared.sqrt();
+    let sign = if dot(&direction, &into_screen) < 0. {
+        -1.
+    } else {
+        1.
+    };
+    for i in 0..3 {
+        direction[i] *= sign / direction_size;
+    }
 
-    // ---- calculate aspect ratio ---- //
-    let aspect: f32 = canvas_width / canvas_height;
-    let scale_x = (right - left) / canvas_width;
-    let scale_y = (top - bottom) / canvas_height;
-    let scale = scale_y;
-
-    let translation_matrix: [f32; 16] = translation_matrix(
-        -1. + scale_x + 2. * left / canvas_width,
-        -1. + scale_y + 2. * bottom / canvas_height,
-        Z_PLANE,
-    );
-    let scale_matrix: [f32; 16] = scaling_matrix(scale, scale, 0.);
-    let rotation_scale = cross_multiply_matrix(&rotation_matrix, &scale_matrix);
-    let combined_transform = cross_multiply_matrix(&rotation_scale, &translation_matrix);
-    let perspective_matrix_tmp: Perspective3<f32> =
-        Perspective3::new(aspect, FIELD_OF_VIEW, Z_NEAR, Z_FAR);
-    let mut perspective: [f32; 16] = [0.; 16];
-    perspective.copy_from_slice(perspective_matrix_tmp.as_matrix().as_slice());
-
-    protag.projection = cross_multiply_matrix(&combined_transform, &perspective);
-    // ---- normal matrix for protag ---- //
-    let normal_matrix = Matrix4::new(
-        rotation_matrix[0],
-        rotation_matrix[1],
-        rotation_matrix[2],
-        rotation_matrix[3],
-        rotation_matrix[4],
-        rotation_matrix[5],
-        rotation_matrix[6],
-        rotation_matrix[7],
-        rotation_matrix[8],
-        rotation_matrix[9],
-        rotation_matrix[10],
-        rotation_matrix[11],
-        rotation_matrix[12],
-        rotation_matrix[13],
-        rotation_matrix[14],
-        rotation_matrix[15],
-    );
+    // back off far enough to be in front of anything inside the grid's box
+    let back_off = 4.;
+    let origin = [
+        closest[0] - back_off * direction[0],
+        closest[1] - back_off * direction[1],
+        closest[2] - back_off * direction[2],
+    ];
+
+    Some((origin, direction))
+}
+
+// ==== height of the surface at (x, z), between the grid points ==== //
+// linear within each of the graph's triangles (split top left to bottom right, see get_position_grid_n_by_n),
+// so it matches what's drawn
+pub fn get_height_at(heights: &Vec<f32>, n: usize, x: f32, z: f32) -> f32 {
+    let square_size: f32 = 2. / n as f32;
+    let points_per_row = n + 1;
+    let grid_x = ((x + 1.) / square_size).max(0.).min(n as f32);
+    let grid_z = ((z + 1.) / square_size).max(0.).min(n as f32);
+    let cell_x = (grid_x.floor() as usize).min(n - 1);
+    let cell_z = (grid_z.floor() as usize).min(n - 1);
+    let (u, v) = (grid_x - cell_x as f32, grid_z - cell_z as f32);
+
+    let height = |x: usize, z: usize| heights[z * points_per_row + x];
+    let top_left = height(cell_x, cell_z);
+    let btm_right = height(cell_x + 1, cell_z + 1);
+    if v >= u {
+        // top left, bottom left, bottom right
+        let btm_left = height(cell_x, cell_z + 1);
+        top_left + v * (btm_left - top_left) + u * (btm_right - btm_left)
+    } else {
+        // top left, bottom right, top right
+        let top_right = height(cell_x + 1, cell_z);
+        top_left + u * (top_right - top_left) + v * (btm_right - top_right)
+    }
+}
+
+// ==== where a ray first hits the height field (y values laid out like the 3d graph's) ==== //
+// walks the grid cells under the ray, front to back (a 2d dda over x and z), and tests the two
+// triangles of each cell the way the graph draws them. returns the hit point and its cell (x, z)
+pub fn get_height_field_hit(
+    heights: &Vec<f32>,
+    n: usize,
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+) -> Option<([f32; 3], (usize, usize))> {
+    let square_size: f32 = 2. / n as f32;
+    let points_per_row = n + 1;
+
+    // the part of the ray over the grid (x and z from -1 to +1) and between the lowest and highest point
+    let lowest = heights.iter().cloned().fold(std::f32::INFINITY, f32::min);
+    let highest = heights
+        .iter()
+        .cloned()
+        .fold(std::f32::NEG_INFINITY, f32::max);
+    let mut t_enter = 0_f32;
+    let mut t_exit = std::f32::INFINITY;
+    for &(axis, min, max) in [(0, -1., 1.), (1, lowest, highest), (2, -1., 1.)].iter() {
+        if direction[axis].abs() < 1e-9 {
+            if origin[axis] < min || origin[axis] > max {
+                return None;
+            }
+            continue;
+        }
+        let t_a = (min - origin[axis]) / direction[axis];
+        let t_b = (max - origin[axis]) / direction[axis];
+        t_enter = t_enter.max(t_a.min(t_b));
+        t_exit = t_exit.min(t_a.max(t_b));
+    }
+    if t_enter > t_exit {
+        return None;
+    }
 
-    match normal_matrix.try_inverse() {
-        Some(inv) => {
-            protag.normals_rotation.copy_from_slice(inv.as_slice());
+    let cell_of = |coord: f32| -> i64 {
+        (((coord + 1.) / square_size).floor() as i64)
+            .max(0)
+            .min(n as i64 - 1)
+    };
+    let mut cell_x = cell_of(origin[0] + t_enter * direction[0]);
+    let mut cell_z = cell_of(origin[2] + t_enter * direction[2]);
+
+    // how far along the ray to the next cell border in x (and z), and from one border to the next
+    let step_x: i64 = if direction[0] > 0. { 1 } else { -1 };
+    let step_z: i64 = if direction[2] > 0. { 1 } else { -1 };
+    let next_border = |cell: i64, step: i64, axis: usize| -> f32 {
+        if direction[axis].abs() < 1e-9 {
+            return std::f32::INFINITY;
         }
-        None => {
-            panic!("failed to get normal matrix' inverse!");
+        let border = -1. + (cell + if step > 0 { 1 } else { 0 }) as f32 * square_size;
+        (border - origin[axis]) / direction[axis]
+    };
+    let mut t_next_x = next_border(cell_x, step_x, 0);
+    let mut t_next_z = next_border(cell_z, step_z, 2);
+    let t_delta_x = square_size / direction[0].abs();
+    let t_delta_z = square_size / direction[2].abs();
+
+    let point = |x: usize, z: usize| -> [f32; 3] {
+        [
+            -1. + x as f32 * square_size,
+            heights[z * points_per_row + x],
+            -1. + z as f32 * square_size,
+        ]
+    };
+    loop {
+        let (x, z) = (cell_x as usize, cell_z as usize);
+        // same two triangles as get_position_grid_n_by_n, split along top left to bottom right
+        let top_left = point(x, z);
+        let btm_left = point(x, z + 1);
+        let btm_right = point(x + 1, z + 1);
+        let top_right = point(x + 1, z);
+        let hit = [
+            get_ray_triangle_hit(origin, direction, &top_left, &btm_left, &btm_right),
+            get_ray_triangle_hit(origin, direction, &top_left, &btm_right, &top_right),
+        ]
+        .iter()
+        .filter_map(|&t| t)
+        .fold(None, |nearest: Option<f32>, t| {
+            Some(nearest.map_or(t, |nearest| nearest.min(t)))
+        });
+        if let Some(t) = hit {
+            return Some((
+                [
+                    origin[0] + t * direction[0],
+                    origin[1] + t * direction[1],
+                    origin[2] + t * direction[2],
+                ],
+                (x, z),
+            ));
+        }
+
+        // on to the neighbouring cell whose border comes first
+        if t_next_x < t_next_z {
+            if t_next_x > t_exit {
+                return None;
+            }
+            cell_x += step_x;
+            t_next_x += t_delta_x;
+        } else {
+            if t_next_z > t_exit {
+                return None;
+            }
+            cell_z += step_z;
+            t_next_z += t_delta_z;
+        }
+        if cell_x < 0 || cell_z < 0 || cell_x >= n as i64 || cell_z >= n as i64 {
+            return None;
         }
     }
+}
 
+// moller-trumbore. how far along the ray it hits the triangle, if it does (in front of the origin)
+pub fn get_ray_triangle_hit(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    a: &[f32; 3],
+    b: &[f32; 3],
+    c: &[f32; 3],
+) -> Option<f32> {
+    let sub = |p: &[f32; 3], q: &[f32; 3]| [p[0] - q[0], p[1] - q[1], p[2] - q[2]];
+    let cross = |p: &[f32; 3], q: &[f32; 3]| {
+        [
+            p[1] * q[2] - p[2] * q[1],
+            p[2] * q[0] - p[0] * q[2],
+            p[0] * q[1] - p[1] * q[0],
+        ]
+    };
+    let dot = |p: &[f32; 3], q: &[f32; 3]| p[0] * q[0] + p[1] * q[1] + p[2] * q[2];
+
+    let edge_1 = sub(b, a);
+    let edge_2 = sub(c, a);
+    let p = cross(direction, &edge_2);
+    let determinant = dot(&edge_1, &p);
+    if determinant.abs() < 1e-12 {
+        return None; // ray runs along the triangle
+    }
+    let from_a = sub(origin, a);
+    let u = dot(&from_a, &p) / determinant;
+    if u < 0. || u > 1. {
+        return None;
+    }
+    let q = cross(&from_a, &edge_1);
+    let v = dot(direction, &q) / determinant;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let t = dot(&edge_2, &q) / determinant;
+    if t < 0. {
+        return None;
+    }
+    Some(t)
+}
+
+// three little lines crossing at the point, one along each axis, as pairs of points for GL::LINES
+pub fn get_marker_segments(point: &[f32; 3], size: f32) -> Vec<f32> {
+    let mut protag: Vec<f32> = Vec::with_capacity(3 * 2 * 3);
+    for axis in 0..3 {
+        for &side in [-1., 1.].iter() {
+            let mut end = *point;
+            end[axis] += side * 0.5 * size;
+            protag.extend_from_slice(&end);
+        }
+    }
     protag
 }
 
@@ -355,3 +706,172 @@ pub fn get_updated_3d_y_values(cur_time: f32) -> Vec<f32> {
 
     y_vals
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    // what the shaders do with a normal: uNormalMatrix * vec4(n, 0.), normalized
+    fn transform_normal(matrices: &Matrices3D, normal: Vec3) -> Vec3 {
+        matrices.normal_matrix.transform_vector(normal).normalize()
+    }
+
+    fn get_matrices() -> Matrices3D {
+        get_3d_projection_matrix_and_rotation(
+            0.,
+            600.,
+            0.,
+            800.,
+            600.,
+            800.,
+            0.5,
+            -0.7,
+            &Lens::new(),
+        )
+    }
+
+    // clip space depth (-1 near to +1 far) of a point in the graph's box
+    fn get_depth(matrices: &Matrices3D, point: Vec3) -> f32 {
+        let [_, _, z, w] = matrices
+            .get_model_view_projection()
+            .transform_homogeneous(point);
+        z / w
+    }
+
+    #[test]
+    fn the_whole_box_is_between_the_clip_planes() {
+        let matrices = get_matrices();
+        for corner in 0..8 {
+            let sign = |bit: usize| if corner & bit == 0 { -1. } else { 1. };
+            let depth = get_depth(&matrices, Vec3::new(sign(1), sign(2), sign(4)));
+            assert!(depth > -1. && depth < 1., "{}", depth);
+        }
+        // and so is something a scene node puts well behind the box, which the old depth (worked out
+        // from view space, rather than the lens) cut off 2 behind the middle
+        let towards_camera = matrices
+            .view
+            .transpose()
+            .transform_vector(Vec3::new(0., 0., 1.));
+        let behind = get_depth(&matrices, towards_camera * -10.);
+        assert!(behind > -1. && behind < 1., "{}", behind);
+        // nearer is smaller
+        let middle = get_depth(&matrices, Vec3::new(0., 0., 0.));
+        assert!(get_depth(&matrices, towards_camera * 0.5) < middle);
+        assert!(middle < behind);
+    }
+
+    #[test]
+    fn clip_planes_cut_the_box() {
+        let lens = Lens::new();
+        let focal_distance = lens.get_focal_distance();
+        let cut = |z_near: f32, z_far: f32| {
+            get_3d_projection_matrix_and_rotation(
+                0.,
+                600.,
+                0.,
+                800.,
+                600.,
+                800.,
+                0.,
+                0.,
+                &Lens {
+                    z_near: z_near,
+                    z_far: z_far,
+                    ..lens
+                },
+            )
+        };
+        // looking straight down -z, so the box's +z side is nearer
+        let near_side = Vec3::new(0., 0., 0.5);
+        let far_side = Vec3::new(0., 0., -0.5);
+
+        let matrices = cut(focal_distance - 0.25, focal_distance + 0.25);
+        assert!(get_depth(&matrices, Vec3::new(0., 0., 0.)).abs() < 1.);
+        assert!(get_depth(&matrices, near_side) < -1.);
+        assert!(get_depth(&matrices, far_side) > 1.);
+
+        let matrices = cut(focal_distance - 1., focal_distance + 1.);
+        assert!(get_depth(&matrices, near_side).abs() < 1.);
+        assert!(get_depth(&matrices, far_side).abs() < 1.);
+    }
+
+    #[test]
+    fn model_matrices_compose_in_order() {
+        let matrices = get_matrices();
+        let parent = Mat4::translation(Vec3::new(1., 0., 0.));
+        let child = Mat4::scaling(Vec3::new(2., 1., 1.));
+        let applied = apply_model_matrix(&apply_model_matrix(&matrices, &parent), &child);
+        // the child's scaling first, then the parent's move
+        let point = applied.model.transform_vector(Vec3::new(1., 0., 0.));
+        assert_close(point, Vec3::new(2., 0., 0.));
+        assert_eq!(applied.model, parent * child);
+        assert_eq!(applied.view, matrices.view);
+        assert_eq!(applied.projection, matrices.projection);
+    }
+
+    #[test]
+    fn normals_under_non_uniform_scale() {
+        let matrices = get_matrices();
+        let applied = apply_model_matrix(&matrices, &Mat4::scaling(Vec3::new(2., 1., 1.)));
+
+        // the plane z = x: tangents (1, 0, 1) and (0, 1, 0), normal (-1, 0, 1) / sqrt 2.
+        // stretched to twice as wide it's z = x / 2, with tangents (2, 0, 1) and (0, 1, 0), and
+        // normal (-1, 0, 2) / sqrt 5 (worked out by hand). the view then turns that like anything else
+        let normal = transform_normal(&applied, Vec3::new(-1., 0., 1.));
+        let expected = Vec3::new(-1., 0., 2.) * (1. / 5f32.sqrt());
+        assert_close(normal, matrices.view.transform_vector(expected));
+
+        // just scaling the normal along (what the shaders used to do) would get it wrong
+        let naive = (applied.view * applied.model)
+            .transform_vector(Vec3::new(-1., 0., 1.))
+            .normalize();
+        assert!((naive - normal).length() > 0.1);
+
+        // and it stays perpendicular to the surface, in view space
+        for tangent in [Vec3::new(1., 0., 1.), Vec3::new(0., 1., 0.)].iter() {
+            let tangent = (applied.view * applied.model).transform_vector(*tangent);
+            assert!(normal.dot(tangent).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn normals_under_a_zero_scale() {
+        let matrices = get_matrices();
+        // flattened onto the xz plane, whatever the surface was, so every normal ends up along y
+        let applied = apply_model_matrix(&matrices, &Mat4::scaling(Vec3::new(1., 0., 1.)));
+        let up = matrices.view.transform_vector(Vec3::new(0., 1., 0.));
+        for normal in [
+            Vec3::new(0., 1., 0.),
+            Vec3::new(0.3, 1., -0.2),
+            Vec3::new(-0.5, 0.4, 0.6),
+        ]
+        .iter()
+        {
+            let normal = transform_normal(&applied, *normal);
+            assert_close(normal, up);
+            // a normal can't be NaN, or the lighting goes black
+            assert!(normal.length().is_finite());
+        }
+
+        // a normal lying in the flattened direction has nowhere to go, and stays zero rather than NaN
+        let lost = applied
+            .normal_matrix
+            .transform_vector(Vec3::new(1., 0., 0.));
+        assert_close(lost, Vec3::new(0., 0., 0.));
+    }
+
+    #[test]
+    fn normals_under_uniform_scale_just_turn_with_the_view() {
+        let matrices = get_matrices();
+        let applied = apply_model_matrix(&matrices, &Mat4::scaling(Vec3::new(3., 3., 3.)));
+        let normal = Vec3::new(0.2, 0.9, -0.4).normalize();
+        assert_close(
+            transform_normal(&applied, normal),
+            matrices.view.transform_vector(normal),
+        );
+    }
+}
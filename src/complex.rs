@@ -0,0 +1,115 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// ==== complex numbers, just enough for evaluating f(z) ==== //
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re: re, im: im }
+    }
+
+    pub fn from_polar(modulus: f32, arg: f32) -> Self {
+        Self::new(modulus * arg.cos(), modulus * arg.sin())
+    }
+
+    pub fn modulus(&self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    // in (-pi, pi]
+    pub fn arg(&self) -> f32 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn exp(&self) -> Self {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    // principal branch, cut along the negative real axis
+    pub fn ln(&self) -> Self {
+        Self::new(self.modulus().ln(), self.arg())
+    }
+
+    pub fn sqrt(&self) -> Self {
+        Self::from_polar(self.modulus().sqrt(), 0.5 * self.arg())
+    }
+
+    pub fn powc(&self, exponent: Complex) -> Self {
+        if self.re == 0. && self.im == 0. {
+            return if exponent.re == 0. && exponent.im == 0. {
+                Self::new(1., 0.)
+            } else {
+                Self::new(0., 0.)
+            };
+        }
+        (self.ln() * exponent).exp()
+    }
+
+    pub fn sin(&self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(&self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn tan(&self) -> Self {
+        self.sin() / self.cos()
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let denominator = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
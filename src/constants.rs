@@ -1,6 +1,155 @@
 pub const GRID_SIZE: usize = 100;
+pub const GRAPH_3D_COLOR: [f32; 3] = [0.5, 0.5, 0.8]; // slightly bluish surface, unless the surface brings its own colors
+pub const WIREFRAME_COLOR: [f32; 3] = [0.85, 0.9, 1.];
+pub const SCATTER_3D_COLOR: [f32; 3] = [1., 0.8, 0.3]; // for point clouds without their own colours
+pub const SCATTER_3D_POINT_SIZE: f32 = 3.; // px
+pub const LINE_3D_COLOR: [f32; 3] = [0.4, 0.9, 0.6]; // for 3d lines without their own colours
 
+// every viewport's lens starts out with these. see camera.rs
 pub const FIELD_OF_VIEW: f32 = 45. * std::f32::consts::PI / 180.;
 pub const Z_FAR: f32 = 100.; // how far you can see before things are clipped
 pub const Z_NEAR: f32 = 0.1; // clip things nearer than this to the camera
-pub const Z_PLANE: f32 = -2.414213; // related to our 45 deg FOV. -1/tan(pi/8)
+pub const VIEW_TRANSITION_DURATION: f32 = 600.; // ms, for swinging over to a preset view
+
+pub const CLICK_DRAG_TOLERANCE: f32 = 4.; // in px. moving further than this between press and release is a drag
+pub const HOVER_MARKER_SIZE: f32 = 0.08; // in the graph's units
+pub const HOVER_MARKER_COLOR: [f32; 4] = [1., 1., 0.3, 1.];
+pub const MEASURE_COLOR: [f32; 4] = [0.3, 1., 1., 1.]; // measuring points and lines
+
+// ---- simulations ---- //
+pub const SIMULATION_MAX_ELAPSED: f32 = TIMELINE_MAX_WALL_ELAPSED * TIMELINE_MAX_SPEED; // ms, the most timeline time one update can hand in while playing
+
+// ---- wave simulation ---- //
+pub const WAVE_TIME_STEP: f32 = 1. / 240.; // in seconds. fixed, so the sim does not care how often update is called
+pub const WAVE_MAX_COURANT: f32 = 0.7; // c * dt / dx has to stay below 1/sqrt(2) for the 2d scheme to be stable
+pub const WAVE_DEFAULT_SPEED: f32 = 0.6; // in grid units (the grid is 2 units wide) per second
+pub const WAVE_DEFAULT_DAMPING: f32 = 0.4; // per second
+pub const WAVE_MAX_DAMPING: f32 = 100.; // per second. by then a droplet's ring is gone before it's spread a cell
+pub const WAVE_DROPLET_VELOCITY: f32 = -2.; // in grid units per second. negative, a droplet pushes the surface down
+pub const WAVE_DROPLET_RADIUS: f32 = 2.5; // in grid cells
+
+// ---- heat simulation ---- //
+pub const HEAT_TIME_STEP: f32 = 1. / 60.; // in seconds
+pub const HEAT_MAX_EXPLICIT_RATIO: f32 = 0.24; // diffusivity * dt / dx^2 above 1/4 and the explicit scheme blows up
+pub const HEAT_DEFAULT_DIFFUSIVITY: f32 = 0.02; // in grid units^2 per second
+pub const HEAT_MAX_DIFFUSIVITY: f32 = 0.5; // the explicit scheme already takes ~90 sub steps per step at this, any more and the page stalls
+pub const HEAT_IMPULSE_AMPLITUDE: f32 = 0.3;
+pub const HEAT_IMPULSE_RADIUS: f32 = 4.; // in grid cells
+
+// ---- gray-scott reaction diffusion ---- //
+pub const GRAY_SCOTT_TIME_STEP: f32 = 1. / 480.; // in seconds of wall clock per (unit) step of the sim
+pub const GRAY_SCOTT_DEFAULT_FEED: f32 = 0.037;
+pub const GRAY_SCOTT_DEFAULT_KILL: f32 = 0.06;
+pub const GRAY_SCOTT_DEFAULT_DIFFUSION_U: f32 = 0.2097;
+pub const GRAY_SCOTT_DEFAULT_DIFFUSION_V: f32 = 0.105;
+pub const GRAY_SCOTT_MAX_DIFFUSION: f32 = 0.22; // a checkerboard ripple gets multiplied by 1 - 8 diffusion - feed - kill every step, which has to stay above -1
+pub const GRAY_SCOTT_MAX_FEED: f32 = 0.1; // past the edge of the usual parameter maps (about 0.08 for both), and
+pub const GRAY_SCOTT_MAX_KILL: f32 = 0.1; // well inside what the explicit step can take
+pub const GRAY_SCOTT_SEED_RADIUS: usize = 4; // in grid cells
+pub const GRAY_SCOTT_HEIGHT_SCALE: f32 = 0.6;
+
+// ---- terrain ---- //
+pub const TERRAIN_DEFAULT_HEIGHT_SCALE: f32 = 0.35;
+pub const TERRAIN_DEFAULT_TALUS_ANGLE: f32 = 35. * std::f32::consts::PI / 180.;
+pub const TERRAIN_WARP_SEED_OFFSET: u32 = 0x5EED; // warp noise and erosion drops get their own seeds, derived from the terrain's
+pub const TERRAIN_EROSION_SEED_OFFSET: u32 = 0xD209;
+pub const TERRAIN_WARP_OFFSET: f32 = 5.2; // decorrelates the x and z warp, which sample the same noise
+pub const TERRAIN_MAX_OCTAVES: u32 = 16; // by then (lacunarity 2) an octave's bumps are far finer than the grid
+pub const TERRAIN_MAX_HYDRAULIC_DROPLETS: u32 = 200_000; // about 20 per grid point, at most HYDRAULIC_MAX_DROPLET_LIFETIME steps each
+pub const TERRAIN_MAX_THERMAL_ITERATIONS: u32 = 1000;
+pub const HYDRAULIC_MAX_DROPLET_LIFETIME: usize = 30; // in steps (one grid cell each)
+pub const HYDRAULIC_INERTIA: f32 = 0.05; // 0 = drops always go straight downhill, 1 = drops never turn
+pub const HYDRAULIC_SEDIMENT_CAPACITY: f32 = 4.;
+pub const HYDRAULIC_MIN_SEDIMENT_CAPACITY: f32 = 0.0001;
+pub const HYDRAULIC_ERODE_SPEED: f32 = 0.3;
+pub const HYDRAULIC_DEPOSIT_SPEED: f32 = 0.3;
+pub const HYDRAULIC_EVAPORATE_SPEED: f32 = 0.01;
+pub const HYDRAULIC_GRAVITY: f32 = 4.;
+pub const THERMAL_EROSION_RATE: f32 = 0.5;
+
+// ---- parametric surfaces ---- //
+pub const PARAMETRIC_GRID_SIZE: usize = 100; // steps along u and along v
+
+// ---- isosurfaces ---- //
+pub const ISOSURFACE_RESOLUTION: usize = 48; // samples along each axis when the volume comes from an expression
+pub const ISOSURFACE_DEFAULT_EXPRESSION: &str = "x*x + y*y + z*z + 0.3*sin(6*x)*sin(6*z)";
+pub const ISOSURFACE_DEFAULT_ISO_VALUE: f32 = 0.5;
+pub const ISOSURFACE_COLOR: [f32; 3] = [0.45, 0.8, 0.5]; // greenish
+
+// ---- complex functions ---- //
+pub const COMPLEX_DOMAIN_SCALE: f32 = 2.; // the grid covers re and im from -2 to +2
+pub const COMPLEX_HEIGHT_SCALE: f32 = 0.15;
+pub const COMPLEX_MAX_HEIGHT: f32 = 0.8; // poles and zeros of log|f| are clipped here
+pub const COMPLEX_DEFAULT_EXPRESSION: &str = "(z*z - 1) / (z*z + 1)";
+pub const COMPLEX_IMAGE_SIZE: f32 = 0.3; // the flat image's share of the display box, inset in its lower left corner
+
+// ---- contour lines ---- //
+pub const CONTOUR_OFFSET: f32 = 0.005; // lifts the lines off the surface so they don't fight it for the same pixels
+pub const CONTOUR_COLOR: [f32; 4] = [1., 1., 1., 0.9];
+pub const CONTOUR_MAP_COLOR: [f32; 4] = [1., 0.85, 0.3, 0.9];
+
+// ---- slicing plane ---- //
+pub const SLICE_PROFILE_Y_RANGE: f32 = 0.5; // the plane's outline and the profile chart go from -0.5 to +0.5
+pub const SLICE_PLANE_COLOR: [f32; 4] = [0.6, 0.8, 1., 0.5];
+pub const SLICE_PROFILE_COLOR: [f32; 4] = [1., 0.4, 0.3, 1.];
+pub const SLICE_PANEL_WIDTH: f32 = 0.35; // the profile chart's share of the display box, in its upper right corner
+pub const SLICE_PANEL_HEIGHT: f32 = 0.25;
+
+// ---- bar charts ---- //
+pub const BARS_3D_MAX_HEIGHT: f32 = 1.; // the bar furthest from the baseline, in the graph's units
+pub const BARS_3D_PALETTE: [[f32; 3]; 6] = [
+    [0.35, 0.6, 0.9],
+    [0.95, 0.55, 0.25],
+    [0.45, 0.8, 0.45],
+    [0.9, 0.35, 0.4],
+    [0.65, 0.5, 0.85],
+    [0.9, 0.8, 0.35],
+]; // one colour per column, for bars without their own colours
+pub const BARS_3D_BOX_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.6];
+
+// ---- vector fields ---- //
+pub const VECTOR_FIELD_DEFAULT_U: &str = "-z";
+pub const VECTOR_FIELD_DEFAULT_V: &str = "0.3 * x";
+pub const VECTOR_FIELD_DEFAULT_W: &str = "x";
+pub const VECTOR_FIELD_GLYPH_STRIDE: usize = 5; // an arrow on every 5th point of the graph's lattice
+pub const VECTOR_FIELD_STREAMLINE_STRIDE: usize = 20; // a streamline from every 20th point
+pub const VECTOR_FIELD_GLYPH_LENGTH: f32 = 0.09; // of the fastest arrow, in the graph's units
+pub const VECTOR_FIELD_GLYPH_SEGMENTS: usize = 8; // around the arrow
+pub const VECTOR_FIELD_GLYPH_SHAFT_RADIUS: f32 = 0.05; // these three are fractions of the arrow's length
+pub const VECTOR_FIELD_GLYPH_HEAD_RADIUS: f32 = 0.15;
+pub const VECTOR_FIELD_GLYPH_HEAD_LENGTH: f32 = 0.35;
+pub const VECTOR_FIELD_STREAMLINE_STEP: f32 = 0.01; // rk4 time step
+pub const VECTOR_FIELD_STREAMLINE_STEPS: usize = 600;
+pub const VECTOR_FIELD_MIN_MAGNITUDE: f32 = 1e-4; // streamlines stop where the field all but vanishes
+pub const VECTOR_FIELD_STREAMLINE_WIDTH: f32 = 2.; // px
+
+// ---- ode trajectories / attractors ---- //
+pub const ODE_STEP: f32 = 0.005; // rk4's step, and where rk45 starts
+pub const ODE_MIN_STEP: f32 = 1e-5;
+pub const ODE_MAX_STEP: f32 = 0.02; // rk45 could go further, but the lines would get visibly kinked
+pub const ODE_TOLERANCE: f32 = 1e-5; // rk45's error per step, relative to the size of the state
+pub const ODE_DURATION: f32 = 60.; // in the system's time. the animation loops after this
+pub const ODE_TIME_SCALE: f32 = 1.5; // system time per second
+pub const ODE_TRAIL_LENGTH: f32 = 8.; // in the system's time
+pub const ODE_TRAJECTORY_COUNT: usize = 5;
+pub const ODE_PERTURBATION: f32 = 1e-3; // between the starting points of neighbouring trajectories
+pub const ODE_BOX_FILL: f32 = 0.9; // the trajectories reach this far out from the middle of the box
+pub const ODE_LINE_WIDTH: f32 = 2.; // px
+
+// ---- annotations ---- //
+pub const ANNOTATION_COLOR: [f32; 3] = [1., 0.35, 0.25];
+pub const ANNOTATION_PIN_SIZE: f32 = 28.; // px
+pub const ANNOTATION_DEPTH_BIAS: f32 = 0.005; // in the graph's units, towards the camera
+pub const ANNOTATION_OCCLUSION_TOLERANCE: f32 = 0.02; // in the graph's units, for the cpu side visibility check
+
+// ---- scene graph ---- //
+pub const SCENE_MARKER_SIZE: f32 = 0.05; // in the node's units
+
+// ---- timeline ---- //
+pub const TIMELINE_FRAME: f32 = 1000. / 60.; // ms, how far one step moves the playhead
+pub const TIMELINE_MAX_SPEED: f32 = 16.; // timeline ms per wall clock ms
+pub const TIMELINE_MAX_WALL_ELAPSED: f32 = 100.; // ms. a longer gap between frames is a stall (hidden tab, breakpoint), and the rest of it is dropped
+
+// ---- camera paths ---- //
+pub const CAMERA_MIN_ZOOM: f32 = 0.1;
+pub const CAMERA_MAX_ZOOM: f32 = 20.;
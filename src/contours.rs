@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+// a contour line as a chain of points. closed ones loop back to their first point
+pub struct Polyline {
+    pub points: Vec<[f32; 3]>,
+    pub closed: bool,
+}
+
+// ==== marching squares over the height grid (laid out like the y values of the 3d graph) ==== //
+// every cell whose corners straddle the level gets a piece of the contour, which runs between the
+// points where the level crosses the cell's edges. the pieces are then chained into polylines.
+// points are in the grid's coordinates, with y = level
+pub fn extract_contours(heights: &Vec<f32>, n: usize, level: f32) -> Vec<Polyline> {
+    let segments = get_contour_segments(heights, n, level);
+    chain_segments(&segments)
+}
+
+// each segment joins two edge crossings, named by edge id (see edge_id) so that the neighbouring cell
+// comes up with exactly the same endpoint and the chaining doesn't depend on comparing floats
+struct Segment {
+    start: (usize, [f32; 3]),
+    end: (usize, [f32; 3]),
+}
+
+// horizontal edges (x, z)-(x + 1, z) come first, then the vertical ones (x, z)-(x, z + 1)
+fn edge_id(n: usize, x: usize, z: usize, vertical: bool) -> usize {
+    let points_per_row = n + 1;
+    if vertical {
+        n * points_per_row + z * points_per_row + x
+    } else {
+        z * n + x
+    }
+}
+
+fn get_contour_segments(heights: &Vec<f32>, n: usize, level: f32) -> Vec<Segment> {
+    let points_per_row = n + 1;
+    let square_size = 2. / n as f32; // derived from webgl's clip space, which goes from -1 to +1
+    let mut protag: Vec<Segment> = Vec::new();
+
+    for z in 0..n {
+        for x in 0..n {
+            // corners clockwise from the top left, and the edges in the same order starting with the top one
+            let corners = [(x, z), (x + 1, z), (x + 1, z + 1), (x, z + 1)];
+            let values: Vec<f32> = corners
+                .iter()
+                .map(|&(cx, cz)| heights[cz * points_per_row + cx])
+                .collect();
+            let edges = [
+                edge_id(n, x, z, false),
+                edge_id(n, x + 1, z, true),
+                edge_id(n, x, z + 1, false),
+                edge_id(n, x, z, true),
+            ];
+
+            let mut crossings: Vec<(usize, [f32; 3])> = Vec::with_capacity(4);
+            for edge in 0..4 {
+                let (a, b) = (values[edge], values[(edge + 1) % 4]);
+                if (a >= level) == (b >= level) {
+                    continue;
+                }
+                let t = (level - a) / (b - a);
+                let (ax, az) = corners[edge];
+                let (bx, bz) = corners[(edge + 1) % 4];
+                let crossing_x = ax as f32 + t * (bx as f32 - ax as f32);
+                let crossing_z = az as f32 + t * (bz as f32 - az as f32);
+                crossings.push((
+                    edges[edge],
+                    [
+                        -1. + crossing_x * square_size,
+                        level,
+                        -1. + crossing_z * square_size,
+                    ],
+                ));
+            }
+
+            match crossings.len() {
+                2 => protag.push(Segment {
+                    start: crossings[0],
+                    end: crossings[1],
+                }),
+                4 => {
+                    // saddle: top left and bottom right are on one side, the other two on the other.
+                    // the average of the corners stands in for the middle of the cell and decides
+                    // which pair of corners gets cut off
+                    let center = values.iter().sum::<f32>() / 4.;
+                    let (first, second) = if (center >= level) == (values[0] >= level) {
+                        ((0, 1), (2, 3)) // cut off top right and bottom left
+                    } else {
+                        ((3, 0), (1, 2)) // cut off top left and bottom right
+                    };
+                    protag.push(Segment {
+                        start: crossings[first.0],
+                        end: crossings[first.1],
+                    });
+                    protag.push(Segment {
+                        start: crossings[second.0],
+                        end: crossings[second.1],
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    protag
+}
+
+fn chain_segments(segments: &Vec<Segment>) -> Vec<Polyline> {
+    // which segments touch each edge crossing. at most two, one from each cell sharing the edge
+    let mut touching: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        touching
+            .entry(segment.start.0)
+            .or_insert_with(Vec::new)
+            .push(i);
+        touching
+            .entry(segment.end.0)
+            .or_insert_with(Vec::new)
+            .push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut protag: Vec<Polyline> = Vec::new();
+
+    // open lines first, starting from their loose ends (crossings on the grid's border).
+    // whatever is left after that is made of loops
+    let loose_ends: Vec<usize> = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| {
+            touching[&segment.start.0].len() == 1 || touching[&segment.end.0].len() == 1
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let starts = loose_ends.into_iter().chain(0..segments.len());
+
+    for first in starts {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+
+        // walk away from the loose end, if this segment has one
+        let (mut cur_edge, start_point) = if touching[&segments[first].start.0].len() == 1 {
+            (segments[first].end.0, segments[first].start.1)
+        } else {
+            (segments[first].start.0, segments[first].end.1)
+        };
+        let first_edge = if cur_edge == segments[first].end.0 {
+            segments[first].start.0
+        } else {
+            segments[first].end.0
+        };
+        let mut points = vec![start_point, point_on(&segments[first], cur_edge)];
+
+        loop {
+            let next = touching[&cur_edge].iter().cloned().find(|&i| !used[i]);
+            match next {
+                Some(i) => {
+                    used[i] = true;
+                    cur_edge = if segments[i].start.0 == cur_edge {
+                        segments[i].end.0
+                    } else {
+                        segments[i].start.0
+                    };
+                    points.push(point_on(&segments[i], cur_edge));
+                }
+                None => break,
+            }
+        }
+
+        let closed = cur_edge == first_edge && points.len() > 2;
+        if closed {
+            points.pop(); // the last point is the first one again
+        }
+        protag.push(Polyline {
+            points: points,
+            closed: closed,
+        });
+    }
+
+    protag
+}
+
+fn point_on(segment: &Segment, edge: usize) -> [f32; 3] {
+    if segment.start.0 == edge {
+        segment.start.1
+    } else {
+        segment.end.1
+    }
+}
+
+// ---- polylines -> pairs of points for GL::LINES, lifted a little off the surface ---- //
+pub fn get_line_segments(polylines: &Vec<Polyline>, y_offset: f32) -> Vec<f32> {
+    let mut protag: Vec<f32> = Vec::new();
+    for polyline in polylines.iter() {
+        let count = polyline.points.len();
+        let segment_count = if polyline.closed { count } else { count - 1 };
+        for i in 0..segment_count {
+            for point in [polyline.points[i], polyline.points[(i + 1) % count]].iter() {
+                protag.extend_from_slice(&[point[0], point[1] + y_offset, point[2]]);
+            }
+        }
+    }
+    protag
+}
+
+// the same lines flattened onto a 0 to 1 square (grid x becomes x, grid z becomes y, height is dropped),
+// the way the 2d programs lay out their rectangles
+pub fn get_flat_line_segments(polylines: &Vec<Polyline>) -> Vec<f32> {
+    let mut protag: Vec<f32> = Vec::new();
+    for polyline in polylines.iter() {
+        let count = polyline.points.len();
+        let segment_count = if polyline.closed { count } else { count - 1 };
+        for i in 0..segment_count {
+            for point in [polyline.points[i], polyline.points[(i + 1) % count]].iter() {
+                protag.extend_from_slice(&[(point[0] + 1.) / 2., (point[2] + 1.) / 2., 0.]);
+            }
+        }
+    }
+    protag
+}
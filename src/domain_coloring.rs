@@ -0,0 +1,90 @@
+use super::complex::Complex;
+use super::constants::*;
+use super::expression::Expr;
+use wasm_bindgen::prelude::*;
+
+// what the height of the 3d graph shows for a complex function
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComplexHeight {
+    Modulus,    // |f|. zeros sit on the floor, poles shoot up (and get clipped)
+    LogModulus, // log|f|. zeros and poles both become (clipped) spikes, one down and one up
+}
+
+// ==== f(z) over the x-z grid, with z = x + i * z_grid (scaled up to COMPLEX_DOMAIN_SCALE) ==== //
+// returns the heights (laid out like the y values of the 3d graph) and an rgb colour per grid point,
+// with the hue coming from arg(f). the expression can use z and t (seconds)
+pub fn get_domain_coloring(
+    expression: &Expr,
+    n: usize,
+    height_mode: ComplexHeight,
+    time: f32,
+) -> (Vec<f32>, Vec<f32>) {
+    let points_per_row = n + 1;
+    let mut heights: Vec<f32> = vec![0.; points_per_row * points_per_row];
+    let mut colors: Vec<f32> = vec![0.; 3 * points_per_row * points_per_row];
+    let square_size = 2. / n as f32;
+    let t = Complex::new(time / 1000., 0.);
+
+    for grid_z in 0..points_per_row {
+        for grid_x in 0..points_per_row {
+            let i = grid_z * points_per_row + grid_x;
+            let z = Complex::new(
+                COMPLEX_DOMAIN_SCALE * (-1. + grid_x as f32 * square_size),
+                COMPLEX_DOMAIN_SCALE * (-1. + grid_z as f32 * square_size),
+            );
+            let value = expression.eval_complex(&[z, t]);
+
+            let height = match height_mode {
+                ComplexHeight::Modulus => COMPLEX_HEIGHT_SCALE * value.modulus(),
+                ComplexHeight::LogModulus => COMPLEX_HEIGHT_SCALE * value.modulus().ln(),
+            };
+            // poles are infinitely high and 0/0 is nan. neither is much use to webgl
+            heights[i] = if height.is_nan() {
+                0.
+            } else {
+                height.max(-COMPLEX_MAX_HEIGHT).min(COMPLEX_MAX_HEIGHT)
+            };
+
+            colors[3 * i..3 * i + 3].copy_from_slice(&domain_color(value));
+        }
+    }
+
+    (heights, colors)
+}
+
+// ---- the classic domain colouring: hue is the argument, brightness bands mark |f| doubling ---- //
+pub fn domain_color(value: Complex) -> [f32; 3] {
+    let modulus = value.modulus();
+    if !modulus.is_finite() {
+        return [1., 1., 1.]; // poles go white
+    }
+    if modulus == 0. {
+        return [0., 0., 0.]; // zeros go black
+    }
+
+    let hue = (value.arg() + std::f32::consts::PI) / (2. * std::f32::consts::PI);
+    let log_modulus = modulus.log2();
+    let band = log_modulus - log_modulus.floor(); // 0 -> 1 every time |f| doubles
+    let brightness = 0.7 + 0.3 * band;
+
+    hsv_to_rgb(hue, 0.85, brightness)
+}
+
+// hue, saturation and value all in [0, 1]
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [f32; 3] {
+    let sector = (hue - hue.floor()) * 6.;
+    let fraction = sector - sector.floor();
+    let p = value * (1. - saturation);
+    let q = value * (1. - saturation * fraction);
+    let t = value * (1. - saturation * (1. - fraction));
+
+    match sector as usize {
+        0 => [value, t, p],
+        1 => [q, value, p],
+        2 => [p, value, t],
+        3 => [p, q, value],
+        4 => [t, p, value],
+        _ => [value, p, q],
+    }
+}
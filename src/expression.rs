@@ -0,0 +1,384 @@
+use super::complex::Complex;
+
+// ==== tiny expression language for user supplied functions, e.g. "sin(x*x + z*z) - y" ==== //
+// numbers, + - * / ^, parentheses, the constants pi and e, and a handful of functions.
+// the variable names are fixed when compiling, so evaluating is just a walk over the tree
+// with the values looked up by position (no string compares in the hot loop).
+// compile_complex also knows i, and its expressions are meant for eval_complex
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Number(f32),
+    ImaginaryUnit,
+    Variable(usize),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+    Atan2,
+    Pow,
+    Min,
+    Max,
+    Re,
+    Im,
+    Arg,
+    Conj,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Function, usize)> {
+        // (function, how many arguments it takes)
+        match name {
+            "sin" => Some((Function::Sin, 1)),
+            "cos" => Some((Function::Cos, 1)),
+            "tan" => Some((Function::Tan, 1)),
+            "exp" => Some((Function::Exp, 1)),
+            "log" | "ln" => Some((Function::Log, 1)),
+            "sqrt" => Some((Function::Sqrt, 1)),
+            "abs" => Some((Function::Abs, 1)),
+            "atan2" => Some((Function::Atan2, 2)),
+            "pow" => Some((Function::Pow, 2)),
+            "min" => Some((Function::Min, 2)),
+            "max" => Some((Function::Max, 2)),
+            "re" => Some((Function::Re, 1)),
+            "im" => Some((Function::Im, 1)),
+            "arg" => Some((Function::Arg, 1)),
+            "conj" => Some((Function::Conj, 1)),
+            _ => None,
+        }
+    }
+}
+
+impl Expr {
+    // variable_names decides both which names are allowed and where their values sit when evaluating
+    pub fn compile(source: &str, variable_names: &[&str]) -> Result<Expr, String> {
+        Self::compile_with(source, variable_names, false)
+    }
+
+    // like compile, but i is the imaginary unit (so it can't also be a variable name)
+    pub fn compile_complex(source: &str, variable_names: &[&str]) -> Result<Expr, String> {
+        Self::compile_with(source, variable_names, true)
+    }
+
+    fn compile_with(
+        source: &str,
+        variable_names: &[&str],
+        allow_imaginary: bool,
+    ) -> Result<Expr, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: tokens,
+            position: 0,
+            variable_names: variable_names,
+            allow_imaginary: allow_imaginary,
+        };
+        let protag = parser.parse_sum()?;
+        match parser.peek() {
+            None => Ok(protag),
+            Some(token) => Err(format!("unexpected {:?} in expression", token)),
+        }
+    }
+
+    pub fn eval(&self, variables: &[f32]) -> f32 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::ImaginaryUnit => std::f32::NAN, // no real value. only compile_complex lets this in
+            Expr::Variable(index) => variables[*index],
+            Expr::Negate(inner) => -inner.eval(variables),
+            Expr::Binary(op, a, b) => {
+                let a = a.eval(variables);
+                let b = b.eval(variables);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Subtract => a - b,
+                    BinaryOp::Multiply => a * b,
+                    BinaryOp::Divide => a / b,
+                    BinaryOp::Power => a.powf(b),
+                }
+            }
+            Expr::Call(function, arguments) => {
+                let a = arguments[0].eval(variables);
+                match function {
+                    Function::Sin => a.sin(),
+                    Function::Cos => a.cos(),
+                    Function::Tan => a.tan(),
+                    Function::Exp => a.exp(),
+                    Function::Log => a.ln(),
+                    Function::Sqrt => a.sqrt(),
+                    Function::Abs => a.abs(),
+                    Function::Atan2 => a.atan2(arguments[1].eval(variables)),
+                    Function::Pow => a.powf(arguments[1].eval(variables)),
+                    Function::Min => a.min(arguments[1].eval(variables)),
+                    Function::Max => a.max(arguments[1].eval(variables)),
+                    Function::Re | Function::Conj => a,
+                    Function::Im => 0.,
+                    Function::Arg => 0f32.atan2(a),
+                }
+            }
+        }
+    }
+
+    pub fn eval_complex(&self, variables: &[Complex]) -> Complex {
+        match self {
+            Expr::Number(value) => Complex::new(*value, 0.),
+            Expr::ImaginaryUnit => Complex::new(0., 1.),
+            Expr::Variable(index) => variables[*index],
+            Expr::Negate(inner) => -inner.eval_complex(variables),
+            Expr::Binary(op, a, b) => {
+                let a = a.eval_complex(variables);
+                let b = b.eval_complex(variables);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Subtract => a - b,
+                    BinaryOp::Multiply => a * b,
+                    BinaryOp::Divide => a / b,
+                    BinaryOp::Power => a.powc(b),
+                }
+            }
+            Expr::Call(function, arguments) => {
+                let a = arguments[0].eval_complex(variables);
+                let real = |value: f32| Complex::new(value, 0.);
+                match function {
+                    Function::Sin => a.sin(),
+                    Function::Cos => a.cos(),
+                    Function::Tan => a.tan(),
+                    Function::Exp => a.exp(),
+                    Function::Log => a.ln(),
+                    Function::Sqrt => a.sqrt(),
+                    Function::Abs => real(a.modulus()),
+                    Function::Pow => a.powc(arguments[1].eval_complex(variables)),
+                    // these only make sense for real numbers, so they look at the real parts
+                    Function::Atan2 => real(a.re.atan2(arguments[1].eval_complex(variables).re)),
+                    Function::Min => real(a.re.min(arguments[1].eval_complex(variables).re)),
+                    Function::Max => real(a.re.max(arguments[1].eval_complex(variables).re)),
+                    Function::Re => real(a.re),
+                    Function::Im => real(a.im),
+                    Function::Arg => real(a.arg()),
+                    Function::Conj => a.conj(),
+                }
+            }
+        }
+    }
+}
+
+// ---- tokens ---- //
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Number(f32),
+    Identifier(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+    Comma,
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut protag: Vec<Token> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // exponent, e.g. 1e-3. only if a digit follows, so "2e" still reads as 2 * e
+            if i + 1 < chars.len()
+                && (chars[i] == 'e' || chars[i] == 'E')
+                && (chars[i + 1].is_ascii_digit()
+                    || ((chars[i + 1] == '-' || chars[i + 1] == '+')
+                        && i + 2 < chars.len()
+                        && chars[i + 2].is_ascii_digit()))
+            {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse::<f32>()
+                .map_err(|_| format!("{} is not a number", text))?;
+            protag.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            protag.push(Token::Identifier(chars[start..i].iter().collect()));
+        } else {
+            protag.push(match c {
+                '+' | '-' | '*' | '/' | '^' => Token::Operator(c),
+                '(' => Token::OpenParen,
+                ')' => Token::CloseParen,
+                ',' => Token::Comma,
+                _ => return Err(format!("unexpected character '{}' in expression", c)),
+            });
+            i += 1;
+        }
+    }
+
+    Ok(protag)
+}
+
+// ---- recursive descent, one function per precedence level ---- //
+//   sum     = product (('+' | '-') product)*
+//   product = unary (('*' | '/') unary)*
+//   unary   = ('-' | '+') unary | power
+//   power   = atom ('^' unary)?            right associative, and -x^2 is -(x^2)
+//   atom    = number | name | name '(' sum (',' sum)* ')' | '(' sum ')'
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    variable_names: &'a [&'a str],
+    allow_imaginary: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?} but found {:?}", expected, token)),
+            None => Err(format!("expected {:?} but the expression ended", expected)),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, String> {
+        let mut protag = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Operator('+')) => BinaryOp::Add,
+                Some(Token::Operator('-')) => BinaryOp::Subtract,
+                _ => return Ok(protag),
+            };
+            self.next();
+            protag = Expr::Binary(op, Box::new(protag), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, String> {
+        let mut protag = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Operator('*')) => BinaryOp::Multiply,
+                Some(Token::Operator('/')) => BinaryOp::Divide,
+                _ => return Ok(protag),
+            };
+            self.next();
+            protag = Expr::Binary(op, Box::new(protag), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.next();
+                Ok(Expr::Negate(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Operator('+')) => {
+                self.next();
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    fn parse_power(&mut self) -> Result<Expr, String> {
+        let base = self.parse_atom()?;
+        if let Some(Token::Operator('^')) = self.peek() {
+            self.next();
+            let exponent = self.parse_unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::OpenParen) => {
+                let inner = self.parse_sum()?;
+                self.expect(Token::CloseParen)?;
+                Ok(inner)
+            }
+            Some(Token::Identifier(name)) => {
+                if let Some(Token::OpenParen) = self.peek() {
+                    return self.parse_call(&name);
+                }
+                if let Some(index) = self.variable_names.iter().position(|v| *v == name) {
+                    return Ok(Expr::Variable(index));
+                }
+                match name.as_str() {
+                    "i" if self.allow_imaginary => Ok(Expr::ImaginaryUnit),
+                    "pi" => Ok(Expr::Number(std::f32::consts::PI)),
+                    "e" => Ok(Expr::Number(std::f32::consts::E)),
+                    _ => Err(format!(
+                        "unknown name {}. variables are: {}",
+                        name,
+                        self.variable_names.join(", ")
+                    )),
+                }
+            }
+            Some(token) => Err(format!("unexpected {:?} in expression", token)),
+            None => Err(String::from("expression ended too early")),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> Result<Expr, String> {
+        let (function, argument_count) =
+            Function::from_name(name).ok_or_else(|| format!("unknown function {}", name))?;
+        self.expect(Token::OpenParen)?;
+        let mut arguments = vec![self.parse_sum()?];
+        while let Some(Token::Comma) = self.peek() {
+            self.next();
+            arguments.push(self.parse_sum()?);
+        }
+        self.expect(Token::CloseParen)?;
+
+        if arguments.len() != argument_count {
+            return Err(format!(
+                "{} takes {} argument(s), got {}",
+                name,
+                argument_count,
+                arguments.len()
+            ));
+        }
+        Ok(Expr::Call(function, arguments))
+    }
+}
@@ -0,0 +1,280 @@
+use super::expression::Expr;
+
+// ==== a box of scalar samples filling the -1 to +1 cube the 3d graph lives in ==== //
+// samples are laid out x fastest, then y, then z: index = (z * size_y + y) * size_x + x
+pub struct ScalarVolume {
+    pub size_x: usize,
+    pub size_y: usize,
+    pub size_z: usize,
+    pub values: Vec<f32>,
+}
+
+// indexed triangles, ready for the mesh program. 3 floats per position/normal, 3 indices per triangle
+pub struct Mesh {
+    pub positions: Vec<f32>,
+    pub normals: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl ScalarVolume {
+    // samples f(x, y, z) on a resolution^3 lattice
+    pub fn from_expression(expression: &Expr, resolution: usize) -> Self {
+        let mut values: Vec<f32> = Vec::with_capacity(resolution * resolution * resolution);
+        let step = 2. / (resolution - 1) as f32;
+        for z in 0..resolution {
+            for y in 0..resolution {
+                for x in 0..resolution {
+                    values.push(expression.eval(&[
+                        -1. + x as f32 * step,
+                        -1. + y as f32 * step,
+                        -1. + z as f32 * step,
+                    ]));
+                }
+            }
+        }
+
+        Self {
+            size_x: resolution,
+            size_y: resolution,
+            size_z: resolution,
+            values: values,
+        }
+    }
+
+    // e.g. a ct scan or simulation output loaded on the js side
+    pub fn from_voxels(
+        values: Vec<f32>,
+        size_x: usize,
+        size_y: usize,
+        size_z: usize,
+    ) -> Result<Self, String> {
+        if size_x < 2 || size_y < 2 || size_z < 2 {
+            return Err(String::from(
+                "a volume needs at least 2 samples along each axis",
+            ));
+        }
+        if values.len() != size_x * size_y * size_z {
+            return Err(format!(
+                "expected {} x {} x {} = {} voxels, got {}",
+                size_x,
+                size_y,
+                size_z,
+                size_x * size_y * size_z,
+                values.len()
+            ));
+        }
+
+        Ok(Self {
+            size_x: size_x,
+            size_y: size_y,
+            size_z: size_z,
+            values: values,
+        })
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.values[(z * self.size_y + y) * self.size_x + x]
+    }
+
+    // lattice coords -> the -1 to +1 cube
+    fn to_world(&self, x: f32, y: f32, z: f32) -> [f32; 3] {
+        [
+            -1. + 2. * x / (self.size_x - 1) as f32,
+            -1. + 2. * y / (self.size_y - 1) as f32,
+            -1. + 2. * z / (self.size_z - 1) as f32,
+        ]
+    }
+
+    // trilinear interpolation at fractional lattice coords
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        let clamp = |v: f32, size: usize| v.max(0.).min((size - 1) as f32);
+        let (x, y, z) = (
+            clamp(x, self.size_x),
+            clamp(y, self.size_y),
+            clamp(z, self.size_z),
+        );
+        let x0 = (x as usize).min(self.size_x - 2);
+        let y0 = (y as usize).min(self.size_y - 2);
+        let z0 = (z as usize).min(self.size_z - 2);
+        let (fx, fy, fz) = (x - x0 as f32, y - y0 as f32, z - z0 as f32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + t * (b - a);
+        let along_x = |y: usize, z: usize| lerp(self.value(x0, y, z), self.value(x0 + 1, y, z), fx);
+        lerp(
+            lerp(along_x(y0, z0), along_x(y0 + 1, z0), fy),
+            lerp(along_x(y0, z0 + 1), along_x(y0 + 1, z0 + 1), fy),
+            fz,
+        )
+    }
+
+    // direction the values grow fastest in, at fractional lattice coords. normalized, so it works as a normal
+    fn gradient(&self, x: f32, y: f32, z: f32) -> [f32; 3] {
+        let h = 0.5;
+        let mut protag = [
+            self.sample(x + h, y, z) - self.sample(x - h, y, z),
+            self.sample(x, y + h, z) - self.sample(x, y - h, z),
+            self.sample(x, y, z + h) - self.sample(x, y, z - h),
+        ];
+        // the lattice isn't necessarily the same resolution along every axis
+        protag[0] *= (self.size_x - 1) as f32;
+        protag[1] *= (self.size_y - 1) as f32;
+        protag[2] *= (self.size_z - 1) as f32;
+
+        let size = (protag[0] * protag[0] + protag[1] * protag[1] + protag[2] * protag[2]).sqrt();
+        if size < 1e-12 {
+            return [0., 1., 0.];
+        }
+        [protag[0] / size, protag[1] / size, protag[2] / size]
+    }
+}
+
+// the 8 corners of a lattice cell, as offsets, and the 12 edges between them (as corner pairs)
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+// ==== the surface where the volume equals iso_value ==== //
+// dual contouring with surface nets vertex placement: every cell the surface passes through gets one vertex,
+// at the average of the points where the surface crosses the cell's edges (instead of solving a qef,
+// which keeps it simple and still gives a smooth mesh). then every lattice edge the surface crosses
+// is surrounded by 4 cells, and their 4 vertices make the quad (2 triangles) that plugs that crossing.
+// normals come from the volume's gradient, not the triangles, so the shading is smooth too
+pub fn extract_isosurface(volume: &ScalarVolume, iso_value: f32) -> Mesh {
+    let cells_x = volume.size_x - 1;
+    let cells_y = volume.size_y - 1;
+    let cells_z = volume.size_z - 1;
+    let cell_index = |x: usize, y: usize, z: usize| (z * cells_y + y) * cells_x + x;
+    let no_vertex = u32::max_value();
+
+    let mut protag = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        indices: Vec::new(),
+    };
+    let mut cell_vertices: Vec<u32> = vec![no_vertex; cells_x * cells_y * cells_z];
+
+    // ---- one vertex per cell with a crossing ---- //
+    for z in 0..cells_z {
+        for y in 0..cells_y {
+            for x in 0..cells_x {
+                let mut corner_values = [0.; 8];
+                let mut inside_count = 0;
+                for (i, corner) in CORNERS.iter().enumerate() {
+                    corner_values[i] = volume.value(x + corner[0], y + corner[1], z + corner[2]);
+                    if corner_values[i] < iso_value {
+                        inside_count += 1;
+                    }
+                }
+                if inside_count == 0 || inside_count == 8 {
+                    continue; // the surface doesn't pass through this cell
+                }
+
+                let mut sum = [0.; 3];
+                let mut crossing_count = 0.;
+                for edge in EDGES.iter() {
+                    let a = corner_values[edge[0]];
+                    let b = corner_values[edge[1]];
+                    if (a < iso_value) == (b < iso_value) {
+                        continue;
+                    }
+                    let t = (iso_value - a) / (b - a);
+                    for axis in 0..3 {
+                        let from = CORNERS[edge[0]][axis] as f32;
+                        let to = CORNERS[edge[1]][axis] as f32;
+                        sum[axis] += from + t * (to - from);
+                    }
+                    crossing_count += 1.;
+                }
+
+                let lattice_x = x as f32 + sum[0] / crossing_count;
+                let lattice_y = y as f32 + sum[1] / crossing_count;
+                let lattice_z = z as f32 + sum[2] / crossing_count;
+
+                cell_vertices[cell_index(x, y, z)] = (protag.positions.len() / 3) as u32;
+                protag
+                    .positions
+                    .extend_from_slice(&volume.to_world(lattice_x, lattice_y, lattice_z));
+                protag
+                    .normals
+                    .extend_from_slice(&volume.gradient(lattice_x, lattice_y, lattice_z));
+            }
+        }
+    }
+
+    // ---- one quad per crossed lattice edge ---- //
+    // an edge from sample p along axis a is shared by the cells at p, p - b, p - c and p - b - c,
+    // where b and c are the other two axes. edges on the outer faces of the volume don't have all 4
+    let sizes = [volume.size_x, volume.size_y, volume.size_z];
+    for z in 0..volume.size_z {
+        for y in 0..volume.size_y {
+            for x in 0..volume.size_x {
+                let p = [x, y, z];
+                let start_inside = volume.value(x, y, z) < iso_value;
+
+                for axis in 0..3 {
+                    let b = (axis + 1) % 3;
+                    let c = (axis + 2) % 3;
+                    if p[axis] + 1 >= sizes[axis] || p[b] == 0 || p[c] == 0 {
+                        continue;
+                    }
+                    if p[b] >= sizes[b] - 1 || p[c] >= sizes[c] - 1 {
+                        continue;
+                    }
+                    let mut end = p;
+                    end[axis] += 1;
+                    let end_inside = volume.value(end[0], end[1], end[2]) < iso_value;
+                    if start_inside == end_inside {
+                        continue;
+                    }
+
+                    let vertex_of = |minus_b: usize, minus_c: usize| {
+                        let mut cell = p;
+                        cell[b] -= minus_b;
+                        cell[c] -= minus_c;
+                        cell_vertices[cell_index(cell[0], cell[1], cell[2])]
+                    };
+                    let quad = [
+                        vertex_of(1, 1),
+                        vertex_of(0, 1),
+                        vertex_of(0, 0),
+                        vertex_of(1, 0),
+                    ];
+
+                    // keep the winding facing from inside to outside, whichever way round the edge crosses
+                    if start_inside {
+                        protag.indices.extend_from_slice(&[
+                            quad[0], quad[1], quad[2], quad[0], quad[2], quad[3],
+                        ]);
+                    } else {
+                        protag.indices.extend_from_slice(&[
+                            quad[0], quad[2], quad[1], quad[0], quad[3], quad[2],
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    protag
+}
@@ -6,12 +6,45 @@ use web_sys::*;
 #[macro_use]
 extern crate lazy_static;
 
+mod annotations;
 mod app_state;
+mod bar_chart;
+mod camera;
+mod camera_path;
 mod common_funcs;
+mod complex;
 mod constants;
+mod contours;
+mod domain_coloring;
+mod expression;
 mod gl_setup;
+mod isosurface;
+mod math;
+mod measurement;
+mod ode;
+mod parametric;
 mod programs;
+mod random;
+mod scene;
 mod shaders;
+mod simulations;
+mod slicing;
+mod terrain;
+mod timeline;
+mod vector_field;
+
+pub use app_state::{SurfaceMode, ViewportLayout};
+pub use camera::{Projection, ViewPreset};
+pub use camera_path::Easing;
+pub use domain_coloring::ComplexHeight;
+use math::Mat4;
+pub use measurement::MeasureTool;
+pub use ode::{Attractor, OdeMethod};
+pub use parametric::ParametricSurface;
+pub use programs::{LineCap, PickObject, RenderMode};
+pub use simulations::Boundary;
+use simulations::Simulation;
+pub use terrain::{FractalKind, NoiseKind, TerrainSettings};
 
 #[wasm_bindgen]
 extern "C" {
@@ -22,9 +55,45 @@ extern "C" {
 #[wasm_bindgen]
 pub struct Client {
     gl: WebGlRenderingContext,
+    program_bars_3d: programs::Bars3d,
+    program_billboards_3d: programs::Billboards3d,
     program_color_2d: programs::Color2D,
-    _program_color_2d_gradient: programs::Color2DGradient,
+    program_color_2d_grid: programs::Color2DGrid,
+    program_color_2d_gradient: programs::Color2DGradient,
+    program_glyphs_3d: programs::Glyphs3d,
     program_graph_3d: programs::Graph3d,
+    program_line_3d: programs::Line3d,
+    program_lines: programs::Lines,
+    program_parametric_3d: programs::Parametric3d,
+    program_picker: programs::Picker,
+    program_mesh_3d: programs::Mesh3d,
+    program_scatter_3d: programs::Scatter3d,
+    program_streamlines: programs::Line3d,
+    program_trajectories: programs::Line3d,
+    simulation: Option<Box<dyn Simulation>>,
+    simulation_clock: simulations::FixedStepClock,
+    timeline: timeline::Timeline,
+    camera_path: camera_path::CameraPath,
+    camera_path_viewport: Option<usize>, // the viewport following the camera path, while it plays
+    terrain_heights: Vec<f32>,
+    volume: Option<isosurface::ScalarVolume>,
+    isosurface_mesh: Option<isosurface::Mesh>,
+    iso_value: f32,
+    complex_expression: Option<expression::Expr>,
+    complex_height: ComplexHeight,
+    contour_levels: Vec<f32>,
+    show_contour_map: bool,
+    bars_box_segments: Vec<f32>,
+    ode_system: Option<ode::OdeSystem>,
+    ode_initial: [f32; 3],
+    ode_method: OdeMethod,
+    trajectories: Vec<ode::Trajectory>,
+    slice_plane: Option<slicing::SlicePlane>,
+    measure_tool: MeasureTool,
+    measure_points: Vec<(f32, f32)>,
+    measure_path: Option<measurement::SurfacePath>, // the distance tool's path along the surface, see update_measure_path
+    annotations: annotations::AnnotationStore,
+    scene: scene::Scene,
 }
 
 #[wasm_bindgen]
@@ -34,24 +103,1568 @@ impl Client {
         console_error_panic_hook::set_once();
         let gl = gl_setup::initialize_webgl_context().unwrap();
         Self {
+            program_bars_3d: programs::Bars3d::new(&gl),
+            program_billboards_3d: programs::Billboards3d::new(&gl),
             program_color_2d: programs::Color2D::new(&gl),
-            _program_color_2d_gradient: programs::Color2DGradient::new(&gl),
+            program_color_2d_grid: programs::Color2DGrid::new(&gl, constants::GRID_SIZE),
+            program_color_2d_gradient: programs::Color2DGradient::new(&gl),
+            program_glyphs_3d: programs::Glyphs3d::new(&gl),
             program_graph_3d: programs::Graph3d::new(&gl),
+            program_line_3d: programs::Line3d::new(&gl),
+            program_lines: programs::Lines::new(&gl),
+            program_parametric_3d: programs::Parametric3d::new(&gl),
+            program_picker: programs::Picker::new(&gl),
+            program_mesh_3d: programs::Mesh3d::new(&gl),
+            program_scatter_3d: programs::Scatter3d::new(&gl),
+            program_streamlines: programs::Line3d::new(&gl),
+            program_trajectories: programs::Line3d::new(&gl),
+            simulation: None,
+            simulation_clock: simulations::FixedStepClock::new(),
+            timeline: timeline::Timeline::new(),
+            camera_path: camera_path::CameraPath::new(),
+            camera_path_viewport: None,
+            terrain_heights: Vec::new(),
+            volume: None,
+            isosurface_mesh: None,
+            iso_value: constants::ISOSURFACE_DEFAULT_ISO_VALUE,
+            complex_expression: None,
+            complex_height: ComplexHeight::LogModulus,
+            contour_levels: Vec::new(),
+            show_contour_map: false,
+            bars_box_segments: Vec::new(),
+            ode_system: None,
+            ode_initial: [0., 0., 0.],
+            ode_method: OdeMethod::Rk45,
+            trajectories: Vec::new(),
+            slice_plane: None,
+            measure_tool: MeasureTool::Off,
+            measure_points: Vec::new(),
+            measure_path: None,
+            annotations: annotations::AnnotationStore::new(),
+            scene: scene::Scene::new(),
             gl: gl,
         }
     }
 
+    // time is the wall clock. what the graph animates by is the timeline's (see timeline.rs)
     pub fn update(&mut self, time: f32, height: f32, width: f32) -> Result<(), JsValue> {
-        app_state::update_dynamic_data(time, height, width);
+        let elapsed = self.timeline.advance_to(time);
+        app_state::update_dynamic_data(time, self.timeline.get_time(), height, width);
+        self.follow_camera_path();
+
+        // clicks measure while a measuring tool is on, otherwise they poke the simulation (if any)
+        if let Some((click_x, click_y)) = app_state::take_pending_click() {
+            if self.measure_tool != MeasureTool::Off {
+                self.add_measure_point_at_canvas_point(click_x, click_y);
+            } else if self.simulation.is_some() {
+                self.add_impulse_at_canvas_point(click_x, click_y);
+            }
+        }
+        if let Some(simulation) = self.simulation.as_mut() {
+            self.simulation_clock
+                .advance_by(elapsed, simulation.as_mut());
+        }
+
+        // the trajectories grow with time, and start over once they run out
+        if app_state::get_cur_state().surface_mode == SurfaceMode::Ode {
+            let system_time = (self.timeline.get_time() / 1000. * constants::ODE_TIME_SCALE)
+                % constants::ODE_DURATION;
+            self.program_trajectories.set_polylines(
+                &self.gl,
+                &ode::get_trails(&self.trajectories, system_time),
+                constants::ODE_LINE_WIDTH,
+                0.,
+                0.,
+                LineCap::Round,
+            );
+        }
+
+        self.update_measure_path();
+        Ok(())
+    }
+
+    // switching mode starts the new simulation (if any) from scratch, with default parameters.
+    // the modes that need a webgl extension the browser doesn't have stay switched off, with an error
+    pub fn set_surface_mode(&mut self, surface_mode: SurfaceMode) -> Result<(), JsValue> {
+        match surface_mode {
+            SurfaceMode::Bars => self.program_bars_3d.load_extension(&self.gl)?,
+            SurfaceMode::VectorField => self.program_glyphs_3d.load_extension(&self.gl)?,
+            SurfaceMode::Isosurface => self.program_mesh_3d.load_extension(&self.gl)?,
+            _ => {}
+        }
+        app_state::update_surface_mode(surface_mode);
+        let n = constants::GRID_SIZE;
+        if surface_mode == SurfaceMode::Terrain && self.terrain_heights.is_empty() {
+            self.terrain_heights = terrain::generate_terrain(n, &TerrainSettings::new());
+        }
+        if surface_mode == SurfaceMode::Isosurface && self.volume.is_none() {
+            self.set_isosurface_expression(constants::ISOSURFACE_DEFAULT_EXPRESSION)?;
+        }
+        if surface_mode == SurfaceMode::VectorField && self.program_glyphs_3d.glyph_count == 0 {
+            self.set_vector_field(
+                constants::VECTOR_FIELD_DEFAULT_U,
+                constants::VECTOR_FIELD_DEFAULT_V,
+                constants::VECTOR_FIELD_DEFAULT_W,
+            )?;
+        }
+        if surface_mode == SurfaceMode::Ode && self.ode_system.is_none() {
+            self.set_attractor(Attractor::Lorenz);
+        }
+        if surface_mode == SurfaceMode::Complex && self.complex_expression.is_none() {
+            self.set_complex_expression(constants::COMPLEX_DEFAULT_EXPRESSION)
+                .unwrap();
+        }
+        self.simulation = match surface_mode {
+            SurfaceMode::Analytic
+            | SurfaceMode::Terrain
+            | SurfaceMode::Parametric
+            | SurfaceMode::Isosurface
+            | SurfaceMode::Complex
+            | SurfaceMode::Bars
+            | SurfaceMode::VectorField
+            | SurfaceMode::Ode => None,
+            SurfaceMode::Wave => Some(Box::new(simulations::WaveSimulation::new(n))),
+            SurfaceMode::HeatExplicit => Some(Box::new(simulations::HeatSimulation::new(
+                n,
+                simulations::HeatScheme::Explicit,
+            ))),
+            SurfaceMode::HeatImplicit => Some(Box::new(simulations::HeatSimulation::new(
+                n,
+                simulations::HeatScheme::Adi,
+            ))),
+            SurfaceMode::ReactionDiffusion => {
+                Some(Box::new(simulations::ReactionDiffusionSimulation::new(n)))
+            }
+        };
+        self.simulation_clock.reset();
+        Ok(())
+    }
+
+    pub fn get_surface_mode(&self) -> SurfaceMode {
+        app_state::get_cur_state().surface_mode
+    }
+
+    // ---- simulation knobs. which names exist depends on the current surface mode ---- //
+    pub fn set_simulation_parameter(&mut self, name: &str, value: f32) -> Result<(), JsValue> {
+        match self.simulation.as_mut() {
+            Some(simulation) => simulation.set_parameter(name, value).map_err(JsValue::from),
+            None => Err(JsValue::from("current surface mode is not a simulation")),
+        }
+    }
+
+    pub fn get_simulation_parameter(&self, name: &str) -> Option<f32> {
+        self.simulation
+            .as_ref()
+            .and_then(|simulation| simulation.get_parameter(name))
+    }
+
+    pub fn set_simulation_boundary(&mut self, boundary: Boundary) {
+        if let Some(simulation) = self.simulation.as_mut() {
+            simulation.set_boundary(boundary);
+        }
+    }
+
+    pub fn reset_simulation(&mut self) {
+        if let Some(simulation) = self.simulation.as_mut() {
+            simulation.reset();
+        }
+        self.simulation_clock.reset();
+    }
+
+    // ---- the timeline everything animates by. times in ms ---- //
+    pub fn play(&mut self) {
+        self.timeline.play();
+    }
+
+    pub fn pause(&mut self) {
+        self.timeline.pause();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.timeline.is_playing()
+    }
+
+    // pauses, and moves a whole number of frames on (or back, for negative frames)
+    pub fn step_frames(&mut self, frames: i32) {
+        self.timeline.step(frames);
+    }
+
+    // 1 is real time, 0.5 half speed and so on
+    pub fn set_playback_speed(&mut self, speed: f32) -> Result<(), JsValue> {
+        self.timeline.set_speed(speed).map_err(JsValue::from)
+    }
+
+    pub fn get_playback_speed(&self) -> f32 {
+        self.timeline.get_speed()
+    }
+
+    pub fn set_loop_range(&mut self, start: f32, end: f32) -> Result<(), JsValue> {
+        self.timeline
+            .set_loop_range(start, end)
+            .map_err(JsValue::from)
+    }
+
+    pub fn clear_loop_range(&mut self) {
+        self.timeline.clear_loop_range();
+    }
+
+    // [start, end], or empty if it isn't looping
+    pub fn get_loop_range(&self) -> Vec<f32> {
+        match self.timeline.get_loop_range() {
+            Some((start, end)) => vec![start, end],
+            None => Vec::new(),
+        }
+    }
+
+    // the surfaces jump there. simulations carry on from where they are, they can't be rewound
+    pub fn seek(&mut self, time: f32) -> Result<(), JsValue> {
+        self.timeline.seek(time).map_err(JsValue::from)
+    }
+
+    pub fn get_playhead(&self) -> f32 {
+        self.timeline.get_time()
+    }
+
+    // shows a parametric surface instead of the height field
+    pub fn set_parametric_surface(&mut self, parametric_surface: ParametricSurface) {
+        app_state::update_parametric_surface(parametric_surface);
+        self.set_surface_mode(SurfaceMode::Parametric).unwrap();
+    }
+
+    // builds a new terrain from the settings and shows it. same settings, same terrain
+    pub fn generate_terrain(&mut self, settings: &TerrainSettings) {
+        self.terrain_heights = terrain::generate_terrain(constants::GRID_SIZE, settings);
+        self.set_surface_mode(SurfaceMode::Terrain).unwrap();
+    }
+
+    // ---- isosurfaces, f(x, y, z) = iso value ---- //
+    // the expression can use x, y and z, each running from -1 to +1
+    pub fn set_isosurface_expression(&mut self, expression: &str) -> Result<(), JsValue> {
+        let expression = expression::Expr::compile(expression, &["x", "y", "z"])?;
+        self.program_mesh_3d.load_extension(&self.gl)?;
+        self.volume = Some(isosurface::ScalarVolume::from_expression(
+            &expression,
+            constants::ISOSURFACE_RESOLUTION,
+        ));
+        self.update_isosurface_mesh();
+        app_state::update_surface_mode(SurfaceMode::Isosurface);
+        self.simulation = None;
+        Ok(())
+    }
+
+    // voxels laid out x fastest, then y, then z. the volume is stretched over the -1 to +1 cube
+    pub fn load_volume(
+        &mut self,
+        values: Vec<f32>,
+        size_x: usize,
+        size_y: usize,
+        size_z: usize,
+    ) -> Result<(), JsValue> {
+        self.program_mesh_3d.load_extension(&self.gl)?;
+        self.volume = Some(isosurface::ScalarVolume::from_voxels(
+            values, size_x, size_y, size_z,
+        )?);
+        self.update_isosurface_mesh();
+        app_state::update_surface_mode(SurfaceMode::Isosurface);
+        self.simulation = None;
+        Ok(())
+    }
+
+    pub fn set_iso_value(&mut self, iso_value: f32) {
+        self.iso_value = iso_value;
+        self.update_isosurface_mesh();
+    }
+
+    pub fn get_iso_value(&self) -> f32 {
+        self.iso_value
+    }
+
+    // [min, max] of the loaded volume, handy for the range of an iso value slider
+    pub fn get_volume_range(&self) -> Vec<f32> {
+        match self.volume.as_ref() {
+            Some(volume) => vec![
+                volume
+                    .values
+                    .iter()
+                    .cloned()
+                    .fold(std::f32::INFINITY, f32::min),
+                volume
+                    .values
+                    .iter()
+                    .cloned()
+                    .fold(std::f32::NEG_INFINITY, f32::max),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn update_isosurface_mesh(&mut self) {
+        if let Some(volume) = self.volume.as_ref() {
+            let mesh = isosurface::extract_isosurface(volume, self.iso_value);
+            self.program_mesh_3d.set_mesh(&self.gl, &mesh);
+            self.isosurface_mesh = Some(mesh); // kept for picking
+        }
+    }
+
+    // ---- complex functions f(z), domain coloured ---- //
+    // the expression can use z (the grid, from -2-2i to 2+2i), t (seconds) and i
+    pub fn set_complex_expression(&mut self, expression: &str) -> Result<(), JsValue> {
+        self.complex_expression = Some(expression::Expr::compile_complex(expression, &["z", "t"])?);
+        app_state::update_surface_mode(SurfaceMode::Complex);
+        self.simulation = None;
+        Ok(())
+    }
+
+    pub fn set_complex_height(&mut self, complex_height: ComplexHeight) {
+        self.complex_height = complex_height;
+    }
+
+    // ---- 3d bar charts ---- //
+    // values row by row, rows along z and columns along x. colors are rgb per bar, or empty to colour
+    // by column. gap is the fraction of each cell between bars (0 to 1), and bars grow up or down
+    // from the baseline value
+    pub fn set_bar_data(
+        &mut self,
+        values: Vec<f32>,
+        rows: usize,
+        columns: usize,
+        colors: Vec<f32>,
+        gap: f32,
+        baseline: f32,
+    ) -> Result<(), JsValue> {
+        let instances =
+            bar_chart::get_bar_instances(&values, rows, columns, &colors, gap, baseline)?;
+        self.program_bars_3d.load_extension(&self.gl)?;
+        self.program_bars_3d.set_bars(&self.gl, &instances);
+        self.bars_box_segments = bar_chart::get_bounding_box_segments(&instances);
+        app_state::update_surface_mode(SurfaceMode::Bars);
+        self.simulation = None;
+        Ok(())
+    }
+
+    // ---- vector fields: arrows over the graph's lattice, plus streamlines ---- //
+    // u, v and w are the x, y and z parts of the vector, as expressions of x, y and z (each -1 to +1)
+    pub fn set_vector_field(&mut self, u: &str, v: &str, w: &str) -> Result<(), JsValue> {
+        let field = vector_field::VectorField::compile(u, v, w)?;
+        self.program_glyphs_3d.load_extension(&self.gl)?;
+        let n = constants::GRID_SIZE;
+
+        let glyph_points = vector_field::get_sample_points(n, constants::VECTOR_FIELD_GLYPH_STRIDE);
+        self.program_glyphs_3d.set_glyphs(
+            &self.gl,
+            &vector_field::get_glyph_instances(&field, &glyph_points),
+        );
+
+        let seeds = vector_field::get_sample_points(n, constants::VECTOR_FIELD_STREAMLINE_STRIDE);
+        self.program_streamlines.set_polylines(
+            &self.gl,
+            &vector_field::get_streamlines(&field, &seeds, constants::VECTOR_FIELD_STREAMLINE_STEP),
+            constants::VECTOR_FIELD_STREAMLINE_WIDTH,
+            0.,
+            0.,
+            LineCap::Round,
+        );
+
+        app_state::update_surface_mode(SurfaceMode::VectorField);
+        self.simulation = None;
+        Ok(())
+    }
+
+    // ---- ode systems, a handful of trajectories from nearly the same start ---- //
+    pub fn set_attractor(&mut self, attractor: Attractor) {
+        let (derivatives, initial) = ode::get_attractor_system(attractor);
+        self.set_ode_system(
+            derivatives[0],
+            derivatives[1],
+            derivatives[2],
+            initial[0],
+            initial[1],
+            initial[2],
+        )
+        .unwrap();
+    }
+
+    // dx/dt, dy/dt and dz/dt as expressions of x, y, z and t, and where to start
+    pub fn set_ode_system(
+        &mut self,
+        dx: &str,
+        dy: &str,
+        dz: &str,
+        x0: f32,
+        y0: f32,
+        z0: f32,
+    ) -> Result<(), JsValue> {
+        self.ode_system = Some(ode::OdeSystem::compile(dx, dy, dz)?);
+        self.ode_initial = [x0, y0, z0];
+        self.update_trajectories();
+        app_state::update_surface_mode(SurfaceMode::Ode);
+        self.simulation = None;
+        Ok(())
+    }
+
+    pub fn set_ode_method(&mut self, ode_method: OdeMethod) {
+        self.ode_method = ode_method;
+        self.update_trajectories();
+    }
+
+    fn update_trajectories(&mut self) {
+        if let Some(ode_system) = self.ode_system.as_ref() {
+            self.trajectories = ode::get_diverging_trajectories(
+                ode_system,
+                self.ode_method,
+                &self.ode_initial,
+                constants::ODE_TRAJECTORY_COUNT,
+            );
+        }
+    }
+
+    // how the height field is drawn: solid, wireframe, both, or hidden line
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        app_state::update_render_mode(render_mode);
+    }
+
+    // ---- contour lines on the height field. no levels, no lines ---- //
+    pub fn set_contour_levels(&mut self, levels: Vec<f32>) {
+        self.contour_levels = levels;
+    }
+
+    // flat contour map over the 2d panel
+    pub fn set_contour_map_visible(&mut self, show_contour_map: bool) {
+        self.show_contour_map = show_contour_map;
+    }
+
+    // ---- point clouds, drawn on top of whatever surface is showing ---- //
+    // xyz per point, in the graph's -1 to +1 box. colors (rgb per point) and sizes (px per point)
+    // can be left empty for a default colour / size
+    pub fn set_scatter_points(
+        &mut self,
+        positions: Vec<f32>,
+        colors: Vec<f32>,
+        sizes: Vec<f32>,
+    ) -> Result<(), JsValue> {
+        if positions.len() % 3 != 0 {
+            return Err(JsValue::from("positions should be x, y, z for every point"));
+        }
+        let point_count = positions.len() / 3;
+        if !colors.is_empty() && colors.len() != 3 * point_count {
+            return Err(JsValue::from(format!(
+                "expected {} colour values (rgb for {} points), got {}",
+                3 * point_count,
+                point_count,
+                colors.len()
+            )));
+        }
+        if !sizes.is_empty() && sizes.len() != point_count {
+            return Err(JsValue::from(format!(
+                "expected {} sizes (one per point), got {}",
+                point_count,
+                sizes.len()
+            )));
+        }
+        self.program_scatter_3d
+            .set_points(&self.gl, &positions, &colors, &sizes);
         Ok(())
     }
 
+    pub fn clear_scatter_points(&mut self) {
+        self.program_scatter_3d
+            .set_points(&self.gl, &Vec::new(), &Vec::new(), &Vec::new());
+    }
+
+    // ---- thick 3d lines (trajectories, tracks), drawn on top of whatever surface is showing ---- //
+    // xyz per point, in the graph's -1 to +1 box. colors (rgb per point) can be left empty.
+    // width in px. dash and gap lengths are in the box's units, a gap of 0 for a solid line
+    pub fn add_line_3d(
+        &mut self,
+        positions: Vec<f32>,
+        colors: Vec<f32>,
+        width: f32,
+        dash_length: f32,
+        gap_length: f32,
+        cap: LineCap,
+    ) -> Result<(), JsValue> {
+        if positions.len() % 3 != 0 || positions.len() < 6 {
+            return Err(JsValue::from(
+                "positions should be x, y, z for at least two points",
+            ));
+        }
+        if !colors.is_empty() && colors.len() != positions.len() {
+            return Err(JsValue::from(format!(
+                "expected {} colour values (rgb per point), got {}",
+                positions.len(),
+                colors.len()
+            )));
+        }
+        self.program_line_3d.add_polyline(
+            &self.gl,
+            &positions,
+            &colors,
+            width,
+            dash_length,
+            gap_length,
+            cap,
+        );
+        Ok(())
+    }
+
+    pub fn clear_lines_3d(&mut self) {
+        self.program_line_3d.clear(&self.gl);
+    }
+
+    // ---- vertical slicing plane through the height field, with its profile in a side panel ---- //
+    // the plane is all points with x cos(angle) + z sin(angle) = offset (angle in radians)
+    pub fn set_slice_plane(&mut self, offset: f32, angle: f32) {
+        self.slice_plane = Some(slicing::SlicePlane {
+            offset: offset,
+            angle: angle,
+        });
+    }
+
+    pub fn clear_slice_plane(&mut self) {
+        self.slice_plane = None;
+    }
+
+    // the current profile as [s, x, y, z] for each point, s being the distance along the plane
+    pub fn get_slice_profile(&self) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+        match self.slice_plane.as_ref() {
+            Some(plane) if is_height_field(cur_app_state.surface_mode) => {
+                let (y_vals, _) =
+                    self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+                slicing::get_slice_profile(&y_vals, constants::GRID_SIZE, plane)
+                    .iter()
+                    .flat_map(|(s, point)| vec![*s, point[0], point[1], point[2]])
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // ---- measuring on the height field, by clicking points on it ---- //
+    // switching tool starts a new measurement
+    pub fn set_measure_tool(&mut self, measure_tool: MeasureTool) {
+        self.measure_tool = measure_tool;
+        self.measure_points.clear();
+        self.measure_path = None;
+    }
+
+    pub fn clear_measurement(&mut self) {
+        self.measure_points.clear();
+        self.measure_path = None;
+    }
+
+    // distance tool, once both points are in: [straight distance, path length along the surface, slope in degrees].
+    // polygon tool, from 3 points on: [projected (flat) area, true surface area].
+    // otherwise empty. the points stay put in x and z, so the numbers follow the surface as it moves
+    pub fn get_measurement(&self) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+        if !is_height_field(cur_app_state.surface_mode) {
+            return Vec::new();
+        }
+        let (y_vals, _) =
+            self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+        let n = constants::GRID_SIZE;
+        match self.measure_tool {
+            MeasureTool::Distance if self.measure_points.len() == 2 => {
+                let (a, b) = (self.measure_points[0], self.measure_points[1]);
+                let point_a = [a.0, common_funcs::get_height_at(&y_vals, n, a.0, a.1), a.1];
+                let point_b = [b.0, common_funcs::get_height_at(&y_vals, n, b.0, b.1), b.1];
+                let path_length = match self.measure_path.as_ref() {
+                    Some(measure_path) => measurement::get_path_length(&measure_path.path),
+                    None => return Vec::new(), // until the next update works it out
+                };
+                vec![
+                    measurement::get_distance(&point_a, &point_b),
+                    path_length,
+                    measurement::get_slope_degrees(&point_a, &point_b),
+                ]
+            }
+            MeasureTool::Polygon if self.measure_points.len() >= 3 => vec![
+                measurement::get_projected_area(&self.measure_points),
+                measurement::get_surface_area(&y_vals, n, &self.measure_points),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    // x, y in canvas px, with y going up like in webgl
+    fn add_measure_point_at_canvas_point(&mut self, x: f32, y: f32) {
+        let cur_app_state = app_state::get_cur_state();
+        if !is_height_field(cur_app_state.surface_mode) {
+            return;
+        }
+        let (y_vals, _) =
+            self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+        let ray = common_funcs::get_3d_pick_ray(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+            x,
+            y,
+        );
+        let hit = ray.and_then(|(origin, direction)| {
+            common_funcs::get_height_field_hit(&y_vals, constants::GRID_SIZE, &origin, &direction)
+        });
+        if let Some((point, _)) = hit {
+            // a third click on the distance tool starts a new pair
+            if self.measure_tool == MeasureTool::Distance && self.measure_points.len() == 2 {
+                self.measure_points.clear();
+            }
+            self.measure_points.push((point[0], point[2]));
+            self.measure_path = None;
+        }
+    }
+
+    // the path along the surface is a shortest path search over the whole grid, too slow to run for
+    // every viewport every frame. it's worked out here once the distance tool has both its points,
+    // and again only when the surface under it changes (the points stay put in x and z)
+    fn update_measure_path(&mut self) {
+        let cur_app_state = app_state::get_cur_state();
+        if self.measure_tool != MeasureTool::Distance
+            || self.measure_points.len() != 2
+            || !is_height_field(cur_app_state.surface_mode)
+        {
+            self.measure_path = None;
+            return;
+        }
+        let (y_vals, _) =
+            self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+        if let Some(measure_path) = self.measure_path.as_ref() {
+            if measure_path.heights == y_vals {
+                return;
+            }
+        }
+        let (a, b) = (self.measure_points[0], self.measure_points[1]);
+        self.measure_path = Some(measurement::SurfacePath {
+            path: measurement::get_surface_path(&y_vals, constants::GRID_SIZE, a, b),
+            heights: y_vals,
+        });
+    }
+
+    // ---- viewports ---- //
+    // split the canvas into several views of the same scene, each with its own camera.
+    // the mouse drives whichever one it's over
+    pub fn set_viewport_layout(&mut self, viewport_layout: ViewportLayout) {
+        app_state::update_viewport_layout(viewport_layout);
+    }
+
+    pub fn get_viewport_layout(&self) -> ViewportLayout {
+        app_state::get_cur_state().viewport_layout
+    }
+
+    // [bottom, top, left, right] per viewport, in px with y going up, for placing captions on the js side
+    pub fn get_viewport_rects(&self) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+        cur_app_state.viewports[..cur_app_state.viewport_count]
+            .iter()
+            .flat_map(|viewport| vec![viewport.bottom, viewport.top, viewport.left, viewport.right])
+            .collect()
+    }
+
+    // ---- the camera of the viewport under the mouse (the only one, unless the layout splits the canvas) ---- //
+    pub fn set_projection(&mut self, projection: Projection) {
+        let lens = app_state::get_cur_state().lens;
+        app_state::update_lens(camera::Lens {
+            projection: projection,
+            ..lens
+        });
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        app_state::get_cur_state().lens.projection
+    }
+
+    // in degrees, top to bottom. only the perspective projection has one
+    pub fn set_field_of_view(&mut self, degrees: f32) -> Result<(), JsValue> {
+        if !(degrees > 1. && degrees < 170.) {
+            return Err(JsValue::from(
+                "the field of view should be between 1 and 170 degrees",
+            ));
+        }
+        let lens = app_state::get_cur_state().lens;
+        app_state::update_lens(camera::Lens {
+            field_of_view: degrees.to_radians(),
+            ..lens
+        });
+        Ok(())
+    }
+
+    // distances from the camera. anything nearer than near or further than far isn't drawn. the middle
+    // of the graph's box is about 2.4 away with the default field of view, and its corners at most 1.7
+    // nearer or further (less when the display box is smaller than the canvas). zooming doesn't move them
+    pub fn set_clip_planes(&mut self, z_near: f32, z_far: f32) -> Result<(), JsValue> {
+        if !(z_near > 0. && z_far > z_near) {
+            return Err(JsValue::from("clip planes need 0 < near < far"));
+        }
+        let lens = app_state::get_cur_state().lens;
+        app_state::update_lens(camera::Lens {
+            z_near: z_near,
+            z_far: z_far,
+            ..lens
+        });
+        Ok(())
+    }
+
+    // swings the camera round to a standard view
+    pub fn set_view_preset(&mut self, preset: ViewPreset) {
+        app_state::update_view_preset(preset);
+    }
+
+    // 2 = everything twice the size
+    pub fn set_zoom(&mut self, zoom: f32) -> Result<(), JsValue> {
+        if !(zoom >= constants::CAMERA_MIN_ZOOM && zoom <= constants::CAMERA_MAX_ZOOM) {
+            return Err(JsValue::from(format!(
+                "the zoom should be between {} and {}",
+                constants::CAMERA_MIN_ZOOM,
+                constants::CAMERA_MAX_ZOOM
+            )));
+        }
+        let lens = app_state::get_cur_state().lens;
+        app_state::update_lens(camera::Lens { zoom: zoom, ..lens });
+        Ok(())
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        app_state::get_cur_state().lens.zoom
+    }
+
+    // the point in the graph's box (-1 to 1 each way) to center the view on
+    pub fn set_camera_target(&mut self, x: f32, y: f32, z: f32) -> Result<(), JsValue> {
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return Err(JsValue::from("the target has to be a point"));
+        }
+        let lens = app_state::get_cur_state().lens;
+        app_state::update_lens(camera::Lens {
+            target: [x, y, z],
+            ..lens
+        });
+        Ok(())
+    }
+
+    pub fn get_camera_target(&self) -> Vec<f32> {
+        app_state::get_cur_state().lens.target.to_vec()
+    }
+
+    // ---- camera paths: keyframes on the timeline for the camera to fly through ---- //
+    // keeps the current camera (rotation, zoom and target) at the playhead, replacing any keyframe
+    // already there. easing is how the camera comes in from the keyframe before
+    pub fn record_camera_keyframe(&mut self, easing: Easing) {
+        let cur_app_state = app_state::get_cur_state();
+        self.camera_path.add(camera_path::CameraKeyframe {
+            time: self.timeline.get_time(),
+            pose: camera_path::CameraPose {
+                rotation: (cur_app_state.rotation_x_axis, cur_app_state.rotation_y_axis),
+                zoom: cur_app_state.lens.zoom,
+                target: cur_app_state.lens.target,
+            },
+            easing: easing,
+        });
+    }
+
+    // index in time order
+    pub fn remove_camera_keyframe(&mut self, index: u32) -> bool {
+        self.camera_path.remove(index as usize)
+    }
+
+    pub fn clear_camera_path(&mut self) {
+        self.camera_path.clear();
+        self.camera_path_viewport = None;
+    }
+
+    pub fn get_camera_keyframe_times(&self) -> Vec<f32> {
+        self.camera_path
+            .get_keyframes()
+            .iter()
+            .map(|keyframe| keyframe.time)
+            .collect()
+    }
+
+    // rewinds the timeline to the first keyframe and plays it, with the current viewport's camera
+    // following along. it lets go at the last keyframe, or when the camera is dragged
+    pub fn play_camera_path(&mut self) -> Result<(), JsValue> {
+        let start = match self.camera_path.get_keyframes().first() {
+            Some(keyframe) => keyframe.time,
+            None => return Err(JsValue::from("the camera path has no keyframes")),
+        };
+        self.timeline.seek(start)?;
+        self.timeline.play();
+        self.camera_path_viewport = Some(app_state::get_cur_state().current_viewport);
+        self.follow_camera_path();
+        Ok(())
+    }
+
+    pub fn stop_camera_path(&mut self) {
+        self.camera_path_viewport = None;
+    }
+
+    pub fn is_camera_path_playing(&self) -> bool {
+        self.camera_path_viewport.is_some()
+    }
+
+    // [{ "time": ms, "rotation": [x, y], "zoom": 1, "target": [x, y, z], "easing": "easeInOut" }, ...]
+    pub fn get_camera_path_json(&self) -> Result<String, JsValue> {
+        self.camera_path.to_json()
+    }
+
+    // replaces the camera path. easing is one of linear, easeIn, easeOut and easeInOut
+    pub fn set_camera_path_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.camera_path.load_json(json)?;
+        self.camera_path_viewport = None;
+        Ok(())
+    }
+
+    fn follow_camera_path(&mut self) {
+        let index = match self.camera_path_viewport {
+            Some(index) => index,
+            None => return,
+        };
+        let cur_app_state = app_state::get_cur_state();
+        // grabbing the camera takes it back
+        if cur_app_state.mouse_down && cur_app_state.current_viewport == index {
+            self.camera_path_viewport = None;
+            return;
+        }
+        // the playhead comes from the timeline directly, app_state's time catches up on the next update
+        let time = self.timeline.get_time();
+        if let Some(pose) = self.camera_path.get_pose(time) {
+            app_state::update_viewport_camera(
+                index,
+                pose.rotation,
+                camera::Lens {
+                    zoom: pose.zoom,
+                    target: pose.target,
+                    ..cur_app_state.viewports[index].lens
+                },
+            );
+        }
+        if self
+            .camera_path
+            .get_end_time()
+            .map_or(true, |end| time >= end)
+        {
+            self.camera_path_viewport = None;
+        }
+    }
+
+    // ---- scene graph ---- //
+    // every scene starts with the root (node 0), with the background (1) and the graph (2) under it. the graph
+    // draws whatever the surface mode shows. nodes sit relative to their parent, and are drawn parents
+    // first, then siblings in the order they were added. the add_ functions return the new node's id
+    pub fn add_scene_group(&mut self, parent: u32) -> Result<u32, JsValue> {
+        Ok(self.scene.add(parent as usize, scene::Drawable::Group)? as u32)
+    }
+
+    pub fn add_scene_gradient(&mut self, parent: u32) -> Result<u32, JsValue> {
+        Ok(self.scene.add(parent as usize, scene::Drawable::Gradient)? as u32)
+    }
+
+    // another height field, y = f(x, z, t) over the graph's grid. t is in seconds
+    pub fn add_scene_surface(&mut self, parent: u32, expression: &str) -> Result<u32, JsValue> {
+        let expression = expression::Expr::compile(expression, &["x", "z", "t"])?;
+        Ok(self
+            .scene
+            .add(parent as usize, scene::Drawable::Surface(expression))? as u32)
+    }
+
+    pub fn add_scene_parametric(
+        &mut self,
+        parent: u32,
+        surface: ParametricSurface,
+    ) -> Result<u32, JsValue> {
+        Ok(self
+            .scene
+            .add(parent as usize, scene::Drawable::Parametric(surface))? as u32)
+    }
+
+    // a cross on every point, xyz per point
+    pub fn add_scene_markers(&mut self, parent: u32, positions: Vec<f32>) -> Result<u32, JsValue> {
+        if positions.len() % 3 != 0 {
+            return Err(JsValue::from("positions should be xyz per point"));
+        }
+        Ok(self
+            .scene
+            .add(parent as usize, scene::Drawable::Markers(positions))? as u32)
+    }
+
+    // the node and everything under it
+    pub fn remove_scene_node(&mut self, id: u32) -> Result<(), JsValue> {
+        Ok(self.scene.remove(id as usize)?)
+    }
+
+    // scale, then rotate about x, y and z (radians), then translate, all relative to the parent.
+    // the background and gradient are flat on the canvas, so they ignore theirs
+    pub fn set_scene_node_transform(
+        &mut self,
+        id: u32,
+        translate_x: f32,
+        translate_y: f32,
+        translate_z: f32,
+        rotate_x: f32,
+        rotate_y: f32,
+        rotate_z: f32,
+        scale_x: f32,
+        scale_y: f32,
+        scale_z: f32,
+    ) -> Result<(), JsValue> {
+        let transform = scene::Transform {
+            translation: [translate_x, translate_y, translate_z],
+            rotation: [rotate_x, rotate_y, rotate_z],
+            scale: [scale_x, scale_y, scale_z],
+        };
+        Ok(self.scene.set_transform(id as usize, transform)?)
+    }
+
+    // hiding a node hides everything under it too
+    pub fn set_scene_node_visible(&mut self, id: u32, visible: bool) -> Result<(), JsValue> {
+        self.get_scene_node_mut(id)?.visible = visible;
+        Ok(())
+    }
+
+    // for surfaces and markers
+    pub fn set_scene_node_color(&mut self, id: u32, r: f32, g: f32, b: f32) -> Result<(), JsValue> {
+        self.get_scene_node_mut(id)?.material.color = [r, g, b];
+        Ok(())
+    }
+
+    // for surfaces. the graph goes by set_render_mode
+    pub fn set_scene_node_render_mode(
+        &mut self,
+        id: u32,
+        render_mode: RenderMode,
+    ) -> Result<(), JsValue> {
+        self.get_scene_node_mut(id)?.material.render_mode = render_mode;
+        Ok(())
+    }
+
+    fn get_scene_node_mut(&mut self, id: u32) -> Result<&mut scene::SceneNode, JsValue> {
+        self.scene
+            .get_mut(id as usize)
+            .ok_or_else(|| JsValue::from(format!("there's no scene node {}", id)))
+    }
+
+    // ---- annotations: labelled pins in the graph's box ---- //
+    // a pin at a fixed point. returns its id
+    pub fn add_annotation(&mut self, label: &str, x: f32, y: f32, z: f32) -> u32 {
+        self.annotations
+            .add(label, [x, y, z], false, constants::ANNOTATION_COLOR)
+    }
+
+    // a pin that rides on the height field at (x, z), whatever it's doing. returns its id
+    pub fn add_surface_annotation(&mut self, label: &str, x: f32, z: f32) -> u32 {
+        self.annotations
+            .add(label, [x, 0., z], true, constants::ANNOTATION_COLOR)
+    }
+
+    // these return false if there's no annotation with that id
+    pub fn set_annotation_label(&mut self, id: u32, label: &str) -> bool {
+        match self.annotations.get_mut(id) {
+            Some(annotation) => {
+                annotation.label = String::from(label);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_annotation_color(&mut self, id: u32, r: f32, g: f32, b: f32) -> bool {
+        match self.annotations.get_mut(id) {
+            Some(annotation) => {
+                annotation.color = [r, g, b];
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_annotation(&mut self, id: u32) -> bool {
+        self.annotations.remove(id)
+    }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    pub fn get_annotations_json(&self) -> Result<String, JsValue> {
+        self.annotations.to_json()
+    }
+
+    pub fn set_annotations_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.annotations.load_json(json)
+    }
+
+    // where the pins' tips are on the canvas (in the viewport under the mouse), for drawing the labels on the js side:
+    // [id, x, y, visible] per annotation, x and y in px from the canvas's top left.
+    // visible is 0 when the pin is behind the height field (the gpu hides the pin itself the same way)
+    pub fn get_annotation_screen_positions(&self) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+        let y_vals = if is_height_field(cur_app_state.surface_mode) {
+            Some(
+                self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time)
+                    .0,
+            )
+        } else {
+            None
+        };
+        let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+        )
+        .get_model_view_projection();
+
+        let anchors = self
+            .annotations
+            .get_anchors(y_vals.as_ref(), constants::GRID_SIZE);
+        let mut protag: Vec<f32> = Vec::with_capacity(4 * anchors.len());
+        for (annotation, anchor) in self.annotations.annotations.iter().zip(anchors.iter()) {
+            let (x, y) = match annotations::get_canvas_point(
+                &projection,
+                anchor,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+            ) {
+                Some(point) => point,
+                None => {
+                    // behind the camera
+                    protag.extend_from_slice(&[annotation.id as f32, 0., 0., 0.]);
+                    continue;
+                }
+            };
+
+            // anything on the ray through the pin's tip, closer than the tip, hides it
+            let mut visible = true;
+            if let Some(y_vals) = y_vals.as_ref() {
+                let ray = common_funcs::get_3d_pick_ray(
+                    cur_app_state.control_bottom,
+                    cur_app_state.control_top,
+                    cur_app_state.control_left,
+                    cur_app_state.control_right,
+                    cur_app_state.canvas_height,
+                    cur_app_state.canvas_width,
+                    cur_app_state.rotation_x_axis,
+                    cur_app_state.rotation_y_axis,
+                    &cur_app_state.lens,
+                    x,
+                    y,
+                );
+                if let Some((origin, direction)) = ray {
+                    let along = |point: &[f32; 3]| {
+                        (0..3)
+                            .map(|i| (point[i] - origin[i]) * direction[i])
+                            .sum::<f32>()
+                    };
+                    if let Some((hit, _)) = common_funcs::get_height_field_hit(
+                        y_vals,
+                        constants::GRID_SIZE,
+                        &origin,
+                        &direction,
+                    ) {
+                        visible =
+                            along(&hit) > along(anchor) - constants::ANNOTATION_OCCLUSION_TOLERANCE;
+                    }
+                }
+            }
+
+            protag.extend_from_slice(&[
+                annotation.id as f32,
+                x,
+                cur_app_state.canvas_height - y,
+                if visible { 1. } else { 0. },
+            ]);
+        }
+        protag
+    }
+
+    // ---- hover readout ---- //
+    // what's under the mouse on the height field: [x, y, z, cell x, cell z], in the graph's units.
+    // empty when the mouse isn't over the surface, or the current mode isn't a height field
+    pub fn get_hover_point(&self) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+        if !is_height_field(cur_app_state.surface_mode) {
+            return Vec::new();
+        }
+        let (y_vals, _) =
+            self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+        match self.get_hover_hit(&y_vals) {
+            Some((point, (cell_x, cell_z))) => {
+                vec![point[0], point[1], point[2], cell_x as f32, cell_z as f32]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // ---- gpu picking ---- //
+    // x, y in px from the canvas's top left, like the mouse events. returns
+    // [object id (see PickObject), triangle index, barycentric weights of the triangle's 3 corners],
+    // or empty if there's nothing there
+    pub fn pick(&mut self, x: f32, y: f32) -> Vec<f32> {
+        let cur_app_state = app_state::get_cur_state();
+
+        // whatever is showing right now. the vecs live out here, the targets only borrow them
+        let grid_positions: Vec<f32>;
+        let parametric_positions: Vec<f32>;
+        let mut targets: Vec<programs::PickTarget> = Vec::new();
+        if is_height_field(cur_app_state.surface_mode) {
+            let n = constants::GRID_SIZE;
+            let (mut positions, indices) = common_funcs::get_position_grid_n_by_n(n);
+            let (y_vals, _) =
+                self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+            for (i, &y) in y_vals.iter().enumerate() {
+                positions[3 * i + 1] = y;
+            }
+            grid_positions = positions;
+            targets.push(programs::PickTarget {
+                object_id: PickObject::Surface as u32,
+                positions: &grid_positions,
+                indices: indices.iter().map(|&index| index as u32).collect(),
+            });
+        }
+        if cur_app_state.surface_mode == SurfaceMode::Isosurface {
+            if let Some(mesh) = self.isosurface_mesh.as_ref() {
+                targets.push(programs::PickTarget {
+                    object_id: PickObject::Isosurface as u32,
+                    positions: &mesh.positions,
+                    indices: mesh.indices.clone(),
+                });
+            }
+        }
+        if cur_app_state.surface_mode == SurfaceMode::Parametric {
+            let n = constants::PARAMETRIC_GRID_SIZE;
+            parametric_positions = parametric::get_parametric_mesh(
+                cur_app_state.parametric_surface,
+                n,
+                cur_app_state.time,
+            )
+            .0;
+            targets.push(programs::PickTarget {
+                object_id: PickObject::ParametricSurface as u32,
+                positions: &parametric_positions,
+                indices: common_funcs::get_position_grid_n_by_n(n)
+                    .1
+                    .iter()
+                    .map(|&index| index as u32)
+                    .collect(),
+            });
+        }
+
+        let picked = self.program_picker.pick(
+            &self.gl,
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+            &targets,
+            x,
+            cur_app_state.canvas_height - y,
+        );
+        match picked {
+            Some(picked) => vec![
+                picked.object_id as f32,
+                picked.triangle_index as f32,
+                picked.barycentric[0],
+                picked.barycentric[1],
+                picked.barycentric[2],
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn get_hover_hit(&self, y_vals: &Vec<f32>) -> Option<([f32; 3], (usize, usize))> {
+        let cur_app_state = app_state::get_cur_state();
+        // only the viewport the mouse is in. the others are looking from somewhere else
+        let viewport = cur_app_state.viewports[cur_app_state.current_viewport];
+        if cur_app_state.mouse_x < viewport.left
+            || cur_app_state.mouse_x >= viewport.right
+            || cur_app_state.mouse_y < viewport.bottom
+            || cur_app_state.mouse_y >= viewport.top
+        {
+            return None;
+        }
+        let (origin, direction) = common_funcs::get_3d_pick_ray(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+            cur_app_state.mouse_x,
+            cur_app_state.mouse_y,
+        )?;
+        common_funcs::get_height_field_hit(y_vals, constants::GRID_SIZE, &origin, &direction)
+    }
+
+    // x, y in canvas px, with y going up like in webgl
+    fn add_impulse_at_canvas_point(&mut self, x: f32, y: f32) {
+        let cur_app_state = app_state::get_cur_state();
+        // the click lands on the surface as it is now, not where it would be if it were flat
+        let (y_vals, _) =
+            self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+        let ray = common_funcs::get_3d_pick_ray(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+            x,
+            y,
+        );
+        let hit = ray.and_then(|(origin, direction)| {
+            common_funcs::get_height_field_hit(&y_vals, constants::GRID_SIZE, &origin, &direction)
+        });
+        if let Some((point, _)) = hit {
+            // the grid point nearest the hit, rather than the corner of the cell it's in
+            let square_size: f32 = 2. / constants::GRID_SIZE as f32;
+            let grid_x = ((point[0] + 1.) / square_size).round() as usize;
+            let grid_z = ((point[2] + 1.) / square_size).round() as usize;
+            if let Some(simulation) = self.simulation.as_mut() {
+                simulation.add_impulse(
+                    grid_x.min(constants::GRID_SIZE),
+                    grid_z.min(constants::GRID_SIZE),
+                );
+            }
+        }
+    }
+
     pub fn render(&self) {
         self.gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
 
+        // the whole scene once per viewport, each cut to its own cell of the canvas and seen through
+        // its own camera. the app state is only read here, never switched over to the viewport
         let cur_app_state = app_state::get_cur_state();
+        self.gl.enable(GL::SCISSOR_TEST);
+        for (i, viewport) in cur_app_state.viewports[..cur_app_state.viewport_count]
+            .iter()
+            .enumerate()
+        {
+            self.gl.scissor(
+                viewport.left as i32,
+                viewport.bottom as i32,
+                (viewport.right - viewport.left) as i32,
+                (viewport.top - viewport.bottom) as i32,
+            );
+            // every viewport does its own depth testing
+            self.gl.clear(GL::DEPTH_BUFFER_BIT);
+            self.render_scene(&app_state::with_viewport(&cur_app_state, i));
+        }
+        self.gl.disable(GL::SCISSOR_TEST);
+    }
 
-        self.program_color_2d.render(
+    fn render_scene(&self, cur_app_state: &app_state::AppState) {
+        for (id, world) in self.scene.get_draw_list() {
+            let node = self.scene.get(id).unwrap();
+            match &node.drawable {
+                scene::Drawable::Group => {}
+                scene::Drawable::Background => self.program_color_2d.render(
+                    &self.gl,
+                    cur_app_state.control_bottom,
+                    cur_app_state.control_top,
+                    cur_app_state.control_left,
+                    cur_app_state.control_right,
+                    cur_app_state.canvas_height,
+                    cur_app_state.canvas_width,
+                ),
+                scene::Drawable::Gradient => self.program_color_2d_gradient.render(
+                    &self.gl,
+                    cur_app_state.control_bottom + 30.,
+                    cur_app_state.control_top - 30.,
+                    cur_app_state.control_left + 30.,
+                    cur_app_state.control_right - 30.,
+                    cur_app_state.canvas_height,
+                    cur_app_state.canvas_width,
+                ),
+                scene::Drawable::Graph => self.render_graph(cur_app_state),
+                scene::Drawable::Surface(expression) => {
+                    let y_vals = scene::get_surface_heights(
+                        expression,
+                        constants::GRID_SIZE,
+                        cur_app_state.time,
+                    );
+                    let colors: Vec<f32> = (0..y_vals.len())
+                        .flat_map(|_| node.material.color.to_vec())
+                        .collect();
+                    self.program_graph_3d.render(
+                        &self.gl,
+                        cur_app_state.control_bottom,
+                        cur_app_state.control_top,
+                        cur_app_state.control_left,
+                        cur_app_state.control_right,
+                        cur_app_state.canvas_height,
+                        cur_app_state.canvas_width,
+                        cur_app_state.rotation_x_axis,
+                        cur_app_state.rotation_y_axis,
+                        &cur_app_state.lens,
+                        &y_vals,
+                        Some(&colors),
+                        node.material.render_mode,
+                        &world,
+                    );
+                }
+                scene::Drawable::Parametric(surface) => {
+                    let (positions, normals) = parametric::get_parametric_mesh(
+                        *surface,
+                        constants::PARAMETRIC_GRID_SIZE,
+                        cur_app_state.time,
+                    );
+                    self.program_parametric_3d.render(
+                        &self.gl,
+                        cur_app_state.control_bottom,
+                        cur_app_state.control_top,
+                        cur_app_state.control_left,
+                        cur_app_state.control_right,
+                        cur_app_state.canvas_height,
+                        cur_app_state.canvas_width,
+                        cur_app_state.rotation_x_axis,
+                        cur_app_state.rotation_y_axis,
+                        &cur_app_state.lens,
+                        &positions,
+                        &normals,
+                        &world,
+                    );
+                }
+                scene::Drawable::Markers(points) => {
+                    let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+                        cur_app_state.control_bottom,
+                        cur_app_state.control_top,
+                        cur_app_state.control_left,
+                        cur_app_state.control_right,
+                        cur_app_state.canvas_height,
+                        cur_app_state.canvas_width,
+                        cur_app_state.rotation_x_axis,
+                        cur_app_state.rotation_y_axis,
+                        &cur_app_state.lens,
+                    )
+                    .get_model_view_projection();
+                    let segments: Vec<f32> = points
+                        .chunks(3)
+                        .flat_map(|point| {
+                            common_funcs::get_marker_segments(
+                                &[point[0], point[1], point[2]],
+                                constants::SCENE_MARKER_SIZE,
+                            )
+                        })
+                        .collect();
+                    let color = node.material.color;
+                    self.program_lines.render(
+                        &self.gl,
+                        &(projection * world),
+                        &segments,
+                        [color[0], color[1], color[2], 1.],
+                    );
+                }
+            }
+        }
+    }
+
+    // ---- the graph node: the current surface mode, and everything drawn in its coordinates ---- //
+    fn render_graph(&self, cur_app_state: &app_state::AppState) {
+        // the height field's y values, when it's showing. annotations on the surface need them
+        let mut surface_heights: Option<Vec<f32>> = None;
+
+        if cur_app_state.surface_mode == SurfaceMode::Bars {
+            self.program_bars_3d.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+            );
+            let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+            )
+            .get_model_view_projection();
+            self.program_lines.render(
+                &self.gl,
+                &projection,
+                &self.bars_box_segments,
+                constants::BARS_3D_BOX_COLOR,
+            );
+        } else if cur_app_state.surface_mode == SurfaceMode::Ode {
+            self.program_trajectories.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+            );
+        } else if cur_app_state.surface_mode == SurfaceMode::VectorField {
+            self.program_glyphs_3d.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+            );
+            self.program_streamlines.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+            );
+        } else if cur_app_state.surface_mode == SurfaceMode::Isosurface {
+            self.program_mesh_3d.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+                constants::ISOSURFACE_COLOR,
+            );
+        } else if cur_app_state.surface_mode == SurfaceMode::Parametric {
+            let (positions, normals) = parametric::get_parametric_mesh(
+                cur_app_state.parametric_surface,
+                constants::PARAMETRIC_GRID_SIZE,
+                cur_app_state.time,
+            );
+            self.program_parametric_3d.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+                &positions,
+                &normals,
+                &Mat4::identity(),
+            );
+        } else {
+            let (y_vals, colors) =
+                self.get_3d_y_values_and_colors(cur_app_state.surface_mode, cur_app_state.time);
+            self.program_graph_3d.render(
+                &self.gl,
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+                cur_app_state.rotation_x_axis,
+                cur_app_state.rotation_y_axis,
+                &cur_app_state.lens,
+                &y_vals,
+                colors.as_ref(),
+                cur_app_state.render_mode,
+                &Mat4::identity(),
+            );
+
+            if !self.contour_levels.is_empty() {
+                self.render_contours(&y_vals, cur_app_state);
+            }
+
+            if let Some(plane) = self.slice_plane.as_ref() {
+                self.render_slice(plane, &y_vals, cur_app_state);
+            }
+            if !self.measure_points.is_empty() {
+                self.render_measurement(&y_vals, cur_app_state);
+            }
+
+            // a little 3d cross where the mouse is over the surface
+            if let Some((point, _)) = self.get_hover_hit(&y_vals) {
+                let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+                    cur_app_state.control_bottom,
+                    cur_app_state.control_top,
+                    cur_app_state.control_left,
+                    cur_app_state.control_right,
+                    cur_app_state.canvas_height,
+                    cur_app_state.canvas_width,
+                    cur_app_state.rotation_x_axis,
+                    cur_app_state.rotation_y_axis,
+                    &cur_app_state.lens,
+                )
+                .get_model_view_projection();
+                self.program_lines.render(
+                    &self.gl,
+                    &projection,
+                    &common_funcs::get_marker_segments(&point, constants::HOVER_MARKER_SIZE),
+                    constants::HOVER_MARKER_COLOR,
+                );
+            }
+
+            // surfaces with their own colours also get a flat picture of them, in a corner of the box
+            if let Some(colors) = colors.as_ref() {
+                let image_size = constants::COMPLEX_IMAGE_SIZE
+                    * (cur_app_state.control_top - cur_app_state.control_bottom);
+                self.program_color_2d_grid.render(
+                    &self.gl,
+                    cur_app_state.control_bottom,
+                    cur_app_state.control_bottom + image_size,
+                    cur_app_state.control_left,
+                    cur_app_state.control_left + image_size,
+                    cur_app_state.canvas_height,
+                    cur_app_state.canvas_width,
+                    colors,
+                );
+            }
+
+            // the solid and wireframe modes don't leave the surface in the depth buffer, and the pins need it there
+            if !self.annotations.annotations.is_empty() {
+                self.program_graph_3d.write_depth(&self.gl);
+            }
+            surface_heights = Some(y_vals);
+        }
+
+        self.program_scatter_3d.render(
             &self.gl,
             cur_app_state.control_bottom,
             cur_app_state.control_top,
@@ -59,18 +1672,186 @@ impl Client {
             cur_app_state.control_right,
             cur_app_state.canvas_height,
             cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+        );
+        self.program_line_3d.render(
+            &self.gl,
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+        );
+
+        let colors: Vec<[f32; 3]> = self
+            .annotations
+            .annotations
+            .iter()
+            .map(|annotation| annotation.color)
+            .collect();
+        self.program_billboards_3d.render(
+            &self.gl,
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+            &self
+                .annotations
+                .get_anchors(surface_heights.as_ref(), constants::GRID_SIZE),
+            &colors,
+        );
+    }
+
+    fn render_measurement(&self, y_vals: &Vec<f32>, cur_app_state: &app_state::AppState) {
+        let n = constants::GRID_SIZE;
+        let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+        )
+        .get_model_view_projection();
+
+        // a cross on every point clicked
+        let markers: Vec<f32> = self
+            .measure_points
+            .iter()
+            .flat_map(|&(x, z)| {
+                common_funcs::get_marker_segments(
+                    &[x, common_funcs::get_height_at(y_vals, n, x, z), z],
+                    constants::HOVER_MARKER_SIZE,
+                )
+            })
+            .collect();
+        self.program_lines
+            .render(&self.gl, &projection, &markers, constants::MEASURE_COLOR);
+
+        let mut lines: Vec<f32> = Vec::new();
+        if self.measure_tool == MeasureTool::Distance && self.measure_points.len() == 2 {
+            // the straight line between the two points, and the path along the surface
+            let (a, b) = (self.measure_points[0], self.measure_points[1]);
+            lines.extend_from_slice(&[a.0, common_funcs::get_height_at(y_vals, n, a.0, a.1), a.1]);
+            lines.extend_from_slice(&[b.0, common_funcs::get_height_at(y_vals, n, b.0, b.1), b.1]);
+            let path = match self.measure_path.as_ref() {
+                Some(measure_path) => &measure_path.path[..],
+                None => &[],
+            };
+            for pair in path.windows(2) {
+                for point in pair.iter() {
+                    lines.extend_from_slice(&[
+                        point[0],
+                        point[1] + constants::CONTOUR_OFFSET,
+                        point[2],
+                    ]);
+                }
+            }
+        } else if self.measure_tool == MeasureTool::Polygon {
+            lines = measurement::get_draped_segments(
+                y_vals,
+                n,
+                &self.measure_points,
+                true,
+                constants::CONTOUR_OFFSET,
+            );
+        }
+        self.program_lines
+            .render(&self.gl, &projection, &lines, constants::MEASURE_COLOR);
+    }
+
+    fn render_slice(
+        &self,
+        plane: &slicing::SlicePlane,
+        y_vals: &Vec<f32>,
+        cur_app_state: &app_state::AppState,
+    ) {
+        let profile = slicing::get_slice_profile(y_vals, constants::GRID_SIZE, plane);
+
+        // in the 3d graph: the plane's outline and the cut
+        let projection = common_funcs::get_3d_projection_matrix_and_rotation(
+            cur_app_state.control_bottom,
+            cur_app_state.control_top,
+            cur_app_state.control_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+            cur_app_state.rotation_x_axis,
+            cur_app_state.rotation_y_axis,
+            &cur_app_state.lens,
+        )
+        .get_model_view_projection();
+        self.program_lines.render(
+            &self.gl,
+            &projection,
+            &slicing::get_plane_outline_segments(
+                plane,
+                -constants::SLICE_PROFILE_Y_RANGE,
+                constants::SLICE_PROFILE_Y_RANGE,
+            ),
+            constants::SLICE_PLANE_COLOR,
+        );
+        self.program_lines.render(
+            &self.gl,
+            &projection,
+            &slicing::get_profile_segments(&profile, constants::CONTOUR_OFFSET),
+            constants::SLICE_PROFILE_COLOR,
+        );
+
+        // and as a line chart, in a panel in the upper right corner of the box
+        let panel_width = constants::SLICE_PANEL_WIDTH
+            * (cur_app_state.control_right - cur_app_state.control_left);
+        let panel_height = constants::SLICE_PANEL_HEIGHT
+            * (cur_app_state.control_top - cur_app_state.control_bottom);
+        let panel_bottom = cur_app_state.control_top - panel_height;
+        let panel_left = cur_app_state.control_right - panel_width;
+        self.program_color_2d.render(
+            &self.gl,
+            panel_bottom,
+            cur_app_state.control_top,
+            panel_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
         );
-        // self.program_color_2d_gradient.render(
-        //     &self.gl,
-        //     cur_app_state.control_bottom + 30.,
-        //     cur_app_state.control_top - 30.,
-        //     cur_app_state.control_left + 30.,
-        //     cur_app_state.control_right - 30.,
-        //     cur_app_state.canvas_height,
-        //     cur_app_state.canvas_width,
-        // );
-        self.program_graph_3d.render(
+        let panel_transform = common_funcs::get_2d_panel_transform(
+            panel_bottom,
+            cur_app_state.control_top,
+            panel_left,
+            cur_app_state.control_right,
+            cur_app_state.canvas_height,
+            cur_app_state.canvas_width,
+        );
+        self.program_lines.render(
             &self.gl,
+            &panel_transform,
+            &slicing::get_profile_chart_segments(&profile, constants::SLICE_PROFILE_Y_RANGE),
+            constants::SLICE_PROFILE_COLOR,
+        );
+    }
+
+    fn render_contours(&self, y_vals: &Vec<f32>, cur_app_state: &app_state::AppState) {
+        let polylines: Vec<contours::Polyline> = self
+            .contour_levels
+            .iter()
+            .flat_map(|&level| contours::extract_contours(y_vals, constants::GRID_SIZE, level))
+            .collect();
+
+        let projection = common_funcs::get_3d_projection_matrix_and_rotation(
             cur_app_state.control_bottom,
             cur_app_state.control_top,
             cur_app_state.control_left,
@@ -79,7 +1860,73 @@ impl Client {
             cur_app_state.canvas_width,
             cur_app_state.rotation_x_axis,
             cur_app_state.rotation_y_axis,
-            &common_funcs::get_updated_3d_y_values(cur_app_state.time),
+            &cur_app_state.lens,
+        )
+        .get_model_view_projection();
+        self.program_lines.render(
+            &self.gl,
+            &projection,
+            &contours::get_line_segments(&polylines, constants::CONTOUR_OFFSET),
+            constants::CONTOUR_COLOR,
         );
+
+        if self.show_contour_map {
+            let panel_transform = common_funcs::get_2d_panel_transform(
+                cur_app_state.control_bottom,
+                cur_app_state.control_top,
+                cur_app_state.control_left,
+                cur_app_state.control_right,
+                cur_app_state.canvas_height,
+                cur_app_state.canvas_width,
+            );
+            self.program_lines.render(
+                &self.gl,
+                &panel_transform,
+                &contours::get_flat_line_segments(&polylines),
+                constants::CONTOUR_MAP_COLOR,
+            );
+        }
+    }
+
+    // y values for the 3d graph, and an rgb colour per vertex if the surface mode comes with colours
+    fn get_3d_y_values_and_colors(
+        &self,
+        surface_mode: SurfaceMode,
+        time: f32,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        if let Some(simulation) = self.simulation.as_ref() {
+            return (simulation.heights().clone(), None);
+        }
+        match (surface_mode, self.complex_expression.as_ref()) {
+            (SurfaceMode::Terrain, _) => (self.terrain_heights.clone(), None),
+            (SurfaceMode::Complex, Some(complex_expression)) => {
+                let (heights, colors) = domain_coloring::get_domain_coloring(
+                    complex_expression,
+                    constants::GRID_SIZE,
+                    self.complex_height,
+                    time,
+                );
+                (heights, Some(colors))
+            }
+            _ => (common_funcs::get_updated_3d_y_values(time), None),
+        }
+    }
+}
+
+// the modes drawn by Graph3d, from y values on the grid
+fn is_height_field(surface_mode: SurfaceMode) -> bool {
+    match surface_mode {
+        SurfaceMode::Analytic
+        | SurfaceMode::Wave
+        | SurfaceMode::HeatExplicit
+        | SurfaceMode::HeatImplicit
+        | SurfaceMode::ReactionDiffusion
+        | SurfaceMode::Terrain
+        | SurfaceMode::Complex => true,
+        SurfaceMode::Parametric
+        | SurfaceMode::Isosurface
+        | SurfaceMode::Bars
+        | SurfaceMode::VectorField
+        | SurfaceMode::Ode => false,
     }
 }
@@ -0,0 +1,752 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+// ==== vectors, quaternions and 4x4 matrices for the 3d graph ==== //
+// conventions, the same as webgl's:
+//   - vectors are columns, and a matrix transforms one by multiplying it from the left: m * v
+//   - so a * b is "b first, then a". a model's world matrix is parent * local, and the whole
+//     chain on the way to the screen is projection * view * model
+//   - matrices are stored column major, so Mat4.0 goes straight into uniform_matrix4fv (transpose = false),
+//     and element (row r, column c) is at index 4 * c + r. the translation sits at 12, 13 and 14
+//   - right handed, angles in radians, positive = counterclockwise looking down the axis at the origin
+//   - the camera looks down -z, with y up. clip space depth runs -1 (near) to +1 (far)
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x: x, y: y, z: z }
+    }
+
+    pub fn from_array(values: [f32; 3]) -> Self {
+        Self::new(values[0], values[1], values[2])
+    }
+
+    pub fn to_array(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    pub fn dot(&self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    // the zero vector stays zero
+    pub fn normalize(&self) -> Vec3 {
+        let length = self.length();
+        if length < 1e-12 {
+            return *self;
+        }
+        *self * (1. / length)
+    }
+
+    pub fn lerp(&self, other: Vec3, t: f32) -> Vec3 {
+        *self + (other - *self) * t
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, factor: f32) -> Vec3 {
+        Vec3::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+// ---- rotations as unit quaternions: (x, y, z) = sin(angle / 2) * axis, w = cos(angle / 2) ---- //
+// like matrices, a * b is "b first, then a"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub fn identity() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            w: 1.,
+        }
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.).sin_cos();
+        Self {
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+            w: cos,
+        }
+    }
+
+    pub fn dot(&self, other: Quat) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn normalize(&self) -> Quat {
+        let length = self.dot(*self).sqrt();
+        if length < 1e-12 {
+            return Quat::identity();
+        }
+        Quat {
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+            w: self.w / length,
+        }
+    }
+
+    // spherical interpolation: constant angular speed from a (t = 0) to b (t = 1), the short way round
+    pub fn slerp(a: Quat, b: Quat, t: f32) -> Quat {
+        let mut cos_angle = a.dot(b);
+        // q and -q are the same rotation. pick the one on a's side, so we don't go the long way
+        let b = if cos_angle < 0. {
+            cos_angle = -cos_angle;
+            Quat {
+                x: -b.x,
+                y: -b.y,
+                z: -b.z,
+                w: -b.w,
+            }
+        } else {
+            b
+        };
+
+        // nearly the same rotation: sin(angle) is about 0, and a straight line is just as good
+        let (weight_a, weight_b) = if cos_angle > 0.9995 {
+            (1. - t, t)
+        } else {
+            let angle = cos_angle.acos();
+            let sin_angle = angle.sin();
+            (
+                ((1. - t) * angle).sin() / sin_angle,
+                (t * angle).sin() / sin_angle,
+            )
+        };
+        Quat {
+            x: weight_a * a.x + weight_b * b.x,
+            y: weight_a * a.y + weight_b * b.y,
+            z: weight_a * a.z + weight_b * b.z,
+            w: weight_a * a.w + weight_b * b.w,
+        }
+        .normalize()
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+    fn mul(self, b: Quat) -> Quat {
+        let a = self;
+        Quat {
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        }
+    }
+}
+
+// ---- 4x4 matrices, column major (see the top of the file) ---- //
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4(pub [f32; 16]);
+
+impl Mat4 {
+    #[rustfmt::skip]
+    pub fn identity() -> Self {
+        Mat4([
+            1., 0., 0., 0.,
+            0., 1., 0., 0.,
+            0., 0., 1., 0.,
+            0., 0., 0., 1.,
+        ])
+    }
+
+    // element at (row, column)
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.0[4 * column + row]
+    }
+
+    pub fn row(&self, row: usize) -> [f32; 4] {
+        [
+            self.0[row],
+            self.0[4 + row],
+            self.0[8 + row],
+            self.0[12 + row],
+        ]
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut protag = Mat4::identity();
+        protag.0[12] = offset.x;
+        protag.0[13] = offset.y;
+        protag.0[14] = offset.z;
+        protag
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut protag = Mat4::identity();
+        protag.0[0] = factors.x;
+        protag.0[5] = factors.y;
+        protag.0[10] = factors.z;
+        protag
+    }
+
+    #[rustfmt::skip]
+    pub fn rotation_x(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Mat4([
+            1., 0., 0., 0.,
+            0., cos, sin, 0.,
+            0., -sin, cos, 0.,
+            0., 0., 0., 1.,
+        ])
+    }
+
+    #[rustfmt::skip]
+    pub fn rotation_y(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Mat4([
+            cos, 0., -sin, 0.,
+            0., 1., 0., 0.,
+            sin, 0., cos, 0.,
+            0., 0., 0., 1.,
+        ])
+    }
+
+    #[rustfmt::skip]
+    pub fn rotation_z(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Mat4([
+            cos, sin, 0., 0.,
+            -sin, cos, 0., 0.,
+            0., 0., 1., 0.,
+            0., 0., 0., 1.,
+        ])
+    }
+
+    #[rustfmt::skip]
+    pub fn from_quat(q: Quat) -> Self {
+        let q = q.normalize();
+        let (x, y, z, w) = (q.x, q.y, q.z, q.w);
+        Mat4([
+            1. - 2. * (y * y + z * z), 2. * (x * y + z * w), 2. * (x * z - y * w), 0.,
+            2. * (x * y - z * w), 1. - 2. * (x * x + z * z), 2. * (y * z + x * w), 0.,
+            2. * (x * z + y * w), 2. * (y * z - x * w), 1. - 2. * (x * x + y * y), 0.,
+            0., 0., 0., 1.,
+        ])
+    }
+
+    // ---- projections, from view space (camera at the origin looking down -z) to clip space ---- //
+    // field_of_view is top to bottom, aspect is width / height. same as gluPerspective
+    #[rustfmt::skip]
+    pub fn perspective(field_of_view: f32, aspect: f32, z_near: f32, z_far: f32) -> Self {
+        let f = 1. / (field_of_view / 2.).tan();
+        let depth = z_near - z_far;
+        Mat4([
+            f / aspect, 0., 0., 0.,
+            0., f, 0., 0.,
+            0., 0., (z_far + z_near) / depth, -1.,
+            0., 0., 2. * z_far * z_near / depth, 0.,
+        ])
+    }
+
+    // the box from (left, bottom, -z_near) to (right, top, -z_far) onto clip space. same as glOrtho
+    #[rustfmt::skip]
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, z_near: f32, z_far: f32) -> Self {
+        let width = right - left;
+        let height = top - bottom;
+        let depth = z_far - z_near;
+        Mat4([
+            2. / width, 0., 0., 0.,
+            0., 2. / height, 0., 0.,
+            0., 0., -2. / depth, 0.,
+            -(right + left) / width, -(top + bottom) / height, -(z_far + z_near) / depth, 1.,
+        ])
+    }
+
+    // view matrix for a camera at eye, looking at target, with up roughly up
+    // (no caller: the graph's camera is built from its rotation angles instead)
+    #[allow(dead_code)]
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let backward = (eye - target).normalize(); // the camera looks down its -z
+        let right = up.cross(backward).normalize();
+        let true_up = backward.cross(right);
+        // the camera's axes are the rows: this undoes its orientation, after moving it to the origin
+        #[rustfmt::skip]
+        let protag = Mat4([
+            right.x, true_up.x, backward.x, 0.,
+            right.y, true_up.y, backward.y, 0.,
+            right.z, true_up.z, backward.z, 0.,
+            -right.dot(eye), -true_up.dot(eye), -backward.dot(eye), 1.,
+        ]);
+        protag
+    }
+
+    // ---- applying it ---- //
+    // (x, y, z, 1), without dividing by w
+    pub fn transform_homogeneous(&self, p: Vec3) -> [f32; 4] {
+        let m = &self.0;
+        let mut protag = [0.; 4];
+        for r in 0..4 {
+            protag[r] = m[r] * p.x + m[4 + r] * p.y + m[8 + r] * p.z + m[12 + r];
+        }
+        protag
+    }
+
+    // a direction (w = 0): translation doesn't apply
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0] * v.x + m[4] * v.y + m[8] * v.z,
+            m[1] * v.x + m[5] * v.y + m[9] * v.z,
+            m[2] * v.x + m[6] * v.y + m[10] * v.z,
+        )
+    }
+
+    // (no caller: normal_matrix does without it)
+    #[allow(dead_code)]
+    pub fn transpose(&self) -> Mat4 {
+        let mut protag = [0.; 16];
+        for row in 0..4 {
+            for column in 0..4 {
+                protag[4 * row + column] = self.0[4 * column + row];
+            }
+        }
+        Mat4(protag)
+    }
+
+    // none if the matrix squashes something flat (like a zero scale) and can't be undone
+    // (no caller: the pick ray is read straight off the rows instead, see common_funcs)
+    #[allow(dead_code)]
+    pub fn inverse(&self) -> Option<Mat4> {
+        // cofactors, 2x2 determinants of the bottom two rows and of the top two rows at a time
+        let m = |row: usize, column: usize| self.get(row, column);
+        let s0 = m(0, 0) * m(1, 1) - m(1, 0) * m(0, 1);
+        let s1 = m(0, 0) * m(1, 2) - m(1, 0) * m(0, 2);
+        let s2 = m(0, 0) * m(1, 3) - m(1, 0) * m(0, 3);
+        let s3 = m(0, 1) * m(1, 2) - m(1, 1) * m(0, 2);
+        let s4 = m(0, 1) * m(1, 3) - m(1, 1) * m(0, 3);
+        let s5 = m(0, 2) * m(1, 3) - m(1, 2) * m(0, 3);
+        let c5 = m(2, 2) * m(3, 3) - m(3, 2) * m(2, 3);
+        let c4 = m(2, 1) * m(3, 3) - m(3, 1) * m(2, 3);
+        let c3 = m(2, 1) * m(3, 2) - m(3, 1) * m(2, 2);
+        let c2 = m(2, 0) * m(3, 3) - m(3, 0) * m(2, 3);
+        let c1 = m(2, 0) * m(3, 2) - m(3, 0) * m(2, 2);
+        let c0 = m(2, 0) * m(3, 1) - m(3, 0) * m(2, 1);
+
+        let determinant = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inv = 1. / determinant;
+
+        // rows of the inverse, written out
+        let rows = [
+            [
+                m(1, 1) * c5 - m(1, 2) * c4 + m(1, 3) * c3,
+                -m(0, 1) * c5 + m(0, 2) * c4 - m(0, 3) * c3,
+                m(3, 1) * s5 - m(3, 2) * s4 + m(3, 3) * s3,
+                -m(2, 1) * s5 + m(2, 2) * s4 - m(2, 3) * s3,
+            ],
+            [
+                -m(1, 0) * c5 + m(1, 2) * c2 - m(1, 3) * c1,
+                m(0, 0) * c5 - m(0, 2) * c2 + m(0, 3) * c1,
+                -m(3, 0) * s5 + m(3, 2) * s2 - m(3, 3) * s1,
+                m(2, 0) * s5 - m(2, 2) * s2 + m(2, 3) * s1,
+            ],
+            [
+                m(1, 0) * c4 - m(1, 1) * c2 + m(1, 3) * c0,
+                -m(0, 0) * c4 + m(0, 1) * c2 - m(0, 3) * c0,
+                m(3, 0) * s4 - m(3, 1) * s2 + m(3, 3) * s0,
+                -m(2, 0) * s4 + m(2, 1) * s2 - m(2, 3) * s0,
+            ],
+            [
+                -m(1, 0) * c3 + m(1, 1) * c1 - m(1, 2) * c0,
+                m(0, 0) * c3 - m(0, 1) * c1 + m(0, 2) * c0,
+                -m(3, 0) * s3 + m(3, 1) * s1 - m(3, 2) * s0,
+                m(2, 0) * s3 - m(2, 1) * s1 + m(2, 2) * s0,
+            ],
+        ];
+        let mut protag = [0.; 16];
+        for row in 0..4 {
+            for column in 0..4 {
+                protag[4 * column + row] = rows[row][column] * inv;
+            }
+        }
+        Some(Mat4(protag))
+    }
+
+    // what normals get multiplied by, so they stay perpendicular to the surface under non-uniform
+    // scaling: the inverse transpose of the top left 3x3 (translation dropped). that's the 3x3's
+    // cofactors over its determinant, and the cofactors alone still work when the matrix flattens
+    // something (a scale of 0 on y leaves every normal pointing along y). only the direction
+    // counts, so normalize what comes out
+    pub fn normal_matrix(&self) -> Mat4 {
+        let m = |row: usize, column: usize| self.get(row, column);
+        // cofactor of (row, column): the 2x2 left after crossing both out, with the rows and columns
+        // taken cyclically so the sign comes out right by itself
+        let cofactor = |row: usize, column: usize| {
+            let (r1, r2) = ((row + 1) % 3, (row + 2) % 3);
+            let (c1, c2) = ((column + 1) % 3, (column + 2) % 3);
+            m(r1, c1) * m(r2, c2) - m(r1, c2) * m(r2, c1)
+        };
+        let determinant =
+            m(0, 0) * cofactor(0, 0) + m(0, 1) * cofactor(0, 1) + m(0, 2) * cofactor(0, 2);
+        let divide_by = if determinant.abs() < 1e-12 {
+            1.
+        } else {
+            determinant
+        };
+
+        let mut protag = Mat4::identity();
+        for row in 0..3 {
+            for column in 0..3 {
+                protag.0[4 * column + row] = cofactor(row, column) / divide_by;
+            }
+        }
+        protag
+    }
+
+    // the (translation, rotation, scale) that rebuild it as translation * rotation * scaling.
+    // only for matrices made that way: shears and projections don't survive
+    // (no caller: scene nodes keep their parts separately, so nothing takes them apart yet)
+    #[allow(dead_code)]
+    pub fn decompose(&self) -> (Vec3, Quat, Vec3) {
+        let m = &self.0;
+        let translation = Vec3::new(m[12], m[13], m[14]);
+        let columns = [
+            Vec3::new(m[0], m[1], m[2]),
+            Vec3::new(m[4], m[5], m[6]),
+            Vec3::new(m[8], m[9], m[10]),
+        ];
+        let mut scale = Vec3::new(
+            columns[0].length(),
+            columns[1].length(),
+            columns[2].length(),
+        );
+        // a mirror shows up as a negative determinant. put it on x
+        if columns[0].dot(columns[1].cross(columns[2])) < 0. {
+            scale.x = -scale.x;
+        }
+        let safe = |v: f32| if v.abs() < 1e-12 { 1. } else { v };
+        let x_axis = columns[0] * (1. / safe(scale.x));
+        let y_axis = columns[1] * (1. / safe(scale.y));
+        let z_axis = columns[2] * (1. / safe(scale.z));
+
+        // rotation matrix -> quaternion, going through whichever of w, x, y, z is largest for accuracy
+        let trace = x_axis.x + y_axis.y + z_axis.z;
+        let rotation = if trace > 0. {
+            let s = 2. * (trace + 1.).sqrt();
+            Quat {
+                w: s / 4.,
+                x: (y_axis.z - z_axis.y) / s,
+                y: (z_axis.x - x_axis.z) / s,
+                z: (x_axis.y - y_axis.x) / s,
+            }
+        } else if x_axis.x > y_axis.y && x_axis.x > z_axis.z {
+            let s = 2. * (1. + x_axis.x - y_axis.y - z_axis.z).sqrt();
+            Quat {
+                w: (y_axis.z - z_axis.y) / s,
+                x: s / 4.,
+                y: (y_axis.x + x_axis.y) / s,
+                z: (z_axis.x + x_axis.z) / s,
+            }
+        } else if y_axis.y > z_axis.z {
+            let s = 2. * (1. + y_axis.y - x_axis.x - z_axis.z).sqrt();
+            Quat {
+                w: (z_axis.x - x_axis.z) / s,
+                x: (y_axis.x + x_axis.y) / s,
+                y: s / 4.,
+                z: (z_axis.y + y_axis.z) / s,
+            }
+        } else {
+            let s = 2. * (1. + z_axis.z - x_axis.x - y_axis.y).sqrt();
+            Quat {
+                w: (x_axis.y - y_axis.x) / s,
+                x: (z_axis.x + x_axis.z) / s,
+                y: (z_axis.y + y_axis.z) / s,
+                z: s / 4.,
+            }
+        };
+
+        (translation, rotation.normalize(), scale)
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    // self * other: other first, then self
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut protag = [0.; 16];
+        for column in 0..4 {
+            for row in 0..4 {
+                protag[4 * column + row] = (0..4)
+                    .map(|k| self.get(row, k) * other.get(k, column))
+                    .sum();
+            }
+        }
+        Mat4(protag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::SeededRandom;
+
+    const CASES: usize = 200;
+
+    fn assert_close(a: &[f32], b: &[f32], tolerance: f32) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() <= tolerance, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn random_vec3(random: &mut SeededRandom, range: f32) -> Vec3 {
+        Vec3::new(
+            random.range(-range, range),
+            random.range(-range, range),
+            random.range(-range, range),
+        )
+    }
+
+    // an axis that's not too short to normalize
+    fn random_axis(random: &mut SeededRandom) -> Vec3 {
+        loop {
+            let axis = random_vec3(random, 1.);
+            if axis.length() > 0.1 {
+                return axis.normalize();
+            }
+        }
+    }
+
+    fn random_quat(random: &mut SeededRandom) -> Quat {
+        let angle = random.range(-std::f32::consts::PI, std::f32::consts::PI);
+        Quat::from_axis_angle(random_axis(random), angle)
+    }
+
+    // kept away from 0, so it can be undone
+    fn random_scale(random: &mut SeededRandom) -> Vec3 {
+        let mut factor = || random.range(0.2, 3.);
+        Vec3::new(factor(), factor(), factor())
+    }
+
+    fn random_trs(random: &mut SeededRandom) -> (Vec3, Quat, Vec3) {
+        let translation = random_vec3(random, 10.);
+        let rotation = random_quat(random);
+        (translation, rotation, random_scale(random))
+    }
+
+    fn compose(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+        Mat4::translation(translation) * Mat4::from_quat(rotation) * Mat4::scaling(scale)
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let mut random = SeededRandom::new(1);
+        for _ in 0..CASES {
+            let (translation, rotation, scale) = random_trs(&mut random);
+            let m = compose(translation, rotation, scale);
+            let inverse = m.inverse().unwrap();
+            assert_close(&(m * inverse).0, &Mat4::identity().0, 1e-4);
+            assert_close(&(inverse * m).0, &Mat4::identity().0, 1e-4);
+        }
+        // and a perspective projection, which isn't affine
+        let m = Mat4::perspective(0.8, 1.5, 0.1, 100.);
+        assert_close(&(m * m.inverse().unwrap()).0, &Mat4::identity().0, 1e-4);
+    }
+
+    #[test]
+    fn inverse_of_a_flattening_matrix_is_none() {
+        assert!(Mat4::scaling(Vec3::new(1., 0., 1.)).inverse().is_none());
+    }
+
+    #[test]
+    fn transpose_twice_is_the_same_matrix() {
+        let mut random = SeededRandom::new(2);
+        for _ in 0..CASES {
+            let mut values = [0.; 16];
+            for value in values.iter_mut() {
+                *value = random.range(-10., 10.);
+            }
+            let m = Mat4(values);
+            assert_eq!(m.transpose().transpose(), m);
+            assert_eq!(m.transpose().get(1, 3), m.get(3, 1));
+        }
+    }
+
+    #[test]
+    fn from_quat_agrees_with_the_axis_rotations() {
+        let mut random = SeededRandom::new(3);
+        for _ in 0..CASES {
+            let angle = random.range(-6., 6.);
+            let about = |x: f32, y: f32, z: f32| {
+                Mat4::from_quat(Quat::from_axis_angle(Vec3::new(x, y, z), angle))
+            };
+            assert_close(&about(1., 0., 0.).0, &Mat4::rotation_x(angle).0, 1e-5);
+            assert_close(&about(0., 1., 0.).0, &Mat4::rotation_y(angle).0, 1e-5);
+            assert_close(&about(0., 0., 1.).0, &Mat4::rotation_z(angle).0, 1e-5);
+
+            // any axis: the axis stays put, and a vector across it turns by the angle, counterclockwise
+            let axis = random_axis(&mut random);
+            let m = Mat4::from_quat(Quat::from_axis_angle(axis, angle));
+            assert_close(&m.transform_vector(axis).to_array(), &axis.to_array(), 1e-5);
+            let across = axis.cross(random_axis(&mut random)).normalize();
+            if across.length() < 0.5 {
+                continue;
+            }
+            let turned = m.transform_vector(across);
+            assert!((turned.dot(across) - angle.cos()).abs() < 1e-4);
+            assert!((axis.dot(across.cross(turned)) - angle.sin()).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn quat_product_is_the_matrix_product() {
+        let mut random = SeededRandom::new(4);
+        for _ in 0..CASES {
+            let a = random_quat(&mut random);
+            let b = random_quat(&mut random);
+            let expected = Mat4::from_quat(a) * Mat4::from_quat(b);
+            assert_close(&Mat4::from_quat(a * b).0, &expected.0, 1e-5);
+        }
+    }
+
+    #[test]
+    fn decompose_gives_back_the_parts() {
+        let mut random = SeededRandom::new(5);
+        for _ in 0..CASES {
+            let (translation, rotation, scale) = random_trs(&mut random);
+            let m = compose(translation, rotation, scale);
+            let (t, r, s) = m.decompose();
+            assert_close(&t.to_array(), &translation.to_array(), 1e-4);
+            assert_close(&s.to_array(), &scale.to_array(), 1e-4);
+            // q and -q are the same rotation
+            assert!(r.dot(rotation).abs() > 1. - 1e-4);
+            assert_close(&compose(t, r, s).0, &m.0, 1e-4);
+        }
+    }
+
+    #[test]
+    fn decompose_keeps_a_mirror() {
+        let m = compose(
+            Vec3::new(1., 2., 3.),
+            Quat::from_axis_angle(Vec3::new(0., 1., 0.), 0.7),
+            Vec3::new(2., -1., 0.5),
+        );
+        let (t, r, s) = m.decompose();
+        assert!(s.x * s.y * s.z < 0.);
+        assert_close(&compose(t, r, s).0, &m.0, 1e-4);
+    }
+
+    #[test]
+    fn slerp_hits_its_ends_and_stays_unit() {
+        let mut random = SeededRandom::new(6);
+        for _ in 0..CASES {
+            let a = random_quat(&mut random);
+            let b = random_quat(&mut random);
+            // the same rotation either way round
+            assert!(Quat::slerp(a, b, 0.).dot(a).abs() > 1. - 1e-5);
+            assert!(Quat::slerp(a, b, 1.).dot(b).abs() > 1. - 1e-5);
+            for step in 0..=10 {
+                let q = Quat::slerp(a, b, step as f32 / 10.);
+                assert!((q.dot(q) - 1.).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn slerp_turns_at_a_constant_speed() {
+        let a = Quat::identity();
+        let b = Quat::from_axis_angle(Vec3::new(0., 0., 1.), 2.);
+        let halfway = Mat4::from_quat(Quat::slerp(a, b, 0.5));
+        assert_close(&halfway.0, &Mat4::rotation_z(1.).0, 1e-5);
+    }
+
+    #[test]
+    fn normal_matrix_of_a_rotation_is_the_rotation() {
+        let mut random = SeededRandom::new(7);
+        for _ in 0..CASES {
+            let rotation = Mat4::from_quat(random_quat(&mut random));
+            assert_close(&rotation.normal_matrix().0, &rotation.0, 1e-5);
+            // translation makes no difference to normals
+            let moved = Mat4::translation(random_vec3(&mut random, 10.)) * rotation;
+            assert_close(&moved.normal_matrix().0, &rotation.0, 1e-5);
+        }
+    }
+
+    #[test]
+    fn normal_matrix_is_the_inverse_transpose() {
+        let mut random = SeededRandom::new(8);
+        for _ in 0..CASES {
+            let (_, rotation, scale) = random_trs(&mut random);
+            let m = compose(Vec3::new(0., 0., 0.), rotation, scale);
+            assert_close(
+                &m.normal_matrix().0,
+                &m.inverse().unwrap().transpose().0,
+                1e-4,
+            );
+        }
+    }
+
+    #[test]
+    fn look_at_puts_the_target_straight_ahead() {
+        let mut random = SeededRandom::new(9);
+        for _ in 0..CASES {
+            let eye = random_vec3(&mut random, 10.);
+            let target = random_vec3(&mut random, 10.);
+            let distance = (target - eye).length();
+            if distance < 0.1 || (target - eye).normalize().y.abs() > 0.99 {
+                continue;
+            }
+            let view = Mat4::look_at(eye, target, Vec3::new(0., 1., 0.));
+            let [x, y, z, _] = view.transform_homogeneous(target);
+            assert_close(&[x, y, z], &[0., 0., -distance], 1e-3);
+            assert_close(&view.transform_homogeneous(eye)[..3], &[0., 0., 0.], 1e-3);
+        }
+    }
+}
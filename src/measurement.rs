@@ -0,0 +1,320 @@
+use super::common_funcs as cf;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use wasm_bindgen::prelude::*;
+
+// what clicks on the height field do
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeasureTool {
+    Off,
+    Distance, // two points: distance, path along the surface, slope
+    Polygon,  // any number of points: projected and true surface area
+}
+
+pub fn get_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2) + (b[2] - a[2]).powi(2)).sqrt()
+}
+
+// of the straight line from a to b, in degrees. positive going uphill
+pub fn get_slope_degrees(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    let run = ((b[0] - a[0]).powi(2) + (b[2] - a[2]).powi(2)).sqrt();
+    (b[1] - a[1]).atan2(run).to_degrees()
+}
+
+pub fn get_path_length(path: &Vec<[f32; 3]>) -> f32 {
+    path.windows(2)
+        .map(|pair| get_distance(&pair[0], &pair[1]))
+        .sum()
+}
+
+// a surface path, kept with the heights it was worked out on so it's only worked out again when they change
+pub struct SurfacePath {
+    pub path: Vec<[f32; 3]>,
+    pub heights: Vec<f32>,
+}
+
+// ==== (approximately) shortest path along the surface from a to b, given as (x, z) ==== //
+// dijkstra over the grid points, each linked to its 16 nearest neighbours (knight's moves included,
+// so paths aren't stuck to 45 degree angles). the ends are joined to their nearest grid points
+pub fn get_surface_path(
+    heights: &Vec<f32>,
+    n: usize,
+    a: (f32, f32),
+    b: (f32, f32),
+) -> Vec<[f32; 3]> {
+    let points_per_row = n + 1;
+    let square_size = 2. / n as f32;
+    let nearest = |(x, z): (f32, f32)| -> usize {
+        let grid_x = (((x + 1.) / square_size).round().max(0.) as usize).min(n);
+        let grid_z = (((z + 1.) / square_size).round().max(0.) as usize).min(n);
+        grid_z * points_per_row + grid_x
+    };
+    let point = |i: usize| -> [f32; 3] {
+        [
+            -1. + (i % points_per_row) as f32 * square_size,
+            heights[i],
+            -1. + (i / points_per_row) as f32 * square_size,
+        ]
+    };
+    let (start, end) = (nearest(a), nearest(b));
+
+    let mut distances = vec![std::f32::INFINITY; points_per_row * points_per_row];
+    let mut previous = vec![usize::max_value(); points_per_row * points_per_row];
+    let mut queue = BinaryHeap::new();
+    distances[start] = 0.;
+    queue.push(QueueEntry {
+        distance: 0.,
+        index: start,
+    });
+    let steps: [(i64, i64); 16] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+        (2, 1),
+        (2, -1),
+        (-2, 1),
+        (-2, -1),
+        (1, 2),
+        (1, -2),
+        (-1, 2),
+        (-1, -2),
+    ];
+    while let Some(QueueEntry { distance, index }) = queue.pop() {
+        if index == end {
+            break;
+        }
+        if distance > distances[index] {
+            continue; // already got here a shorter way
+        }
+        let (x, z) = (
+            (index % points_per_row) as i64,
+            (index / points_per_row) as i64,
+        );
+        for &(step_x, step_z) in steps.iter() {
+            let (next_x, next_z) = (x + step_x, z + step_z);
+            if next_x < 0 || next_z < 0 || next_x > n as i64 || next_z > n as i64 {
+                continue;
+            }
+            let next = next_z as usize * points_per_row + next_x as usize;
+            let next_distance = distance + get_distance(&point(index), &point(next));
+            if next_distance < distances[next] {
+                distances[next] = next_distance;
+                previous[next] = index;
+                queue.push(QueueEntry {
+                    distance: next_distance,
+                    index: next,
+                });
+            }
+        }
+    }
+
+    let mut protag: Vec<[f32; 3]> = vec![[b.0, cf::get_height_at(heights, n, b.0, b.1), b.1]];
+    let mut index = end;
+    loop {
+        protag.push(point(index));
+        if index == start || previous[index] == usize::max_value() {
+            break;
+        }
+        index = previous[index];
+    }
+    protag.push([a.0, cf::get_height_at(heights, n, a.0, a.1), a.1]);
+    protag.reverse();
+    protag
+}
+
+// the smallest distance comes out of the heap first
+struct QueueEntry {
+    distance: f32,
+    index: usize,
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+// ==== areas inside a polygon of (x, z) points ==== //
+// shoelace, on the flat x-z plane
+pub fn get_projected_area(polygon: &Vec<(f32, f32)>) -> f32 {
+    get_signed_area(polygon).abs()
+}
+
+// the area of the surface itself above the polygon. every grid triangle is flat, so the part of it
+// inside the polygon is its flat (projected) area stretched by how much the triangle is tilted
+pub fn get_surface_area(heights: &Vec<f32>, n: usize, polygon: &Vec<(f32, f32)>) -> f32 {
+    if polygon.len() < 3 {
+        return 0.;
+    }
+    let square_size = 2. / n as f32;
+    let points_per_row = n + 1;
+    let point = |x: usize, z: usize| -> [f32; 3] {
+        [
+            -1. + x as f32 * square_size,
+            heights[z * points_per_row + x],
+            -1. + z as f32 * square_size,
+        ]
+    };
+
+    // only the cells under the polygon's bounding box can have any of it
+    let to_cell = |coord: f32| -> usize {
+        (((coord + 1.) / square_size).floor().max(0.) as usize).min(n - 1)
+    };
+    let (x_min, x_max, z_min, z_max) = polygon.iter().fold(
+        (
+            std::f32::INFINITY,
+            std::f32::NEG_INFINITY,
+            std::f32::INFINITY,
+            std::f32::NEG_INFINITY,
+        ),
+        |(x_min, x_max, z_min, z_max), &(x, z)| {
+            (x_min.min(x), x_max.max(x), z_min.min(z), z_max.max(z))
+        },
+    );
+
+    let mut protag = 0.;
+    for z in to_cell(z_min)..=to_cell(z_max) {
+        for x in to_cell(x_min)..=to_cell(x_max) {
+            // same two triangles as get_position_grid_n_by_n
+            for triangle in [
+                [point(x, z), point(x, z + 1), point(x + 1, z + 1)],
+                [point(x, z), point(x + 1, z + 1), point(x + 1, z)],
+            ]
+            .iter()
+            {
+                let flat_triangle: Vec<(f32, f32)> =
+                    triangle.iter().map(|p| (p[0], p[2])).collect();
+                let inside = clip_polygon(polygon, &flat_triangle);
+                if inside.len() < 3 {
+                    continue;
+                }
+                let flat_area = get_projected_area(&flat_triangle);
+                let edge_1 = [
+                    triangle[1][0] - triangle[0][0],
+                    triangle[1][1] - triangle[0][1],
+                    triangle[1][2] - triangle[0][2],
+                ];
+                let edge_2 = [
+                    triangle[2][0] - triangle[0][0],
+                    triangle[2][1] - triangle[0][1],
+                    triangle[2][2] - triangle[0][2],
+                ];
+                let true_area = 0.5
+                    * ((edge_1[1] * edge_2[2] - edge_1[2] * edge_2[1]).powi(2)
+                        + (edge_1[2] * edge_2[0] - edge_1[0] * edge_2[2]).powi(2)
+                        + (edge_1[0] * edge_2[1] - edge_1[1] * edge_2[0]).powi(2))
+                    .sqrt();
+                protag += get_projected_area(&inside) * true_area / flat_area;
+            }
+        }
+    }
+    protag
+}
+
+// sutherland-hodgman: the part of polygon inside the convex clip polygon. the polygon itself can be
+// concave. the result may then have some zero width bits, which don't change its area
+fn clip_polygon(polygon: &Vec<(f32, f32)>, clip: &Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    // which way round the clip polygon goes decides which side of each edge is inside
+    let orientation = get_signed_area(clip).signum();
+    let mut protag = polygon.clone();
+    for i in 0..clip.len() {
+        if protag.is_empty() {
+            break;
+        }
+        let edge_start = clip[i];
+        let edge_end = clip[(i + 1) % clip.len()];
+        let side = |p: (f32, f32)| {
+            orientation
+                * ((edge_end.0 - edge_start.0) * (p.1 - edge_start.1)
+                    - (edge_end.1 - edge_start.1) * (p.0 - edge_start.0))
+        };
+
+        let input = protag;
+        protag = Vec::with_capacity(input.len() + 2);
+        for j in 0..input.len() {
+            let current = input[j];
+            let next = input[(j + 1) % input.len()];
+            let (side_current, side_next) = (side(current), side(next));
+            if side_current >= 0. {
+                protag.push(current);
+            }
+            if (side_current >= 0.) != (side_next >= 0.) {
+                let t = side_current / (side_current - side_next);
+                protag.push((
+                    current.0 + t * (next.0 - current.0),
+                    current.1 + t * (next.1 - current.1),
+                ));
+            }
+        }
+    }
+    protag
+}
+
+// positive going one way round, negative the other
+fn get_signed_area(polygon: &Vec<(f32, f32)>) -> f32 {
+    let mut twice_area = 0.;
+    for i in 0..polygon.len() {
+        let (x_a, z_a) = polygon[i];
+        let (x_b, z_b) = polygon[(i + 1) % polygon.len()];
+        twice_area += x_a * z_b - x_b * z_a;
+    }
+    0.5 * twice_area
+}
+
+// ==== lines that follow the surface between (x, z) points, for GL::LINES ==== //
+// each edge is cut into short steps so it hugs the surface instead of cutting through it
+pub fn get_draped_segments(
+    heights: &Vec<f32>,
+    n: usize,
+    points: &Vec<(f32, f32)>,
+    closed: bool,
+    y_offset: f32,
+) -> Vec<f32> {
+    let square_size = 2. / n as f32;
+    let edge_count = if closed && points.len() > 2 {
+        points.len()
+    } else {
+        points.len().saturating_sub(1)
+    };
+
+    let mut protag: Vec<f32> = Vec::new();
+    for i in 0..edge_count {
+        let (x_a, z_a) = points[i];
+        let (x_b, z_b) = points[(i + 1) % points.len()];
+        let length = ((x_b - x_a).powi(2) + (z_b - z_a).powi(2)).sqrt();
+        let steps = ((2. * length / square_size).ceil() as usize).max(1);
+        let at = |step: usize| -> [f32; 3] {
+            let t = step as f32 / steps as f32;
+            let (x, z) = (x_a + t * (x_b - x_a), z_a + t * (z_b - z_a));
+            [x, cf::get_height_at(heights, n, x, z) + y_offset, z]
+        };
+        for step in 0..steps {
+            protag.extend_from_slice(&at(step));
+            protag.extend_from_slice(&at(step + 1));
+        }
+    }
+    protag
+}
@@ -0,0 +1,286 @@
+use super::constants::*;
+use super::domain_coloring::hsv_to_rgb;
+use super::expression::Expr;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attractor {
+    Lorenz,
+    Rossler,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OdeMethod {
+    Rk4,  // fixed step
+    Rk45, // dormand-prince, adaptive step
+}
+
+// dx/dt, dy/dt and dz/dt as expressions of x, y, z and t
+pub struct OdeSystem {
+    pub derivatives: [Expr; 3],
+}
+
+impl OdeSystem {
+    pub fn compile(dx: &str, dy: &str, dz: &str) -> Result<Self, String> {
+        let variable_names = ["x", "y", "z", "t"];
+        Ok(Self {
+            derivatives: [
+                Expr::compile(dx, &variable_names)?,
+                Expr::compile(dy, &variable_names)?,
+                Expr::compile(dz, &variable_names)?,
+            ],
+        })
+    }
+
+    pub fn get(&self, t: f32, state: &[f32; 3]) -> [f32; 3] {
+        let variables = [state[0], state[1], state[2], t];
+        [
+            self.derivatives[0].eval(&variables),
+            self.derivatives[1].eval(&variables),
+            self.derivatives[2].eval(&variables),
+        ]
+    }
+}
+
+// the classic parameters, and a starting point on (or near) the attractor
+pub fn get_attractor_system(attractor: Attractor) -> ([&'static str; 3], [f32; 3]) {
+    match attractor {
+        Attractor::Lorenz => (
+            ["10 * (y - x)", "x * (28 - z) - y", "x * y - 8 / 3 * z"],
+            [1., 1., 1.],
+        ),
+        Attractor::Rossler => (
+            ["-y - z", "x + 0.2 * y", "0.2 + z * (x - 5.7)"],
+            [1., 1., 0.],
+        ),
+    }
+}
+
+// the state at each step, and when it was reached
+pub struct Trajectory {
+    pub times: Vec<f32>,
+    pub points: Vec<[f32; 3]>,
+}
+
+// ==== integrates from the initial state for duration, with either method ==== //
+pub fn integrate(
+    system: &OdeSystem,
+    method: OdeMethod,
+    initial: &[f32; 3],
+    duration: f32,
+) -> Trajectory {
+    let mut protag = Trajectory {
+        times: vec![0.],
+        points: vec![*initial],
+    };
+    let mut t = 0.;
+    let mut state = *initial;
+    let mut h = ODE_STEP;
+
+    while t < duration {
+        h = h.min(duration - t);
+        match method {
+            OdeMethod::Rk4 => {
+                state = rk4_step(system, t, &state, h);
+                t += h;
+            }
+            OdeMethod::Rk45 => {
+                let (next_state, error) = rk45_step(system, t, &state, h);
+                // the usual controller: grow or shrink h so the next error lands near the tolerance
+                let factor = if error > 0. {
+                    0.9 * (ODE_TOLERANCE / error).powf(0.2)
+                } else {
+                    5.
+                };
+                if error <= ODE_TOLERANCE || h <= ODE_MIN_STEP {
+                    state = next_state;
+                    t += h;
+                    h = (h * factor.min(5.)).max(ODE_MIN_STEP).min(ODE_MAX_STEP);
+                } else {
+                    h = (h * factor.max(0.2)).max(ODE_MIN_STEP);
+                    continue;
+                }
+            }
+        }
+        if !state.iter().all(|value| value.is_finite()) {
+            break; // blew up, nothing more worth drawing
+        }
+        protag.times.push(t);
+        protag.points.push(state);
+    }
+
+    protag
+}
+
+pub fn rk4_step(system: &OdeSystem, t: f32, state: &[f32; 3], h: f32) -> [f32; 3] {
+    let k1 = system.get(t, state);
+    let k2 = system.get(t + 0.5 * h, &add_scaled(state, &[(&k1, 0.5 * h)]));
+    let k3 = system.get(t + 0.5 * h, &add_scaled(state, &[(&k2, 0.5 * h)]));
+    let k4 = system.get(t + h, &add_scaled(state, &[(&k3, h)]));
+
+    add_scaled(
+        state,
+        &[(&k1, h / 6.), (&k2, h / 3.), (&k3, h / 3.), (&k4, h / 6.)],
+    )
+}
+
+// dormand-prince 5(4). returns the 5th order step and how far the embedded 4th order one is from it
+pub fn rk45_step(system: &OdeSystem, t: f32, state: &[f32; 3], h: f32) -> ([f32; 3], f32) {
+    let k1 = system.get(t, state);
+    let k2 = system.get(t + h / 5., &add_scaled(state, &[(&k1, h / 5.)]));
+    let k3 = system.get(
+        t + 3. * h / 10.,
+        &add_scaled(state, &[(&k1, 3. * h / 40.), (&k2, 9. * h / 40.)]),
+    );
+    let k4 = system.get(
+        t + 4. * h / 5.,
+        &add_scaled(
+            state,
+            &[
+                (&k1, 44. * h / 45.),
+                (&k2, -56. * h / 15.),
+                (&k3, 32. * h / 9.),
+            ],
+        ),
+    );
+    let k5 = system.get(
+        t + 8. * h / 9.,
+        &add_scaled(
+            state,
+            &[
+                (&k1, 19372. * h / 6561.),
+                (&k2, -25360. * h / 2187.),
+                (&k3, 64448. * h / 6561.),
+                (&k4, -212. * h / 729.),
+            ],
+        ),
+    );
+    let k6 = system.get(
+        t + h,
+        &add_scaled(
+            state,
+            &[
+                (&k1, 9017. * h / 3168.),
+                (&k2, -355. * h / 33.),
+                (&k3, 46732. * h / 5247.),
+                (&k4, 49. * h / 176.),
+                (&k5, -5103. * h / 18656.),
+            ],
+        ),
+    );
+    let fifth = add_scaled(
+        state,
+        &[
+            (&k1, 35. * h / 384.),
+            (&k3, 500. * h / 1113.),
+            (&k4, 125. * h / 192.),
+            (&k5, -2187. * h / 6784.),
+            (&k6, 11. * h / 84.),
+        ],
+    );
+    let k7 = system.get(t + h, &fifth);
+    let fourth = add_scaled(
+        state,
+        &[
+            (&k1, 5179. * h / 57600.),
+            (&k3, 7571. * h / 16695.),
+            (&k4, 393. * h / 640.),
+            (&k5, -92097. * h / 339200.),
+            (&k6, 187. * h / 2100.),
+            (&k7, h / 40.),
+        ],
+    );
+
+    // relative to the size of the state, so big attractors don't need a tiny step
+    let error = (0..3)
+        .map(|i| (fifth[i] - fourth[i]).abs() / (1. + state[i].abs()))
+        .fold(0., f32::max);
+    (fifth, error)
+}
+
+fn add_scaled(state: &[f32; 3], terms: &[(&[f32; 3], f32)]) -> [f32; 3] {
+    let mut protag = *state;
+    for (k, scale) in terms.iter() {
+        for i in 0..3 {
+            protag[i] += scale * k[i];
+        }
+    }
+    protag
+}
+
+// ==== a bundle of trajectories, each started a hair away from the last ==== //
+// the tiny differences grow until the trajectories go their own ways (if the system is chaotic).
+// all of them are then squeezed into the graph's box together, with the system's z pointing up
+pub fn get_diverging_trajectories(
+    system: &OdeSystem,
+    method: OdeMethod,
+    initial: &[f32; 3],
+    count: usize,
+) -> Vec<Trajectory> {
+    let mut protag: Vec<Trajectory> = (0..count)
+        .map(|i| {
+            let start = [
+                initial[0] + i as f32 * ODE_PERTURBATION,
+                initial[1],
+                initial[2],
+            ];
+            integrate(system, method, &start, ODE_DURATION)
+        })
+        .collect();
+
+    let mut min = [std::f32::INFINITY; 3];
+    let mut max = [std::f32::NEG_INFINITY; 3];
+    for trajectory in protag.iter() {
+        for point in trajectory.points.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(point[i]);
+                max[i] = max[i].max(point[i]);
+            }
+        }
+    }
+    let largest_half_size = (0..3).map(|i| 0.5 * (max[i] - min[i])).fold(0., f32::max);
+    let scale = if largest_half_size > 0. {
+        ODE_BOX_FILL / largest_half_size
+    } else {
+        1.
+    };
+    for trajectory in protag.iter_mut() {
+        for point in trajectory.points.iter_mut() {
+            let centered: Vec<f32> = (0..3)
+                .map(|i| (point[i] - 0.5 * (min[i] + max[i])) * scale)
+                .collect();
+            *point = [centered[0], centered[2], centered[1]]; // the graph's y is up
+        }
+    }
+
+    protag
+}
+
+// ==== the last ODE_TRAIL_LENGTH (in the system's time) of each trajectory, up to time ==== //
+// (positions, colors) per trajectory, for Line3d. each trajectory has its own hue, fading out along the trail
+pub fn get_trails(trajectories: &Vec<Trajectory>, time: f32) -> Vec<(Vec<f32>, Vec<f32>)> {
+    let mut protag: Vec<(Vec<f32>, Vec<f32>)> = Vec::new();
+    for (i, trajectory) in trajectories.iter().enumerate() {
+        let hue = i as f32 / trajectories.len() as f32;
+        let mut positions: Vec<f32> = Vec::new();
+        let mut colors: Vec<f32> = Vec::new();
+        for (&t, point) in trajectory.times.iter().zip(trajectory.points.iter()) {
+            if t > time {
+                break;
+            }
+            let age = time - t;
+            if age > ODE_TRAIL_LENGTH {
+                continue;
+            }
+            positions.extend_from_slice(point);
+            colors.extend_from_slice(&hsv_to_rgb(hue, 0.75, 1. - 0.8 * age / ODE_TRAIL_LENGTH));
+        }
+        if positions.len() >= 6 {
+            protag.push((positions, colors));
+        }
+    }
+
+    protag
+}
@@ -0,0 +1,210 @@
+use std::f32::consts::PI;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParametricSurface {
+    Sphere,
+    Torus,
+    MobiusStrip,
+    KleinBottle,
+    SurfaceOfRevolution,
+}
+
+// how the two ends of a parameter's range meet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Seam {
+    Open,     // they don't (e.g. the width of a mobius strip)
+    Periodic, // they are the same points (e.g. going round a torus)
+    Twisted,  // they are the same points, but the other parameter runs backwards (mobius, klein)
+}
+
+pub struct ParameterDomain {
+    pub u_start: f32,
+    pub u_end: f32,
+    pub u_seam: Seam,
+    pub v_start: f32,
+    pub v_end: f32,
+    pub v_seam: Seam,
+}
+
+pub fn get_domain(surface: ParametricSurface) -> ParameterDomain {
+    let (v_start, v_end, v_seam) = match surface {
+        ParametricSurface::Sphere => (0., PI, Seam::Open), // v = 0 and v = pi are the poles
+        ParametricSurface::Torus | ParametricSurface::KleinBottle => (0., 2. * PI, Seam::Periodic),
+        ParametricSurface::MobiusStrip => (-1., 1., Seam::Open),
+        ParametricSurface::SurfaceOfRevolution => (0., 1., Seam::Open),
+    };
+    let u_seam = match surface {
+        ParametricSurface::MobiusStrip | ParametricSurface::KleinBottle => Seam::Twisted,
+        _ => Seam::Periodic,
+    };
+
+    ParameterDomain {
+        u_start: 0.,
+        u_end: 2. * PI,
+        u_seam: u_seam,
+        v_start: v_start,
+        v_end: v_end,
+        v_seam: v_seam,
+    }
+}
+
+// ==== (u, v) -> (x, y, z). everything is sized to fit in the -1 to +1 box the 3d graph lives in ==== //
+pub fn evaluate(surface: ParametricSurface, u: f32, v: f32, time: f32) -> [f32; 3] {
+    match surface {
+        ParametricSurface::Sphere => {
+            let radius = 0.8;
+            [
+                radius * v.sin() * u.cos(),
+                radius * v.cos(),
+                radius * v.sin() * u.sin(),
+            ]
+        }
+        ParametricSurface::Torus => {
+            let (major_radius, minor_radius) = (0.6, 0.25);
+            let ring = major_radius + minor_radius * v.cos();
+            [ring * u.cos(), minor_radius * v.sin(), ring * u.sin()]
+        }
+        ParametricSurface::MobiusStrip => {
+            // v goes across the strip, which turns half way round while u goes once round the loop
+            let scale = 0.7;
+            let ring = 1. + 0.5 * v * (0.5 * u).cos();
+            [
+                scale * ring * u.cos(),
+                scale * 0.5 * v * (0.5 * u).sin(),
+                scale * ring * u.sin(),
+            ]
+        }
+        ParametricSurface::KleinBottle => {
+            // the "figure 8" immersion. the tube's cross section is a figure 8 that turns half way round
+            let scale = 0.3;
+            let ring = 2. + (0.5 * u).cos() * v.sin() - (0.5 * u).sin() * (2. * v).sin();
+            [
+                scale * ring * u.cos(),
+                scale * ((0.5 * u).sin() * v.sin() + (0.5 * u).cos() * (2. * v).sin()),
+                scale * ring * u.sin(),
+            ]
+        }
+        ParametricSurface::SurfaceOfRevolution => {
+            // a vase: a wavy profile radius(v) spun round the y axis. the waves travel up over time
+            let radius =
+                0.3 + 0.2 * (1.5 * PI * v).sin() + 0.04 * (6. * PI * v - time / 500.).sin();
+            [radius * u.cos(), -0.8 + 1.6 * v, radius * u.sin()]
+        }
+    }
+}
+
+// ==== positions and normals for an n by n grid over the surface's (u, v) domain ==== //
+// laid out like get_position_grid_n_by_n lays out its x-z grid (u along x, v along z),
+// so the same indices draw it. returns (positions, normals), 3 floats per vertex each
+pub fn get_parametric_mesh(
+    surface: ParametricSurface,
+    n: usize,
+    time: f32,
+) -> (Vec<f32>, Vec<f32>) {
+    let domain = get_domain(surface);
+    let points_per_row = n + 1;
+    let mut positions: Vec<f32> = vec![0.; 3 * points_per_row * points_per_row];
+    let mut normals: Vec<f32> = vec![0.; 3 * points_per_row * points_per_row];
+
+    let u_step = (domain.u_end - domain.u_start) / n as f32;
+    let v_step = (domain.v_end - domain.v_start) / n as f32;
+
+    for row in 0..points_per_row {
+        for column in 0..points_per_row {
+            let start_i = 3 * (row * points_per_row + column);
+            let u = domain.u_start + column as f32 * u_step;
+            let v = domain.v_start + row as f32 * v_step;
+
+            positions[start_i..start_i + 3].copy_from_slice(&evaluate(surface, u, v, time));
+            normals[start_i..start_i + 3]
+                .copy_from_slice(&get_parametric_normal(surface, &domain, u, v, time));
+        }
+    }
+
+    // ---- seams ---- //
+    // the last column/row lands on the first one again. evaluating the same point from the other end
+    // gives slightly different floats, which shows up as a crack, so copy the first one over exactly
+    for i in 0..points_per_row {
+        let seam_partner = |seam: Seam| match seam {
+            Seam::Twisted => Some(n - i),
+            Seam::Periodic => Some(i),
+            Seam::Open => None,
+        };
+        if let Some(partner_row) = seam_partner(domain.u_seam) {
+            let from = 3 * (partner_row * points_per_row);
+            let to = 3 * (i * points_per_row + n);
+            positions.copy_within(from..from + 3, to);
+        }
+        if let Some(partner_column) = seam_partner(domain.v_seam) {
+            let from = 3 * partner_column;
+            let to = 3 * (n * points_per_row + i);
+            positions.copy_within(from..from + 3, to);
+        }
+    }
+
+    (positions, normals)
+}
+
+// ---- normal = (dP/du) x (dP/dv), with the partial derivatives by central differences ---- //
+// we difference the function itself rather than the grid, so vertices on a seam see their neighbours
+// on the far side of it and the shading runs smoothly across
+pub fn get_parametric_normal(
+    surface: ParametricSurface,
+    domain: &ParameterDomain,
+    u: f32,
+    v: f32,
+    time: f32,
+) -> [f32; 3] {
+    let h_u = 1e-3 * (domain.u_end - domain.u_start);
+    let h_v = 1e-3 * (domain.v_end - domain.v_start);
+
+    let normal_at = |v: f32| {
+        let u_plus = evaluate(surface, u + h_u, v, time);
+        let u_minus = evaluate(surface, u - h_u, v, time);
+        let v_plus = evaluate(surface, u, v + h_v, time);
+        let v_minus = evaluate(surface, u, v - h_v, time);
+        let d_u = [
+            u_plus[0] - u_minus[0],
+            u_plus[1] - u_minus[1],
+            u_plus[2] - u_minus[2],
+        ];
+        let d_v = [
+            v_plus[0] - v_minus[0],
+            v_plus[1] - v_minus[1],
+            v_plus[2] - v_minus[2],
+        ];
+        [
+            d_u[1] * d_v[2] - d_u[2] * d_v[1],
+            d_u[2] * d_v[0] - d_u[0] * d_v[2],
+            d_u[0] * d_v[1] - d_u[1] * d_v[0],
+        ]
+    };
+
+    let mut normal = normal_at(v);
+    let mut normal_size =
+        (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+
+    // at a pole (sphere at v = 0 or pi) dP/du vanishes and so does the cross product.
+    // the normal there is the limit from just inside the domain, so step in a little and ask again
+    if normal_size < 1e-9 {
+        let nudge = if v - domain.v_start < domain.v_end - v {
+            1e-2
+        } else {
+            -1e-2
+        };
+        normal = normal_at(v + nudge * (domain.v_end - domain.v_start));
+        normal_size =
+            (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    }
+    if normal_size < 1e-9 {
+        return [0., 1., 0.];
+    }
+
+    [
+        normal[0] / normal_size,
+        normal[1] / normal_size,
+        normal[2] / normal_size,
+    ]
+}
@@ -0,0 +1,232 @@
+use super::super::bar_chart::FLOATS_PER_BAR;
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// 3d bar charts. one unit cube, drawn once per bar with ANGLE_instanced_arrays: the offset, scale and
+// colour of each bar come from the instances buffer (see bar_chart::get_bar_instances), one set per cube.
+// lit the same way as the 3d graph
+pub struct Bars3d {
+    program: WebGlProgram,
+    instanced_arrays: Option<AngleInstancedArrays>, // see load_extension
+    cube_positions_buffer: WebGlBuffer,
+    cube_normals_buffer: WebGlBuffer,
+    cube_indices_buffer: WebGlBuffer,
+    cube_index_count: i32,
+    instances_buffer: WebGlBuffer,
+    bar_count: i32,
+    matrix_uniforms: cf::MatrixUniforms,
+    u_opacity: WebGlUniformLocation,
+}
+
+impl Bars3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::bars_3d::SHADER,
+            super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+            &["aPosition", "aVertexNormal", "aOffset", "aScale", "aColor"],
+        )
+        .unwrap();
+
+        let (positions, normals, indices) = get_unit_cube();
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+
+        let cube_positions_buffer = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for cube positions")
+            .unwrap();
+        let positions_location = positions.as_ptr() as u32 / 4;
+        let positions_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            positions_location,
+            positions_location + positions.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&cube_positions_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &positions_array, GL::STATIC_DRAW);
+
+        let cube_normals_buffer = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for cube normals")
+            .unwrap();
+        let normals_location = normals.as_ptr() as u32 / 4;
+        let normals_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(normals_location, normals_location + normals.len() as u32);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&cube_normals_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &normals_array, GL::STATIC_DRAW);
+
+        let cube_indices_buffer = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for cube indices")
+            .unwrap();
+        let indices_location = indices.as_ptr() as u32 / 2;
+        let indices_array = js_sys::Uint16Array::new(&memory_buffer)
+            .subarray(indices_location, indices_location + indices.len() as u32);
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&cube_indices_buffer));
+        gl.buffer_data_with_array_buffer_view(
+            GL::ELEMENT_ARRAY_BUFFER,
+            &indices_array,
+            GL::STATIC_DRAW,
+        );
+
+        Self {
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            instanced_arrays: None,
+            cube_positions_buffer: cube_positions_buffer,
+            cube_normals_buffer: cube_normals_buffer,
+            cube_indices_buffer: cube_indices_buffer,
+            cube_index_count: indices.len() as i32,
+            instances_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for bar instances")
+                .unwrap(),
+            bar_count: 0,
+        }
+    }
+
+    // webgl 1 draws instances through ANGLE_instanced_arrays, which is available pretty much everywhere.
+    // it's looked up the first time bars are wanted rather than in new, so without it only the bar
+    // charts are missing
+    pub fn load_extension(&mut self, gl: &WebGlRenderingContext) -> Result<(), String> {
+        if self.instanced_arrays.is_none() {
+            self.instanced_arrays = Some(
+                cf::get_extension(gl, "ANGLE_instanced_arrays", "bar charts")?
+                    .unchecked_into::<AngleInstancedArrays>(),
+            );
+        }
+        Ok(())
+    }
+
+    // FLOATS_PER_BAR per bar, from bar_chart::get_bar_instances
+    pub fn set_bars(&mut self, gl: &WebGlRenderingContext, instances: &Vec<f32>) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let instances_location = instances.as_ptr() as u32 / 4;
+        let instances_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            instances_location,
+            instances_location + instances.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instances_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &instances_array, GL::STATIC_DRAW);
+
+        self.bar_count = (instances.len() / FLOATS_PER_BAR) as i32;
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+    ) {
+        let instanced_arrays = match &self.instanced_arrays {
+            Some(instanced_arrays) if self.bar_count > 0 => instanced_arrays,
+            _ => return,
+        };
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+
+        // the cube, the same for every bar
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.cube_positions_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.cube_normals_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+
+        // offset, scale and colour move on once per bar (divisor 1) instead of once per vertex
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instances_buffer));
+        let stride = (FLOATS_PER_BAR * 4) as i32;
+        for i in 0..3 {
+            let attribute = 2 + i as u32;
+            gl.vertex_attrib_pointer_with_i32(attribute, 3, GL::FLOAT, false, stride, 3 * 4 * i);
+            gl.enable_vertex_attrib_array(attribute);
+            instanced_arrays.vertex_attrib_divisor_angle(attribute, 1);
+        }
+
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LESS);
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.cube_indices_buffer));
+        instanced_arrays.draw_elements_instanced_angle_with_i32(
+            GL::TRIANGLES,
+            self.cube_index_count,
+            GL::UNSIGNED_SHORT,
+            0,
+            self.bar_count,
+        );
+        gl.disable(GL::DEPTH_TEST);
+
+        // done with the attribute arrays. divisors back to 0, the other programs don't expect them
+        for attribute in 0..5 {
+            if attribute >= 2 {
+                instanced_arrays.vertex_attrib_divisor_angle(attribute, 0);
+            }
+            gl.disable_vertex_attrib_array(attribute);
+        }
+    }
+}
+
+// 0 to 1 on every axis. 4 vertices per face rather than 8 shared corners, so each face has its own normal
+fn get_unit_cube() -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let mut positions: Vec<f32> = Vec::with_capacity(6 * 4 * 3);
+    let mut normals: Vec<f32> = Vec::with_capacity(6 * 4 * 3);
+    let mut indices: Vec<u16> = Vec::with_capacity(6 * 6);
+
+    for axis in 0..3 {
+        for &side in [0., 1.].iter() {
+            let mut normal = [0.; 3];
+            normal[axis] = if side == 0. { -1. } else { 1. };
+            // the other two axes, walked around the face
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let first_vertex = (positions.len() / 3) as u16;
+            for &(corner_u, corner_v) in [(0., 0.), (1., 0.), (1., 1.), (0., 1.)].iter() {
+                let mut position = [0.; 3];
+                position[axis] = side;
+                position[u] = corner_u;
+                position[v] = corner_v;
+                positions.extend_from_slice(&position);
+                normals.extend_from_slice(&normal);
+            }
+            indices.extend_from_slice(&[
+                first_vertex,
+                first_vertex + 1,
+                first_vertex + 2,
+                first_vertex,
+                first_vertex + 2,
+                first_vertex + 3,
+            ]);
+        }
+    }
+
+    (positions, normals, indices)
+}
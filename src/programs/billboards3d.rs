@@ -0,0 +1,139 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// anchor xyz, corner xy, rgb
+const FLOATS_PER_VERTEX: usize = 8;
+
+// the quad's corners, x from -0.5 to 0.5 and y from 0 (the anchor) to 1, as 2 triangles
+const CORNERS: [[f32; 2]; 6] = [
+    [-0.5, 0.],
+    [0.5, 0.],
+    [0.5, 1.],
+    [-0.5, 0.],
+    [0.5, 1.],
+    [-0.5, 1.],
+];
+
+// screen aligned pins (annotation markers), ANNOTATION_PIN_SIZE px tall whatever the zoom, with their tip
+// on a point in the graph's box. they're depth tested, so anything already in the depth buffer in front
+// of a pin's tip hides the whole pin. there are only ever a handful, so they're rebuilt every frame
+pub struct Billboards3d {
+    pub program: WebGlProgram,
+    pub vertices_buffer: WebGlBuffer,
+    pub u_depth_bias: WebGlUniformLocation,
+    pub matrix_uniforms: cf::MatrixUniforms,
+    pub u_opacity: WebGlUniformLocation,
+    pub u_size: WebGlUniformLocation,
+    pub u_viewport: WebGlUniformLocation,
+}
+
+impl Billboards3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::billboard::SHADER,
+            super::super::shaders::fragment::pin::SHADER,
+            &["aAnchor", "aCorner", "aColor"],
+        )
+        .unwrap();
+
+        Self {
+            u_depth_bias: gl.get_uniform_location(&program, "uDepthBias").unwrap(),
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            u_size: gl.get_uniform_location(&program, "uSize").unwrap(),
+            u_viewport: gl.get_uniform_location(&program, "uViewport").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            vertices_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create billboard vertices buffer")
+                .unwrap(),
+        }
+    }
+
+    // one pin per anchor, coloured with the matching entry of colors
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+        anchors: &Vec<[f32; 3]>,
+        colors: &Vec<[f32; 3]>,
+    ) {
+        if anchors.is_empty() {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+        gl.uniform1f(Some(&self.u_size), ANNOTATION_PIN_SIZE);
+        gl.uniform1f(Some(&self.u_depth_bias), ANNOTATION_DEPTH_BIAS);
+        gl.uniform2f(Some(&self.u_viewport), canvas_width, canvas_height);
+
+        let mut vertices: Vec<f32> = Vec::with_capacity(anchors.len() * 6 * FLOATS_PER_VERTEX);
+        for (anchor, color) in anchors.iter().zip(colors.iter()) {
+            for corner in CORNERS.iter() {
+                vertices.extend_from_slice(anchor);
+                vertices.extend_from_slice(corner);
+                vertices.extend_from_slice(color);
+            }
+        }
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.vertices_buffer));
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let vertices_location = vertices.as_ptr() as u32 / 4;
+        let vertices_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(vertices_location, vertices_location + vertices.len() as u32);
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vertices_array, GL::DYNAMIC_DRAW);
+
+        let stride = (FLOATS_PER_VERTEX * 4) as i32;
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_with_i32(1, 2, GL::FLOAT, false, stride, 3 * 4);
+        gl.enable_vertex_attrib_array(1);
+        gl.vertex_attrib_pointer_with_i32(2, 3, GL::FLOAT, false, stride, 5 * 4);
+        gl.enable_vertex_attrib_array(2);
+
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LEQUAL);
+        gl.draw_arrays(
+            GL::TRIANGLES,
+            0,
+            (vertices.len() / FLOATS_PER_VERTEX) as i32,
+        );
+        gl.disable(GL::DEPTH_TEST);
+
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+        gl.disable_vertex_attrib_array(2);
+    }
+}
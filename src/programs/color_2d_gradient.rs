@@ -1,4 +1,5 @@
 use super::super::common_funcs as cf;
+use super::super::math::{Mat4, Vec3};
 use js_sys::WebAssembly;
 use wasm_bindgen::JsCast;
 use web_sys::WebGlRenderingContext as GL;
@@ -134,22 +135,26 @@ impl Color2DGradient {
         gl.uniform1f(Some(&self.u_opacity), 1.);
 
         // translation
-        let translation_matrix = cf::translation_matrix(
+        let translation_matrix = Mat4::translation(Vec3::new(
             2. * left / canvas_width - 1.,
             2. * bottom / canvas_height - 1.,
             0.,
-        );
+        ));
 
         // scaling
-        let scale_matrix = cf::scaling_matrix(
+        let scale_matrix = Mat4::scaling(Vec3::new(
             2. * (right - left) / canvas_width,
             2. * (top - bottom) / canvas_height,
             0.,
-        );
+        ));
 
         // combine scaling and translation
-        let transform_matrix = cf::cross_multiply_matrix(&scale_matrix, &translation_matrix);
-        gl.uniform_matrix4fv_with_f32_array(Some(&self.u_transform), false, &transform_matrix);
+        let transform_matrix = translation_matrix * scale_matrix;
+        gl.uniform_matrix4fv_with_f32_array(
+            Some(&self.u_transform),
+            false,
+            transform_matrix.as_slice(),
+        );
 
         // draw, given all the settings loaded above
         let offset = 0;
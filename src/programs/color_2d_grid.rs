@@ -0,0 +1,149 @@
+use super::super::common_funcs as cf;
+use super::super::math::{Mat4, Vec3};
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// a flat image made of an n by n grid of coloured vertices, colours blended across each cell.
+// the same idea as Color2DGradient's 4 coloured corners, just with a lot more corners
+pub struct Color2DGrid {
+    program: WebGlProgram,
+    color_buffer: WebGlBuffer,
+    grid_vertices_buffer: WebGlBuffer,
+    grid_indices_buffer: WebGlBuffer,
+    grid_indices_count: i32,
+    u_opacity: WebGlUniformLocation,
+    u_transform: WebGlUniformLocation,
+}
+
+impl Color2DGrid {
+    pub fn new(gl: &WebGlRenderingContext, n: usize) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::color_2d_gradient::SHADER,
+            super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+            &["aPosition", "aColor"],
+        )
+        .unwrap();
+
+        // the 3d grid goes from -1 to +1 on the x-z plane. we want it flat on x-y, from 0 to 1,
+        // like the other 2d programs' rectangles. the grid's z becomes our y
+        let (positions_3d, indices) = cf::get_position_grid_n_by_n(n);
+        let vertices_grid: Vec<f32> = positions_3d
+            .chunks(3)
+            .flat_map(|position| vec![(position[0] + 1.) / 2., (position[2] + 1.) / 2.])
+            .collect();
+
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let vertices_location = vertices_grid.as_ptr() as u32 / 4;
+        let vert_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            vertices_location,
+            vertices_location + vertices_grid.len() as u32,
+        );
+        let buffer_grid = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for grid vertices")
+            .unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer_grid));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
+
+        let indices_location = indices.as_ptr() as u32 / 2;
+        let indices_array = js_sys::Uint16Array::new(&memory_buffer)
+            .subarray(indices_location, indices_location + indices.len() as u32);
+        let buffer_indices = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for grid indices")
+            .unwrap();
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer_indices));
+        gl.buffer_data_with_array_buffer_view(
+            GL::ELEMENT_ARRAY_BUFFER,
+            &indices_array,
+            GL::STATIC_DRAW,
+        );
+
+        Self {
+            color_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create color buffer")
+                .unwrap(),
+            grid_vertices_buffer: buffer_grid,
+            grid_indices_buffer: buffer_indices,
+            grid_indices_count: indices_array.length() as i32,
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            u_transform: gl.get_uniform_location(&program, "uTransform").unwrap(),
+            program, // must be last as it takes over ownership of program
+        }
+    }
+
+    // colors: rgb per grid vertex, laid out like the y values of the 3d graph
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        colors: &Vec<f32>,
+    ) {
+        gl.use_program(Some(&self.program));
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.grid_vertices_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 2, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+
+        // rgb only, the shader's vec4 fills in alpha = 1
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.color_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+        let colors_memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let color_vals_location = colors.as_ptr() as u32 / 4;
+        let color_vals_array = js_sys::Float32Array::new(&colors_memory_buffer).subarray(
+            color_vals_location,
+            color_vals_location + colors.len() as u32,
+        );
+        gl.buffer_data_with_array_buffer_view(
+            GL::ARRAY_BUFFER,
+            &color_vals_array,
+            GL::DYNAMIC_DRAW,
+        );
+
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+
+        // same placement as the other 2d programs: unit square -> (left, bottom) to (right, top)
+        let translation_matrix = Mat4::translation(Vec3::new(
+            2. * left / canvas_width - 1.,
+            2. * bottom / canvas_height - 1.,
+            0.,
+        ));
+        let scale_matrix = Mat4::scaling(Vec3::new(
+            2. * (right - left) / canvas_width,
+            2. * (top - bottom) / canvas_height,
+            0.,
+        ));
+        let transform_matrix = translation_matrix * scale_matrix;
+        gl.uniform_matrix4fv_with_f32_array(
+            Some(&self.u_transform),
+            false,
+            transform_matrix.as_slice(),
+        );
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.grid_indices_buffer));
+        gl.draw_elements_with_i32(
+            GL::TRIANGLES,
+            self.grid_indices_count,
+            GL::UNSIGNED_SHORT,
+            0,
+        );
+
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+    }
+}
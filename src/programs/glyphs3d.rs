@@ -0,0 +1,252 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use super::super::vector_field::FLOATS_PER_GLYPH;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// arrows for vector fields. like the bar chart, one arrow mesh drawn once per glyph with
+// ANGLE_instanced_arrays, placed, turned, scaled and coloured per glyph (see vector_field::get_glyph_instances)
+pub struct Glyphs3d {
+    program: WebGlProgram,
+    instanced_arrays: Option<AngleInstancedArrays>, // see load_extension
+    arrow_positions_buffer: WebGlBuffer,
+    arrow_normals_buffer: WebGlBuffer,
+    arrow_vertex_count: i32,
+    instances_buffer: WebGlBuffer,
+    pub glyph_count: i32,
+    matrix_uniforms: cf::MatrixUniforms,
+    u_opacity: WebGlUniformLocation,
+}
+
+impl Glyphs3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::glyphs_3d::SHADER,
+            super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+            &[
+                "aPosition",
+                "aVertexNormal",
+                "aOffset",
+                "aDirection",
+                "aColor",
+            ],
+        )
+        .unwrap();
+
+        let (positions, normals) = get_arrow(VECTOR_FIELD_GLYPH_SEGMENTS);
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+
+        let arrow_positions_buffer = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for arrow positions")
+            .unwrap();
+        let positions_location = positions.as_ptr() as u32 / 4;
+        let positions_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            positions_location,
+            positions_location + positions.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&arrow_positions_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &positions_array, GL::STATIC_DRAW);
+
+        let arrow_normals_buffer = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for arrow normals")
+            .unwrap();
+        let normals_location = normals.as_ptr() as u32 / 4;
+        let normals_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(normals_location, normals_location + normals.len() as u32);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&arrow_normals_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &normals_array, GL::STATIC_DRAW);
+
+        Self {
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            instanced_arrays: None,
+            arrow_positions_buffer: arrow_positions_buffer,
+            arrow_normals_buffer: arrow_normals_buffer,
+            arrow_vertex_count: (positions.len() / 3) as i32,
+            instances_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for glyph instances")
+                .unwrap(),
+            glyph_count: 0,
+        }
+    }
+
+    // looked up the first time glyphs are wanted, like Bars3d::load_extension
+    pub fn load_extension(&mut self, gl: &WebGlRenderingContext) -> Result<(), String> {
+        if self.instanced_arrays.is_none() {
+            self.instanced_arrays = Some(
+                cf::get_extension(gl, "ANGLE_instanced_arrays", "vector field glyphs")?
+                    .unchecked_into::<AngleInstancedArrays>(),
+            );
+        }
+        Ok(())
+    }
+
+    // FLOATS_PER_GLYPH per glyph, from vector_field::get_glyph_instances
+    pub fn set_glyphs(&mut self, gl: &WebGlRenderingContext, instances: &Vec<f32>) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let instances_location = instances.as_ptr() as u32 / 4;
+        let instances_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            instances_location,
+            instances_location + instances.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instances_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &instances_array, GL::STATIC_DRAW);
+
+        self.glyph_count = (instances.len() / FLOATS_PER_GLYPH) as i32;
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+    ) {
+        let instanced_arrays = match &self.instanced_arrays {
+            Some(instanced_arrays) if self.glyph_count > 0 => instanced_arrays,
+            _ => return,
+        };
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.arrow_positions_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.arrow_normals_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+
+        // offset, direction and colour once per glyph
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instances_buffer));
+        let stride = (FLOATS_PER_GLYPH * 4) as i32;
+        for i in 0..3 {
+            let attribute = 2 + i as u32;
+            gl.vertex_attrib_pointer_with_i32(attribute, 3, GL::FLOAT, false, stride, 3 * 4 * i);
+            gl.enable_vertex_attrib_array(attribute);
+            instanced_arrays.vertex_attrib_divisor_angle(attribute, 1);
+        }
+
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LESS);
+        instanced_arrays.draw_arrays_instanced_angle(
+            GL::TRIANGLES,
+            0,
+            self.arrow_vertex_count,
+            self.glyph_count,
+        );
+        gl.disable(GL::DEPTH_TEST);
+
+        // done with the attribute arrays, and the divisors back to 0 for everyone else
+        for attribute in 0..5 {
+            if attribute >= 2 {
+                instanced_arrays.vertex_attrib_divisor_angle(attribute, 0);
+            }
+            gl.disable_vertex_attrib_array(attribute);
+        }
+    }
+}
+
+// an arrow one unit long up the y axis: a thin shaft, then a cone for the head. plain triangles,
+// no indices, with the normals of each side pointing straight out
+fn get_arrow(segments: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut positions: Vec<f32> = Vec::new();
+    let mut normals: Vec<f32> = Vec::new();
+    let mut push = |position: [f32; 3], normal: [f32; 3]| {
+        positions.extend_from_slice(&position);
+        normals.extend_from_slice(&normal);
+    };
+
+    let shaft_top = 1. - VECTOR_FIELD_GLYPH_HEAD_LENGTH;
+    let shaft_radius = VECTOR_FIELD_GLYPH_SHAFT_RADIUS;
+    let head_radius = VECTOR_FIELD_GLYPH_HEAD_RADIUS;
+    // the cone's side leans in, so its normal leans up by the same amount
+    let head_slope = head_radius / VECTOR_FIELD_GLYPH_HEAD_LENGTH;
+    let head_normal_size = (1. + head_slope * head_slope).sqrt();
+
+    for i in 0..segments {
+        let angle = |i: usize| 2. * std::f32::consts::PI * i as f32 / segments as f32;
+        let (sin_a, cos_a) = angle(i).sin_cos();
+        let (sin_b, cos_b) = angle(i + 1).sin_cos();
+        let around = |radius: f32, sin: f32, cos: f32, y: f32| [radius * cos, y, radius * sin];
+
+        // shaft side, two triangles
+        let shaft_normal_a = [cos_a, 0., sin_a];
+        let shaft_normal_b = [cos_b, 0., sin_b];
+        push(around(shaft_radius, sin_a, cos_a, 0.), shaft_normal_a);
+        push(
+            around(shaft_radius, sin_a, cos_a, shaft_top),
+            shaft_normal_a,
+        );
+        push(
+            around(shaft_radius, sin_b, cos_b, shaft_top),
+            shaft_normal_b,
+        );
+        push(around(shaft_radius, sin_a, cos_a, 0.), shaft_normal_a);
+        push(
+            around(shaft_radius, sin_b, cos_b, shaft_top),
+            shaft_normal_b,
+        );
+        push(around(shaft_radius, sin_b, cos_b, 0.), shaft_normal_b);
+
+        // underside of the head, facing down
+        push([0., shaft_top, 0.], [0., -1., 0.]);
+        push(around(head_radius, sin_b, cos_b, shaft_top), [0., -1., 0.]);
+        push(around(head_radius, sin_a, cos_a, shaft_top), [0., -1., 0.]);
+
+        // the head's side, up to the tip
+        let head_normal = |sin: f32, cos: f32| {
+            [
+                cos / head_normal_size,
+                head_slope / head_normal_size,
+                sin / head_normal_size,
+            ]
+        };
+        let (mid_sin, mid_cos) = (0.5 * (angle(i) + angle(i + 1))).sin_cos();
+        push(
+            around(head_radius, sin_a, cos_a, shaft_top),
+            head_normal(sin_a, cos_a),
+        );
+        push([0., 1., 0.], head_normal(mid_sin, mid_cos));
+        push(
+            around(head_radius, sin_b, cos_b, shaft_top),
+            head_normal(sin_b, cos_b),
+        );
+    }
+
+    (positions, normals)
+}
@@ -1,10 +1,22 @@
+use super::super::camera::Lens;
 use super::super::common_funcs as cf;
 use super::super::constants::*;
+use super::super::math::Mat4;
 use js_sys::WebAssembly;
+use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::*;
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Solid,
+    Wireframe,      // every edge, including the ones at the back
+    SolidWireframe, // the surface with its (visible) edges drawn on top
+    HiddenLine,     // only the edges you'd see if the surface were opaque
+}
+
 // from shader, we know we will need these
 // attribute vec4 aPosition;
 // uniform mat4 uProjection;
@@ -14,19 +26,23 @@ pub struct Graph3d {
     pub position_buffer: WebGlBuffer,
     pub indices_buffer: WebGlBuffer,
     pub index_count: i32,
+    pub edge_indices_buffer: WebGlBuffer,
+    pub edge_index_count: i32,
     pub y_buffer: WebGlBuffer,
     pub normals_buffer: WebGlBuffer,
-    pub u_normals_rotation: WebGlUniformLocation,
+    pub colors_buffer: WebGlBuffer,
+    pub matrix_uniforms: cf::MatrixUniforms,
     pub u_opacity: WebGlUniformLocation,
-    pub u_projection: WebGlUniformLocation,
+    pub u_unlit: WebGlUniformLocation,
 }
 
 impl Graph3d {
     pub fn new(gl: &WebGlRenderingContext) -> Self {
-        let program = cf::link_program(
+        let program = cf::link_program_with_attributes(
             &gl,
             super::super::shaders::vertex::graph_3d::SHADER,
             super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+            &["aPosition", "aY", "aVertexNormal", "aColor"],
         )
         .unwrap();
 
@@ -63,13 +79,36 @@ impl Graph3d {
             .create_buffer()
             .ok_or("failed to create buffer for grid indices")
             .unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer_indices));
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer_indices));
         gl.buffer_data_with_array_buffer_view(
             GL::ELEMENT_ARRAY_BUFFER,
             &indices_array,
             GL::STATIC_DRAW,
         );
 
+        // each grid line segment once, for the wireframe. lines between neighbouring triangles would
+        // otherwise be drawn twice
+        let edge_indices = cf::get_edge_indices(&positions_and_indices.1);
+        let edge_indices_memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let edge_indices_location = edge_indices.as_ptr() as u32 / 2;
+        let edge_indices_array = js_sys::Uint16Array::new(&edge_indices_memory_buffer).subarray(
+            edge_indices_location,
+            edge_indices_location + edge_indices.len() as u32,
+        );
+        let buffer_edge_indices = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for grid edge indices")
+            .unwrap();
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer_edge_indices));
+        gl.buffer_data_with_array_buffer_view(
+            GL::ELEMENT_ARRAY_BUFFER,
+            &edge_indices_array,
+            GL::STATIC_DRAW,
+        );
+
         Self {
             // color_buffer: gl
             //     .create_buffer()
@@ -78,15 +117,15 @@ impl Graph3d {
             // rect_vertices_buffer: buffer_rect,
             // rect_vertices_indices_count: indices_array.length() as i32,
             u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
-            u_projection: gl.get_uniform_location(&program, "uProjection").unwrap(),
-            u_normals_rotation: gl
-                .get_uniform_location(&program, "uNormalsRotation")
-                .unwrap(),
+            u_unlit: gl.get_uniform_location(&program, "uUnlit").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
             program, // must be last as it takes over ownership of program
 
             position_buffer: buffer_position,
             indices_buffer: buffer_indices,
             index_count: indices_array.length() as i32,
+            edge_indices_buffer: buffer_edge_indices,
+            edge_index_count: edge_indices_array.length() as i32,
             y_buffer: gl
                 .create_buffer()
                 .ok_or("failed to create y buffer")
@@ -96,6 +135,11 @@ impl Graph3d {
                 .create_buffer()
                 .ok_or("failed to create normals buffer")
                 .unwrap(),
+
+            colors_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create colors buffer")
+                .unwrap(),
         }
     }
 
@@ -110,33 +154,32 @@ impl Graph3d {
         canvas_width: f32,
         rotation_angle_x_axis: f32,
         rotation_angle_y_axis: f32,
+        lens: &Lens,
         y_vals: &Vec<f32>,
+        colors: Option<&Vec<f32>>,
+        render_mode: RenderMode,
+        model: &Mat4, // where it sits in the graph's box, see scene.rs
     ) {
         gl.use_program(Some(&self.program));
 
-        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
-            bottom,
-            top,
-            left,
-            right,
-            canvas_height,
-            canvas_width,
-            rotation_angle_x_axis,
-            rotation_angle_y_axis,
-        );
-
-        gl.uniform_matrix4fv_with_f32_array(
-            Some(&self.u_projection),
-            false,
-            &projection_and_rotation_matrices.projection,
+        let projection_and_rotation_matrices = cf::apply_model_matrix(
+            &cf::get_3d_projection_matrix_and_rotation(
+                bottom,
+                top,
+                left,
+                right,
+                canvas_height,
+                canvas_width,
+                rotation_angle_x_axis,
+                rotation_angle_y_axis,
+                lens,
+            ),
+            model,
         );
 
-        // normals
-        gl.uniform_matrix4fv_with_f32_array(
-            Some(&self.u_normals_rotation),
-            false,
-            &projection_and_rotation_matrices.normals_rotation,
-        );
+        // projection, and the normal matrix for the lighting
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
 
         // opacity
         gl.uniform1f(Some(&self.u_opacity), 0.5);
@@ -180,6 +223,112 @@ impl Graph3d {
         );
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &normals_array, GL::DYNAMIC_DRAW);
 
+        // colors. rgb per vertex if we have them, otherwise the whole surface is GRAPH_3D_COLOR
+        match colors {
+            Some(colors) => {
+                gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.colors_buffer));
+                gl.vertex_attrib_pointer_with_i32(3, 3, GL::FLOAT, false, 0, 0);
+                gl.enable_vertex_attrib_array(3);
+
+                let colors_memory_buffer = wasm_bindgen::memory()
+                    .dyn_into::<WebAssembly::Memory>()
+                    .unwrap()
+                    .buffer();
+                let colors_location = colors.as_ptr() as u32 / 4;
+                let colors_array = js_sys::Float32Array::new(&colors_memory_buffer)
+                    .subarray(colors_location, colors_location + colors.len() as u32);
+                gl.buffer_data_with_array_buffer_view(
+                    GL::ARRAY_BUFFER,
+                    &colors_array,
+                    GL::DYNAMIC_DRAW,
+                );
+            }
+            None => {
+                // an attribute with its array switched off reads this constant for every vertex
+                gl.disable_vertex_attrib_array(3);
+                gl.vertex_attrib3f(3, GRAPH_3D_COLOR[0], GRAPH_3D_COLOR[1], GRAPH_3D_COLOR[2]);
+            }
+        }
+
+        gl.uniform1i(Some(&self.u_unlit), 0);
+        match render_mode {
+            RenderMode::Solid => self.draw_triangles(gl),
+            RenderMode::Wireframe => self.draw_edges(gl),
+            RenderMode::SolidWireframe | RenderMode::HiddenLine => {
+                // the surface goes into the depth buffer first, pushed back a touch (polygon offset)
+                // so that edges lying exactly on it still pass the depth test. edges behind it don't
+                gl.enable(GL::DEPTH_TEST);
+                gl.depth_func(GL::LEQUAL);
+                gl.enable(GL::POLYGON_OFFSET_FILL);
+                gl.polygon_offset(1., 1.);
+                if render_mode == RenderMode::HiddenLine {
+                    gl.color_mask(false, false, false, false); // depth only, the surface itself stays invisible
+                }
+                self.draw_triangles(gl);
+                gl.color_mask(true, true, true, true);
+                gl.disable(GL::POLYGON_OFFSET_FILL);
+
+                self.draw_edges(gl);
+                gl.disable(GL::DEPTH_TEST);
+            }
+        }
+
+        // leave no attribute arrays switched on. webgl checks every enabled one against the draw's vertex count,
+        // so a leftover short buffer would break the next program's draw
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+        gl.disable_vertex_attrib_array(2);
+        gl.disable_vertex_attrib_array(3);
+    }
+
+    // the surface from the last render, into the depth buffer only. the solid and wireframe modes draw
+    // without a depth test (so without writing depth), and whatever is drawn afterwards wanting to be
+    // hidden behind the surface needs this first. the buffers and uniforms are still there from render
+    pub fn write_depth(&self, gl: &WebGlRenderingContext) {
+        gl.use_program(Some(&self.program));
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.y_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 1, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.normals_buffer));
+        gl.vertex_attrib_pointer_with_i32(2, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(2);
+        gl.disable_vertex_attrib_array(3);
+
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LEQUAL);
+        gl.color_mask(false, false, false, false);
+        self.draw_triangles(gl);
+        gl.color_mask(true, true, true, true);
+        gl.disable(GL::DEPTH_TEST);
+
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+        gl.disable_vertex_attrib_array(2);
+    }
+
+    fn draw_triangles(&self, gl: &WebGlRenderingContext) {
+        // other programs bind their own indices, so make sure it's ours being drawn
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.indices_buffer));
         gl.draw_elements_with_i32(GL::TRIANGLES, self.index_count, GL::UNSIGNED_SHORT, 0);
     }
+
+    // the grid's edges in WIREFRAME_COLOR, no lighting
+    fn draw_edges(&self, gl: &WebGlRenderingContext) {
+        gl.uniform1i(Some(&self.u_unlit), 1);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+        gl.disable_vertex_attrib_array(3);
+        gl.vertex_attrib3f(
+            3,
+            WIREFRAME_COLOR[0],
+            WIREFRAME_COLOR[1],
+            WIREFRAME_COLOR[2],
+        );
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.edge_indices_buffer));
+        gl.draw_elements_with_i32(GL::LINES, self.edge_index_count, GL::UNSIGNED_SHORT, 0);
+    }
 }
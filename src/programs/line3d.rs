@@ -0,0 +1,340 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use js_sys::WebAssembly;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    Butt,   // stops dead at the end point
+    Round,  // half a circle past the end point
+    Square, // half the width past the end point
+}
+
+// start xyz, end xyz, corner xyz, rgb, distance
+const FLOATS_PER_VERTEX: usize = 13;
+
+// how to draw one polyline, and where its vertices are in the buffers
+struct Polyline3d {
+    first_segment_vertex: i32,
+    segment_vertex_count: i32,
+    first_join: i32,
+    join_count: i32,
+    width: f32,
+    dash_length: f32,
+    gap_length: f32,
+}
+
+// 3d polylines with real pixel widths. every segment becomes a quad (2 triangles) that the vertex
+// shader turns to face the screen, and the joins are round dots (GL::POINTS) on the vertices.
+// lines are added once and kept in static buffers
+pub struct Line3d {
+    program: WebGlProgram,
+    segments_buffer: WebGlBuffer,
+    joins_buffer: WebGlBuffer,
+    segment_vertices: Vec<f32>,
+    join_vertices: Vec<f32>,
+    polylines: Vec<Polyline3d>,
+    u_dash_length: WebGlUniformLocation,
+    u_gap_length: WebGlUniformLocation,
+    u_joins: WebGlUniformLocation,
+    matrix_uniforms: cf::MatrixUniforms,
+    u_opacity: WebGlUniformLocation,
+    u_viewport: WebGlUniformLocation,
+    u_width: WebGlUniformLocation,
+}
+
+impl Line3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::line_3d::SHADER,
+            super::super::shaders::fragment::thick_line::SHADER,
+            &["aStart", "aEnd", "aCorner", "aColor", "aDistance"],
+        )
+        .unwrap();
+
+        Self {
+            segments_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for line segments")
+                .unwrap(),
+            joins_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for line joins")
+                .unwrap(),
+            segment_vertices: Vec::new(),
+            join_vertices: Vec::new(),
+            polylines: Vec::new(),
+            u_dash_length: gl.get_uniform_location(&program, "uDashLength").unwrap(),
+            u_gap_length: gl.get_uniform_location(&program, "uGapLength").unwrap(),
+            u_joins: gl.get_uniform_location(&program, "uJoins").unwrap(),
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            u_viewport: gl.get_uniform_location(&program, "uViewport").unwrap(),
+            u_width: gl.get_uniform_location(&program, "uWidth").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+        }
+    }
+
+    // positions: xyz per point. colors: rgb per point, or empty for LINE_3D_COLOR.
+    // width in px, dash and gap lengths in the graph's units (no gap, no dashes)
+    pub fn add_polyline(
+        &mut self,
+        gl: &WebGlRenderingContext,
+        positions: &Vec<f32>,
+        colors: &Vec<f32>,
+        width: f32,
+        dash_length: f32,
+        gap_length: f32,
+        cap: LineCap,
+    ) {
+        self.push_polyline(positions, colors, width, dash_length, gap_length, cap);
+        self.upload(gl);
+    }
+
+    // replaces all the lines with these (positions, colors) pairs, all drawn the same way.
+    // one upload for the lot, rather than one per line
+    pub fn set_polylines(
+        &mut self,
+        gl: &WebGlRenderingContext,
+        polylines: &Vec<(Vec<f32>, Vec<f32>)>,
+        width: f32,
+        dash_length: f32,
+        gap_length: f32,
+        cap: LineCap,
+    ) {
+        self.segment_vertices.clear();
+        self.join_vertices.clear();
+        self.polylines.clear();
+        for (positions, colors) in polylines.iter() {
+            self.push_polyline(positions, colors, width, dash_length, gap_length, cap);
+        }
+        self.upload(gl);
+    }
+
+    fn push_polyline(
+        &mut self,
+        positions: &Vec<f32>,
+        colors: &Vec<f32>,
+        width: f32,
+        dash_length: f32,
+        gap_length: f32,
+        cap: LineCap,
+    ) {
+        let point_count = positions.len() / 3;
+        if point_count < 2 {
+            return;
+        }
+        let color = |i: usize| -> [f32; 3] {
+            if colors.is_empty() {
+                LINE_3D_COLOR
+            } else {
+                [colors[3 * i], colors[3 * i + 1], colors[3 * i + 2]]
+            }
+        };
+        let point = |i: usize| -> [f32; 3] {
+            [positions[3 * i], positions[3 * i + 1], positions[3 * i + 2]]
+        };
+
+        let first_segment_vertex = (self.segment_vertices.len() / FLOATS_PER_VERTEX) as i32;
+        let first_join = (self.join_vertices.len() / FLOATS_PER_VERTEX) as i32;
+
+        let mut distance = 0.;
+        for i in 0..point_count {
+            // round dots on the inside corners, and on the ends for round caps
+            let is_end = i == 0 || i == point_count - 1;
+            if !is_end || cap == LineCap::Round {
+                push_vertex(
+                    &mut self.join_vertices,
+                    &point(i),
+                    &point(i),
+                    [0., 0., 0.],
+                    color(i),
+                    distance,
+                );
+            }
+            if i == point_count - 1 {
+                break;
+            }
+
+            let (start, end) = (point(i), point(i + 1));
+            let segment_length = ((end[0] - start[0]).powi(2)
+                + (end[1] - start[1]).powi(2)
+                + (end[2] - start[2]).powi(2))
+            .sqrt();
+            // square caps: the first segment sticks out at its start, the last one at its end
+            let start_extend = if i == 0 && cap == LineCap::Square {
+                1.
+            } else {
+                0.
+            };
+            let end_extend = if i + 2 == point_count && cap == LineCap::Square {
+                1.
+            } else {
+                0.
+            };
+            // two triangles, ccw
+            for &(t, side) in [
+                (0., -1.),
+                (1., -1.),
+                (1., 1.),
+                (0., -1.),
+                (1., 1.),
+                (0., 1.),
+            ]
+            .iter()
+            {
+                let (corner_color, corner_distance, extend) = if t == 0. {
+                    (color(i), distance, start_extend)
+                } else {
+                    (color(i + 1), distance + segment_length, end_extend)
+                };
+                push_vertex(
+                    &mut self.segment_vertices,
+                    &start,
+                    &end,
+                    [t, side, extend],
+                    corner_color,
+                    corner_distance,
+                );
+            }
+            distance += segment_length;
+        }
+
+        self.polylines.push(Polyline3d {
+            first_segment_vertex: first_segment_vertex,
+            segment_vertex_count: (self.segment_vertices.len() / FLOATS_PER_VERTEX) as i32
+                - first_segment_vertex,
+            first_join: first_join,
+            join_count: (self.join_vertices.len() / FLOATS_PER_VERTEX) as i32 - first_join,
+            width: width,
+            dash_length: dash_length,
+            gap_length: gap_length,
+        });
+    }
+
+    pub fn clear(&mut self, gl: &WebGlRenderingContext) {
+        self.segment_vertices.clear();
+        self.join_vertices.clear();
+        self.polylines.clear();
+        self.upload(gl);
+    }
+
+    fn upload(&self, gl: &WebGlRenderingContext) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        for (buffer, vertices) in [
+            (&self.segments_buffer, &self.segment_vertices),
+            (&self.joins_buffer, &self.join_vertices),
+        ]
+        .iter()
+        {
+            let vertices_location = vertices.as_ptr() as u32 / 4;
+            let vertices_array = js_sys::Float32Array::new(&memory_buffer)
+                .subarray(vertices_location, vertices_location + vertices.len() as u32);
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+            gl.buffer_data_with_array_buffer_view(
+                GL::ARRAY_BUFFER,
+                &vertices_array,
+                GL::STATIC_DRAW,
+            );
+        }
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+    ) {
+        if self.polylines.is_empty() {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform2f(Some(&self.u_viewport), canvas_width, canvas_height);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LEQUAL);
+
+        // segments first, then the joins over them
+        for &(buffer, joins) in [(&self.segments_buffer, false), (&self.joins_buffer, true)].iter()
+        {
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+            let stride = (FLOATS_PER_VERTEX * 4) as i32;
+            gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, stride, 0);
+            gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, stride, 3 * 4);
+            gl.vertex_attrib_pointer_with_i32(2, 3, GL::FLOAT, false, stride, 6 * 4);
+            gl.vertex_attrib_pointer_with_i32(3, 3, GL::FLOAT, false, stride, 9 * 4);
+            gl.vertex_attrib_pointer_with_i32(4, 1, GL::FLOAT, false, stride, 12 * 4);
+            for i in 0..5 {
+                gl.enable_vertex_attrib_array(i);
+            }
+            gl.uniform1i(Some(&self.u_joins), joins as i32);
+
+            for polyline in self.polylines.iter() {
+                gl.uniform1f(Some(&self.u_width), polyline.width);
+                gl.uniform1f(Some(&self.u_dash_length), polyline.dash_length);
+                gl.uniform1f(Some(&self.u_gap_length), polyline.gap_length);
+                if joins {
+                    gl.draw_arrays(GL::POINTS, polyline.first_join, polyline.join_count);
+                } else {
+                    gl.draw_arrays(
+                        GL::TRIANGLES,
+                        polyline.first_segment_vertex,
+                        polyline.segment_vertex_count,
+                    );
+                }
+            }
+        }
+        gl.disable(GL::DEPTH_TEST);
+
+        // done with the attribute arrays
+        for i in 0..5 {
+            gl.disable_vertex_attrib_array(i);
+        }
+    }
+}
+
+fn push_vertex(
+    vertices: &mut Vec<f32>,
+    start: &[f32; 3],
+    end: &[f32; 3],
+    corner: [f32; 3],
+    color: [f32; 3],
+    distance: f32,
+) {
+    vertices.extend_from_slice(start);
+    vertices.extend_from_slice(end);
+    vertices.extend_from_slice(&corner);
+    vertices.extend_from_slice(&color);
+    vertices.push(distance);
+}
@@ -0,0 +1,72 @@
+use super::super::common_funcs as cf;
+use super::super::math::Mat4;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// plain 1px GL::LINES in a single colour. the caller hands in the whole transform, so the same
+// program draws lines in the 3d graph (with its projection) and flat on the 2d panels
+pub struct Lines {
+    program: WebGlProgram,
+    vertices_buffer: WebGlBuffer,
+    u_color: WebGlUniformLocation,
+    u_opacity: WebGlUniformLocation,
+    u_transform: WebGlUniformLocation,
+}
+
+impl Lines {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program(
+            &gl,
+            super::super::shaders::vertex::lines::SHADER,
+            super::super::shaders::fragment::color_2d::SHADER,
+        )
+        .unwrap();
+
+        Self {
+            vertices_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for lines")
+                .unwrap(),
+            u_color: gl.get_uniform_location(&program, "uColor").unwrap(),
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            u_transform: gl.get_uniform_location(&program, "uTransform").unwrap(),
+            program, // must be last as it takes over ownership of program
+        }
+    }
+
+    // segments: pairs of xyz points, one line per pair
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        transform: &Mat4,
+        segments: &Vec<f32>,
+        color: [f32; 4],
+    ) {
+        if segments.is_empty() {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.vertices_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let segments_location = segments.as_ptr() as u32 / 4;
+        let segments_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(segments_location, segments_location + segments.len() as u32);
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &segments_array, GL::DYNAMIC_DRAW);
+
+        gl.uniform4f(Some(&self.u_color), color[0], color[1], color[2], color[3]);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+        gl.uniform_matrix4fv_with_f32_array(Some(&self.u_transform), false, transform.as_slice());
+
+        gl.draw_arrays(GL::LINES, 0, (segments.len() / 3) as i32);
+
+        gl.disable_vertex_attrib_array(0);
+    }
+}
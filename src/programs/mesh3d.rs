@@ -0,0 +1,159 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::isosurface::Mesh;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// any indexed triangle mesh (positions, normals, u32 indices), lit like the 3d graph.
+// unlike the graph, the mesh only changes now and then, so it's uploaded once per change
+// with set_mesh, not every frame
+pub struct Mesh3d {
+    pub program: WebGlProgram,
+    pub position_buffer: WebGlBuffer,
+    pub normals_buffer: WebGlBuffer,
+    pub indices_buffer: WebGlBuffer,
+    pub index_count: i32,
+    pub has_u32_indices: bool, // see load_extension
+    pub u_base_color: WebGlUniformLocation,
+    pub matrix_uniforms: cf::MatrixUniforms,
+    pub u_opacity: WebGlUniformLocation,
+}
+
+impl Mesh3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program(
+            &gl,
+            super::super::shaders::vertex::mesh_3d::SHADER,
+            super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+        )
+        .unwrap();
+
+        Self {
+            u_base_color: gl.get_uniform_location(&program, "uBaseColor").unwrap(),
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            position_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create position buffer")
+                .unwrap(),
+            normals_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create normals buffer")
+                .unwrap(),
+            indices_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create indices buffer")
+                .unwrap(),
+            index_count: 0,
+            has_u32_indices: false,
+        }
+    }
+
+    // webgl 1 only does u16 indices (65536 vertices) out of the box. bigger meshes need
+    // OES_element_index_uint, which is available pretty much everywhere. it's switched on the first
+    // time a mesh is wanted rather than in new, so without it only the meshes are missing
+    pub fn load_extension(&mut self, gl: &WebGlRenderingContext) -> Result<(), String> {
+        if !self.has_u32_indices {
+            cf::get_extension(gl, "OES_element_index_uint", "meshes with u32 indices")?;
+            self.has_u32_indices = true;
+        }
+        Ok(())
+    }
+
+    pub fn set_mesh(&mut self, gl: &WebGlRenderingContext, mesh: &Mesh) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+
+        let positions_location = mesh.positions.as_ptr() as u32 / 4;
+        let positions_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            positions_location,
+            positions_location + mesh.positions.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &positions_array, GL::STATIC_DRAW);
+
+        let normals_location = mesh.normals.as_ptr() as u32 / 4;
+        let normals_array = js_sys::Float32Array::new(&memory_buffer).subarray(
+            normals_location,
+            normals_location + mesh.normals.len() as u32,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.normals_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &normals_array, GL::STATIC_DRAW);
+
+        let indices_location = mesh.indices.as_ptr() as u32 / 4;
+        let indices_array = js_sys::Uint32Array::new(&memory_buffer).subarray(
+            indices_location,
+            indices_location + mesh.indices.len() as u32,
+        );
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.indices_buffer));
+        gl.buffer_data_with_array_buffer_view(
+            GL::ELEMENT_ARRAY_BUFFER,
+            &indices_array,
+            GL::STATIC_DRAW,
+        );
+
+        self.index_count = mesh.indices.len() as i32;
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+        base_color: [f32; 3],
+    ) {
+        if self.index_count == 0 || !self.has_u32_indices {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform3f(
+            Some(&self.u_base_color),
+            base_color[0],
+            base_color[1],
+            base_color[2],
+        );
+        gl.uniform1f(Some(&self.u_opacity), 0.5);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.normals_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.indices_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, self.index_count, GL::UNSIGNED_INT, 0);
+
+        // done with the attribute arrays
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+    }
+}
@@ -4,3 +4,23 @@ mod color_2d_gradient;
 pub use color_2d_gradient::*;
 mod graph3d;
 pub use graph3d::*;
+mod parametric3d;
+pub use parametric3d::*;
+mod mesh3d;
+pub use mesh3d::*;
+mod color_2d_grid;
+pub use color_2d_grid::*;
+mod lines;
+pub use lines::*;
+mod scatter3d;
+pub use scatter3d::*;
+mod line3d;
+pub use line3d::*;
+mod bars3d;
+pub use bars3d::*;
+mod glyphs3d;
+pub use glyphs3d::*;
+mod picker;
+pub use picker::*;
+mod billboards3d;
+pub use billboards3d::*;
@@ -0,0 +1,141 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use super::super::math::Mat4;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// like Graph3d, but every coordinate of every vertex can move, not just y.
+// so the whole position buffer (and the normals) gets uploaded every frame
+pub struct Parametric3d {
+    pub program: WebGlProgram,
+    pub position_buffer: WebGlBuffer,
+    pub indices_buffer: WebGlBuffer,
+    pub index_count: i32,
+    pub normals_buffer: WebGlBuffer,
+    pub matrix_uniforms: cf::MatrixUniforms,
+    pub u_opacity: WebGlUniformLocation,
+}
+
+impl Parametric3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program(
+            &gl,
+            super::super::shaders::vertex::parametric_3d::SHADER,
+            super::super::shaders::fragment::varying_color_from_vertex::SHADER,
+        )
+        .unwrap();
+
+        // the (u, v) grid is stepped through exactly like the x-z grid, so we borrow its indices
+        let (_, indices) = cf::get_position_grid_n_by_n(PARAMETRIC_GRID_SIZE);
+        let indices_memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let indices_location = indices.as_ptr() as u32 / 2;
+        let indices_array = js_sys::Uint16Array::new(&indices_memory_buffer)
+            .subarray(indices_location, indices_location + indices.len() as u32);
+        let buffer_indices = gl
+            .create_buffer()
+            .ok_or("failed to create buffer for parametric indices")
+            .unwrap();
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer_indices));
+        gl.buffer_data_with_array_buffer_view(
+            GL::ELEMENT_ARRAY_BUFFER,
+            &indices_array,
+            GL::STATIC_DRAW,
+        );
+
+        Self {
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            position_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create position buffer")
+                .unwrap(),
+            indices_buffer: buffer_indices,
+            index_count: indices_array.length() as i32,
+            normals_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create normals buffer")
+                .unwrap(),
+        }
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+        positions: &Vec<f32>,
+        normals: &Vec<f32>,
+        model: &Mat4, // where it sits in the graph's box, see scene.rs
+    ) {
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::apply_model_matrix(
+            &cf::get_3d_projection_matrix_and_rotation(
+                bottom,
+                top,
+                left,
+                right,
+                canvas_height,
+                canvas_width,
+                rotation_angle_x_axis,
+                rotation_angle_y_axis,
+                lens,
+            ),
+            model,
+        );
+
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform1f(Some(&self.u_opacity), 0.5);
+
+        // positions, all of them, every frame
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+        let positions_memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let positions_location = positions.as_ptr() as u32 / 4;
+        let positions_array = js_sys::Float32Array::new(&positions_memory_buffer).subarray(
+            positions_location,
+            positions_location + positions.len() as u32,
+        );
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &positions_array, GL::DYNAMIC_DRAW);
+
+        // normals
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.normals_buffer));
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(1);
+        let normals_memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let normals_location = normals.as_ptr() as u32 / 4;
+        let normals_array = js_sys::Float32Array::new(&normals_memory_buffer)
+            .subarray(normals_location, normals_location + normals.len() as u32);
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &normals_array, GL::DYNAMIC_DRAW);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.indices_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, self.index_count, GL::UNSIGNED_SHORT, 0);
+
+        // switch our attribute arrays back off, same as Graph3d does
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+    }
+}
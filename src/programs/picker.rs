@@ -0,0 +1,280 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use js_sys::WebAssembly;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// what was picked. 0 is left for "nothing there"
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PickObject {
+    Surface = 1, // the 3d graph's height field
+    Isosurface = 2,
+    ParametricSurface = 3,
+}
+
+// triangles to pick from: xyz per vertex and 3 indices per triangle
+pub struct PickTarget<'a> {
+    pub object_id: u32,
+    pub positions: &'a Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+pub struct PickResult {
+    pub object_id: u32,
+    pub triangle_index: u32,
+    pub barycentric: [f32; 3], // weights of the triangle's 3 corners, in index order
+}
+
+// xyz, id bytes, corner
+const FLOATS_PER_VERTEX: usize = 9;
+
+// gpu picking. the pickable triangles are drawn into an offscreen framebuffer, each in a colour that
+// spells out its object id (alpha) and triangle index (rgb, 24 bits). reading back the one pixel under
+// the cursor says what's there, with the depth test picking the nearest. webgl 1 has no triangle id
+// in the shaders, so every triangle gets its own 3 vertices carrying its id
+pub struct Picker {
+    program: WebGlProgram,
+    framebuffer: WebGlFramebuffer,
+    color_texture: WebGlTexture,
+    depth_renderbuffer: WebGlRenderbuffer,
+    width: i32,
+    height: i32,
+    vertices_buffer: WebGlBuffer,
+    u_barycentric: WebGlUniformLocation,
+    matrix_uniforms: cf::MatrixUniforms,
+}
+
+impl Picker {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::pick_id::SHADER,
+            super::super::shaders::fragment::pick_id::SHADER,
+            &["aPosition", "aId", "aCorner"],
+        )
+        .unwrap();
+
+        Self {
+            framebuffer: gl
+                .create_framebuffer()
+                .ok_or("failed to create framebuffer for picking")
+                .unwrap(),
+            color_texture: gl
+                .create_texture()
+                .ok_or("failed to create texture for picking")
+                .unwrap(),
+            depth_renderbuffer: gl
+                .create_renderbuffer()
+                .ok_or("failed to create depth buffer for picking")
+                .unwrap(),
+            width: 0,
+            height: 0,
+            vertices_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create buffer for picking")
+                .unwrap(),
+            u_barycentric: gl.get_uniform_location(&program, "uBarycentric").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+        }
+    }
+
+    // (re)allocates the offscreen buffers when the canvas changes size
+    fn resize(&mut self, gl: &WebGlRenderingContext, width: i32, height: i32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        gl.bind_texture(GL::TEXTURE_2D, Some(&self.color_texture));
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA as i32,
+            width,
+            height,
+            0,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            None,
+        )
+        .unwrap();
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.bind_texture(GL::TEXTURE_2D, None);
+
+        gl.bind_renderbuffer(GL::RENDERBUFFER, Some(&self.depth_renderbuffer));
+        gl.renderbuffer_storage(GL::RENDERBUFFER, GL::DEPTH_COMPONENT16, width, height);
+        gl.bind_renderbuffer(GL::RENDERBUFFER, None);
+
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            Some(&self.color_texture),
+            0,
+        );
+        gl.framebuffer_renderbuffer(
+            GL::FRAMEBUFFER,
+            GL::DEPTH_ATTACHMENT,
+            GL::RENDERBUFFER,
+            Some(&self.depth_renderbuffer),
+        );
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        self.width = width;
+        self.height = height;
+    }
+
+    // x, y in canvas px, with y going up like in webgl
+    pub fn pick(
+        &mut self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+        targets: &Vec<PickTarget>,
+        x: f32,
+        y: f32,
+    ) -> Option<PickResult> {
+        let (pixel_x, pixel_y) = (x.floor() as i32, y.floor() as i32);
+        let (width, height) = (canvas_width as i32, canvas_height as i32);
+        if pixel_x < 0 || pixel_y < 0 || pixel_x >= width || pixel_y >= height {
+            return None;
+        }
+        self.resize(gl, width, height);
+
+        let vertices = get_pick_vertices(targets);
+        if vertices.is_empty() {
+            return None;
+        }
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let vertices_location = vertices.as_ptr() as u32 / 4;
+        let vertices_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(vertices_location, vertices_location + vertices.len() as u32);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.vertices_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vertices_array, GL::STREAM_DRAW);
+        let stride = (FLOATS_PER_VERTEX * 4) as i32;
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, stride, 0);
+        gl.vertex_attrib_pointer_with_i32(1, 4, GL::FLOAT, false, stride, 3 * 4);
+        gl.vertex_attrib_pointer_with_i32(2, 2, GL::FLOAT, false, stride, 7 * 4);
+        for attribute in 0..3 {
+            gl.enable_vertex_attrib_array(attribute);
+        }
+
+        // offscreen, exact colours (no blending), and only the one pixel we read back gets drawn
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.viewport(0, 0, width, height);
+        gl.disable(GL::BLEND);
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LESS);
+        gl.enable(GL::SCISSOR_TEST);
+        gl.scissor(pixel_x, pixel_y, 1, 1);
+        gl.clear_color(0., 0., 0., 0.);
+
+        let read_pass = |barycentric: bool| -> [u8; 4] {
+            gl.uniform1i(Some(&self.u_barycentric), barycentric as i32);
+            gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+            gl.draw_arrays(
+                GL::TRIANGLES,
+                0,
+                (vertices.len() / FLOATS_PER_VERTEX) as i32,
+            );
+            let mut pixel = [0_u8; 4];
+            gl.read_pixels_with_opt_u8_array(
+                pixel_x,
+                pixel_y,
+                1,
+                1,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                Some(&mut pixel),
+            )
+            .unwrap();
+            pixel
+        };
+        let id_pixel = read_pass(false);
+        let barycentric_pixel = if id_pixel[3] == 0 {
+            [0; 4]
+        } else {
+            read_pass(true)
+        };
+
+        // back to drawing on the canvas, the way gl_setup left things
+        gl.disable(GL::SCISSOR_TEST);
+        gl.disable(GL::DEPTH_TEST);
+        gl.enable(GL::BLEND);
+        gl.clear_color(0.0, 0.0, 0.0, 1.0);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+        gl.viewport(0, 0, width, height);
+        for attribute in 0..3 {
+            gl.disable_vertex_attrib_array(attribute);
+        }
+
+        if id_pixel[3] == 0 {
+            return None; // background
+        }
+        let from_two_bytes = |high: u8, low: u8| (high as f32 * 256. + low as f32) / 65535.;
+        let b1 = from_two_bytes(barycentric_pixel[0], barycentric_pixel[1]);
+        let b2 = from_two_bytes(barycentric_pixel[2], barycentric_pixel[3]);
+        Some(PickResult {
+            object_id: id_pixel[3] as u32,
+            triangle_index: (id_pixel[0] as u32) << 16
+                | (id_pixel[1] as u32) << 8
+                | id_pixel[2] as u32,
+            barycentric: [(1. - b1 - b2).max(0.), b1, b2],
+        })
+    }
+}
+
+// every triangle of every target as its own 3 vertices, with the triangle's id split into bytes
+fn get_pick_vertices(targets: &Vec<PickTarget>) -> Vec<f32> {
+    let mut protag: Vec<f32> = Vec::new();
+    for target in targets.iter() {
+        for (triangle_index, triangle) in target.indices.chunks(3).enumerate() {
+            let id = [
+                ((triangle_index >> 16) & 0xff) as f32 / 255.,
+                ((triangle_index >> 8) & 0xff) as f32 / 255.,
+                (triangle_index & 0xff) as f32 / 255.,
+                (target.object_id & 0xff) as f32 / 255.,
+            ];
+            for (&vertex, corner) in triangle.iter().zip([[0., 0.], [1., 0.], [0., 1.]].iter()) {
+                let vertex = 3 * vertex as usize;
+                protag.extend_from_slice(&target.positions[vertex..vertex + 3]);
+                protag.extend_from_slice(&id);
+                protag.extend_from_slice(corner);
+            }
+        }
+    }
+
+    protag
+}
@@ -0,0 +1,159 @@
+use super::super::camera::Lens;
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use js_sys::WebAssembly;
+use wasm_bindgen::JsCast;
+use web_sys::WebGlRenderingContext as GL;
+use web_sys::*;
+
+// point clouds, one round screen space sprite (GL::POINTS) per point, each with its own colour and size.
+// the points are uploaded once with set_points and sit in static buffers after that, so drawing
+// millions of them doesn't copy anything per frame. no indices either, which keeps us clear of
+// the u16 index limit
+pub struct Scatter3d {
+    pub program: WebGlProgram,
+    pub position_buffer: WebGlBuffer,
+    pub colors_buffer: WebGlBuffer,
+    pub sizes_buffer: WebGlBuffer,
+    pub point_count: i32,
+    pub has_colors: bool,
+    pub has_sizes: bool,
+    pub matrix_uniforms: cf::MatrixUniforms,
+    pub u_opacity: WebGlUniformLocation,
+}
+
+impl Scatter3d {
+    pub fn new(gl: &WebGlRenderingContext) -> Self {
+        let program = cf::link_program_with_attributes(
+            &gl,
+            super::super::shaders::vertex::scatter_3d::SHADER,
+            super::super::shaders::fragment::round_point::SHADER,
+            &["aPosition", "aColor", "aSize"],
+        )
+        .unwrap();
+
+        Self {
+            u_opacity: gl.get_uniform_location(&program, "uOpacity").unwrap(),
+            matrix_uniforms: cf::MatrixUniforms::new(&gl, &program),
+            program, // must be last as it takes over ownership of program
+
+            position_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create position buffer")
+                .unwrap(),
+            colors_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create colors buffer")
+                .unwrap(),
+            sizes_buffer: gl
+                .create_buffer()
+                .ok_or("failed to create sizes buffer")
+                .unwrap(),
+            point_count: 0,
+            has_colors: false,
+            has_sizes: false,
+        }
+    }
+
+    // xyz per point, and optionally rgb and a size (px) per point. empty colors / sizes means
+    // every point gets SCATTER_3D_COLOR / SCATTER_3D_POINT_SIZE
+    pub fn set_points(
+        &mut self,
+        gl: &WebGlRenderingContext,
+        positions: &Vec<f32>,
+        colors: &Vec<f32>,
+        sizes: &Vec<f32>,
+    ) {
+        self.upload(gl, &self.position_buffer, positions);
+        self.upload(gl, &self.colors_buffer, colors);
+        self.upload(gl, &self.sizes_buffer, sizes);
+
+        self.point_count = (positions.len() / 3) as i32;
+        self.has_colors = !colors.is_empty();
+        self.has_sizes = !sizes.is_empty();
+    }
+
+    fn upload(&self, gl: &WebGlRenderingContext, buffer: &WebGlBuffer, values: &Vec<f32>) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+        let values_location = values.as_ptr() as u32 / 4;
+        let values_array = js_sys::Float32Array::new(&memory_buffer)
+            .subarray(values_location, values_location + values.len() as u32);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &values_array, GL::STATIC_DRAW);
+    }
+
+    pub fn render(
+        &self,
+        gl: &WebGlRenderingContext,
+        bottom: f32,
+        top: f32,
+        left: f32,
+        right: f32,
+        canvas_height: f32,
+        canvas_width: f32,
+        rotation_angle_x_axis: f32,
+        rotation_angle_y_axis: f32,
+        lens: &Lens,
+    ) {
+        if self.point_count == 0 {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+
+        let projection_and_rotation_matrices = cf::get_3d_projection_matrix_and_rotation(
+            bottom,
+            top,
+            left,
+            right,
+            canvas_height,
+            canvas_width,
+            rotation_angle_x_axis,
+            rotation_angle_y_axis,
+            lens,
+        );
+        self.matrix_uniforms
+            .set(gl, &projection_and_rotation_matrices);
+        gl.uniform1f(Some(&self.u_opacity), 1.);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(0);
+
+        // colours and sizes per point if we were given them, otherwise one for all of them
+        if self.has_colors {
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.colors_buffer));
+            gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(1);
+        } else {
+            gl.disable_vertex_attrib_array(1);
+            gl.vertex_attrib3f(
+                1,
+                SCATTER_3D_COLOR[0],
+                SCATTER_3D_COLOR[1],
+                SCATTER_3D_COLOR[2],
+            );
+        }
+        if self.has_sizes {
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.sizes_buffer));
+            gl.vertex_attrib_pointer_with_i32(2, 1, GL::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(2);
+        } else {
+            gl.disable_vertex_attrib_array(2);
+            gl.vertex_attrib1f(2, SCATTER_3D_POINT_SIZE);
+        }
+
+        // points in front cover the ones behind
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LEQUAL);
+        gl.draw_arrays(GL::POINTS, 0, self.point_count);
+        gl.disable(GL::DEPTH_TEST);
+
+        // done with the attribute arrays
+        gl.disable_vertex_attrib_array(0);
+        gl.disable_vertex_attrib_array(1);
+        gl.disable_vertex_attrib_array(2);
+    }
+}
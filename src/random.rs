@@ -0,0 +1,43 @@
+// ==== tiny seeded pseudo random number generator (xorshift64*) ==== //
+// not for anything secret. the point is that the same seed gives the same numbers on every machine,
+// so whatever we build from it (terrain, perturbed starting points, ...) can be shared as just a seed
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u32) -> Self {
+        // spread the seed over all 64 bits, and make sure we never start at 0 (xorshift would stay there)
+        let mut state = (seed as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        state ^= state >> 31;
+        Self {
+            state: if state == 0 {
+                0x2545_F491_4F6C_DD1D
+            } else {
+                state
+            },
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+
+    // in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    // in [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    // in [0, upper)
+    pub fn below(&mut self, upper: usize) -> usize {
+        (self.next_u32() as u64 * upper as u64 >> 32) as usize
+    }
+}
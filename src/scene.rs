@@ -0,0 +1,200 @@
+use super::constants::*;
+use super::expression::Expr;
+use super::math::{Mat4, Vec3};
+use super::parametric::ParametricSurface;
+use super::programs::RenderMode;
+
+// the nodes every scene starts with. they can be hidden, but not removed
+pub const ROOT_NODE: usize = 0;
+pub const BACKGROUND_NODE: usize = 1;
+pub const GRAPH_NODE: usize = 2;
+
+// what a node draws, which also picks the program it's drawn with
+pub enum Drawable {
+    Group,      // nothing itself, just its children
+    Background, // the Color2D quad behind everything
+    Gradient,   // the Color2DGradient quad, inset in the display box
+    // whatever the surface mode shows, with the hover marker, tools, overlays and annotations.
+    // picking and measuring work in its coordinates, so it never moves (see Scene::set_transform)
+    Graph,
+    Surface(Expr), // another height field, y = f(x, z, t) over the graph's grid
+    Parametric(ParametricSurface), // another parametric surface
+    Markers(Vec<f32>), // a 3d cross on every xyz point
+}
+
+// scale, then rotate (about x, then y, then z, in radians), then translate
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: [0., 0., 0.],
+            rotation: [0., 0., 0.],
+            scale: [1., 1., 1.],
+        }
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::translation(Vec3::from_array(self.translation))
+            * Mat4::rotation_z(self.rotation[2])
+            * Mat4::rotation_y(self.rotation[1])
+            * Mat4::rotation_x(self.rotation[0])
+            * Mat4::scaling(Vec3::from_array(self.scale))
+    }
+}
+
+// how a node looks, for the programs that can take it. parametric surfaces keep their own colour
+#[derive(Clone, Copy, Debug)]
+pub struct Material {
+    pub color: [f32; 3],
+    pub render_mode: RenderMode,
+}
+
+pub struct SceneNode {
+    pub drawable: Drawable,
+    pub transform: Transform,
+    pub material: Material,
+    pub visible: bool, // hidden nodes hide their children too
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// ==== a tree of nodes, each placed relative to its parent ==== //
+// the nodes live in one vec and refer to each other by index (their id). removed nodes leave
+// a None behind, so the other ids stay put
+pub struct Scene {
+    nodes: Vec<Option<SceneNode>>,
+}
+
+impl Scene {
+    // the root, with the background and the graph under it
+    pub fn new() -> Self {
+        let mut protag = Self { nodes: Vec::new() };
+        protag.nodes.push(Some(SceneNode {
+            drawable: Drawable::Group,
+            transform: Transform::identity(),
+            material: default_material(),
+            visible: true,
+            parent: None,
+            children: Vec::new(),
+        }));
+        protag.add(ROOT_NODE, Drawable::Background).unwrap();
+        protag.add(ROOT_NODE, Drawable::Graph).unwrap();
+        protag
+    }
+
+    pub fn get(&self, id: usize) -> Option<&SceneNode> {
+        self.nodes.get(id).and_then(|node| node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut SceneNode> {
+        self.nodes.get_mut(id).and_then(|node| node.as_mut())
+    }
+
+    // returns the new node's id
+    pub fn add(&mut self, parent: usize, drawable: Drawable) -> Result<usize, String> {
+        let id = self.nodes.len();
+        self.get_mut(parent)
+            .ok_or(format!("there's no scene node {}", parent))?
+            .children
+            .push(id);
+        self.nodes.push(Some(SceneNode {
+            drawable: drawable,
+            transform: Transform::identity(),
+            material: default_material(),
+            visible: true,
+            parent: Some(parent),
+            children: Vec::new(),
+        }));
+        Ok(id)
+    }
+
+    // the root and the graph stay where they are: the graph's picking, measuring and annotations
+    // all assume it's drawn in its own coordinates
+    pub fn set_transform(&mut self, id: usize, transform: Transform) -> Result<(), String> {
+        if id == ROOT_NODE || id == GRAPH_NODE {
+            return Err(String::from("the root and graph nodes can't be moved"));
+        }
+        self.get_mut(id)
+            .ok_or(format!("there's no scene node {}", id))?
+            .transform = transform;
+        Ok(())
+    }
+
+    // removes the node and everything under it
+    pub fn remove(&mut self, id: usize) -> Result<(), String> {
+        if id == ROOT_NODE || id == BACKGROUND_NODE || id == GRAPH_NODE {
+            return Err(String::from(
+                "the built in scene nodes can be hidden, but not removed",
+            ));
+        }
+        let parent = self
+            .get(id)
+            .ok_or(format!("there's no scene node {}", id))?
+            .parent;
+        if let Some(parent) = parent.and_then(|parent| self.get_mut(parent)) {
+            parent.children.retain(|&child| child != id);
+        }
+
+        let mut to_remove = vec![id];
+        while let Some(id) = to_remove.pop() {
+            if let Some(node) = self.nodes[id].take() {
+                to_remove.extend_from_slice(&node.children);
+            }
+        }
+        Ok(())
+    }
+
+    // ---- traversal ---- //
+    // every visible node with its world transform (its own, then its parent's, and so on up), parents
+    // before their children and siblings in the order they were added. that's the order they're drawn in
+    pub fn get_draw_list(&self) -> Vec<(usize, Mat4)> {
+        let mut protag: Vec<(usize, Mat4)> = Vec::new();
+        let mut to_visit: Vec<(usize, Mat4)> = vec![(ROOT_NODE, Mat4::identity())];
+        while let Some((id, parent_matrix)) = to_visit.pop() {
+            let node = match self.get(id) {
+                Some(node) if node.visible => node,
+                _ => continue,
+            };
+            let world = parent_matrix * node.transform.to_matrix();
+            protag.push((id, world));
+            // a stack, so the children go on backwards to come off in order
+            for &child in node.children.iter().rev() {
+                to_visit.push((child, world));
+            }
+        }
+        protag
+    }
+}
+
+fn default_material() -> Material {
+    Material {
+        color: GRAPH_3D_COLOR,
+        render_mode: RenderMode::Solid,
+    }
+}
+
+// y values for a Surface node, laid out like the graph's. the expression gets x, z and t (seconds)
+pub fn get_surface_heights(expression: &Expr, n: usize, time: f32) -> Vec<f32> {
+    let points_per_row = n + 1;
+    let square_size = 2. / n as f32;
+    let t = time / 1000.;
+    let mut protag: Vec<f32> = Vec::with_capacity(points_per_row * points_per_row);
+    for z in 0..points_per_row {
+        for x in 0..points_per_row {
+            let value = expression.eval(&[
+                -1. + x as f32 * square_size,
+                -1. + z as f32 * square_size,
+                t,
+            ]);
+            // nan would take the whole draw down with it
+            protag.push(if value.is_finite() { value } else { 0. });
+        }
+    }
+    protag
+}
@@ -1,2 +1,6 @@
 pub mod color_2d;
+pub mod pick_id;
+pub mod pin;
+pub mod round_point;
+pub mod thick_line;
 pub mod varying_color_from_vertex;
@@ -0,0 +1,26 @@
+pub const SHADER: &str = r#"
+    #ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+    #else
+    precision mediump float;
+    #endif
+    uniform bool uBarycentric;
+    varying vec4 vId;
+    varying vec2 vCorner;
+
+    // 0 to 1 into two bytes, high byte first
+    vec2 toTwoBytes(float value) {
+        float scaled = floor(clamp(value, 0., 1.) * 65535. + 0.5);
+        float high = floor(scaled / 256.);
+        return vec2(high, scaled - high * 256.) / 255.;
+    }
+
+    void main(){
+        // one rgba pixel only holds 4 bytes, so the id and where in the triangle we are take a pass each
+        if (uBarycentric) {
+            gl_FragColor = vec4(toTwoBytes(vCorner.x), toTwoBytes(vCorner.y));
+        } else {
+            gl_FragColor = vId;
+        }
+    }
+"#;
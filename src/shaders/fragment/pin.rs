@@ -0,0 +1,20 @@
+pub const SHADER: &str = r#"
+    precision mediump float;
+    uniform float uOpacity;
+    varying lowp vec4 vColor;
+    varying vec2 vCorner;
+
+    void main(){
+        // a round head at the top of the quad, and a thin stem down to the tip
+        vec2 fromHead = vCorner - vec2(0., 0.7);
+        float headDistance = length(fromHead);
+        bool inHead = headDistance < 0.28;
+        bool inStem = abs(vCorner.x) < 0.04 && vCorner.y < 0.7;
+        if (!inHead && !inStem) {
+            discard;
+        }
+        // darker rim around the head so it stands out on a surface of the same colour
+        float shade = inHead && headDistance > 0.2 ? 0.5 : 1.;
+        gl_FragColor = vec4( vColor.rgb * shade, vColor.a * uOpacity );
+    }
+"#;
@@ -0,0 +1,14 @@
+pub const SHADER: &str = r#"
+    precision mediump float;
+    uniform float uOpacity;
+    varying lowp vec4 vColor;
+
+    void main(){
+        // GL::POINTS are squares. cut the corners off to get a round dot
+        vec2 fromCenter = gl_PointCoord - vec2(0.5);
+        if (dot(fromCenter, fromCenter) > 0.25) {
+            discard;
+        }
+        gl_FragColor = vec4( vColor.rgb, vColor.a * uOpacity );
+    }
+"#;
@@ -0,0 +1,24 @@
+pub const SHADER: &str = r#"
+    precision mediump float;
+    uniform float uOpacity;
+    uniform float uDashLength;
+    uniform float uGapLength;
+    uniform bool uJoins;
+    varying lowp vec4 vColor;
+    varying float vDistance;
+
+    void main(){
+        // the joins are GL::POINTS, i.e. squares. round them off
+        if (uJoins) {
+            vec2 fromCenter = gl_PointCoord - vec2(0.5);
+            if (dot(fromCenter, fromCenter) > 0.25) {
+                discard;
+            }
+        }
+        // no gap, no dashes
+        if (uGapLength > 0. && mod(vDistance, uDashLength + uGapLength) > uDashLength) {
+            discard;
+        }
+        gl_FragColor = vec4( vColor.rgb, vColor.a * uOpacity );
+    }
+"#;
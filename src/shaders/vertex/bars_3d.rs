@@ -0,0 +1,27 @@
+pub const SHADER: &str = concat!(
+    directional_lighting_glsl!(),
+    r#"
+attribute vec4 aPosition; // unit cube, 0 to 1 on every axis
+attribute vec3 aVertexNormal;
+attribute vec3 aOffset; // per bar from here down
+attribute vec3 aScale;
+attribute vec3 aColor;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform mat4 uNormalMatrix;
+varying lowp vec4 vColor;
+
+void main() {
+    gl_Position = uProjection * uView * uModel * vec4(aOffset + aPosition.xyz * aScale, 1.);
+
+    // a bar hanging down (negative height) is the cube flipped upside down, so its top and bottom swap
+    vec3 normal = aVertexNormal * vec3(1., aScale.y < 0. ? -1. : 1., 1.);
+    vec3 transformedNormal = normalize((uNormalMatrix * vec4(normal, 0.)).xyz);
+    vec3 vLighting = getDirectionalLighting(transformedNormal);
+
+    vColor = vec4( aColor * vLighting, 1.0 );
+}
+
+"#
+);
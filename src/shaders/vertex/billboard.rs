@@ -0,0 +1,30 @@
+pub const SHADER: &str = r#"
+attribute vec3 aAnchor; // where the pin's tip goes
+attribute vec2 aCorner; // the quad's corner, x from -0.5 to 0.5 and y from 0 (the tip) to 1
+attribute vec3 aColor;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform vec2 uViewport; // canvas size in px
+uniform float uSize; // px
+uniform float uDepthBias; // towards the camera, in the graph's units
+varying lowp vec4 vColor;
+varying vec2 vCorner;
+
+void main() {
+    vec4 viewPosition = uView * uModel * vec4(aAnchor, 1.);
+    gl_Position = uProjection * viewPosition;
+
+    // the whole pin takes its tip's depth, so it's either all hidden behind the surface or not at all.
+    // a pin on the surface sits at exactly the surface's depth, so its depth comes from a touch nearer
+    // the camera (which looks down -z) to win the tie. only the depth: the pin stays where it is on screen
+    vec4 nudged = uProjection * (viewPosition + vec4(0., 0., uDepthBias, 0.));
+    gl_Position.z = nudged.z / nudged.w * gl_Position.w;
+
+    // always facing us, and always the same size on screen
+    gl_Position.xy += 2. * aCorner * uSize / uViewport * gl_Position.w;
+
+    vColor = vec4(aColor, 1.);
+    vCorner = aCorner;
+}
+"#;
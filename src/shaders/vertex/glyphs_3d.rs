@@ -0,0 +1,33 @@
+pub const SHADER: &str = concat!(
+    directional_lighting_glsl!(),
+    r#"
+attribute vec4 aPosition; // an arrow pointing up the y axis, from y = 0 to 1
+attribute vec3 aVertexNormal;
+attribute vec3 aOffset; // per glyph from here down
+attribute vec3 aDirection; // its length is the glyph's length
+attribute vec3 aColor;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform mat4 uNormalMatrix;
+varying lowp vec4 vColor;
+
+void main() {
+    // turn the arrow's y axis onto the direction. the other two axes are any pair at right angles to it
+    float glyphLength = length(aDirection);
+    vec3 forward = glyphLength > 0. ? aDirection / glyphLength : vec3(0., 1., 0.);
+    vec3 helper = abs(forward.y) < 0.99 ? vec3(0., 1., 0.) : vec3(1., 0., 0.);
+    vec3 side = normalize(cross(helper, forward));
+    vec3 up = cross(forward, side);
+    mat3 orientation = mat3(side, forward, up);
+
+    gl_Position = uProjection * uView * uModel * vec4(aOffset + orientation * aPosition.xyz * glyphLength, 1.);
+
+    vec3 transformedNormal = normalize((uNormalMatrix * vec4(orientation * aVertexNormal, 0.)).xyz);
+    vec3 vLighting = getDirectionalLighting(transformedNormal);
+
+    vColor = vec4( aColor * vLighting, 1.0 );
+}
+
+"#
+);
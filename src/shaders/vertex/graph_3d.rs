@@ -1,24 +1,28 @@
-pub const SHADER: &str = r#"
+pub const SHADER: &str = concat!(
+    directional_lighting_glsl!(),
+    r#"
 attribute vec4 aPosition;
 attribute float aY;
 attribute vec3 aVertexNormal;
-uniform mat4 uNormalsRotation;
+attribute vec3 aColor;
+uniform mat4 uModel;
+uniform mat4 uView;
 uniform mat4 uProjection;
+uniform mat4 uNormalMatrix;
+uniform bool uUnlit; // for drawing the wireframe
 varying lowp vec4 vColor;
 
 void main() {
-    gl_Position = uProjection * vec4(aPosition.x, aY, aPosition.z, 1.);
+    gl_Position = uProjection * uView * uModel * vec4(aPosition.x, aY, aPosition.z, 1.);
 
-    vec3 ambientLight = vec3(0.2,0.2,0.2); // soft white light
-    vec3 directionalLightColor = vec3(1,1,1); // strong directed white light
-    vec3 directionalVector = normalize(vec3(-0.85, 0.8, 0.75)); //"over left shoulder"
+    // in view space too, so the light stays over the viewer's shoulder. w = 0 so translations leave
+    // normals alone, and normalized since the normal matrix only keeps their direction
+    vec3 transformedNormal = normalize((uNormalMatrix * vec4(aVertexNormal, 0.)).xyz); // determines if vertex is facing camera or not
+    vec3 vLighting = getDirectionalLighting(transformedNormal);
+    // aColor is either a colour per vertex, or (with its array switched off) one colour for the lot
 
-    vec4 transformedNormal = uNormalsRotation * vec4(aVertexNormal, 1.0); // determines if vertex is facing camera or not
-    float directional = max(dot(transformedNormal.xyz, directionalVector), 0.0);
-    vec3 vLighting = ambientLight + (directionalLightColor * directional); // the more you are facing the light source, the brighter it is
-    vec3 baseColor = vec3( 0.5, 0.5, 0.8 ); // slightly bluish surface. shall combine with lighting
-
-    vColor = vec4( baseColor * vLighting, 1.0 ); // opacity at 1.0
+    vColor = vec4( uUnlit ? aColor : aColor * vLighting, 1.0 ); // opacity at 1.0
 }
 
-"#;
+"#
+);
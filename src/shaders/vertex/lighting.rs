@@ -0,0 +1,114 @@
+// glsl shared by the lit 3d shaders. it's a macro rather than a const so that it can be
+// concat!-ed onto the front of their SHADER consts at compile time.
+// the normal handed in is expected to be in view space already (see common_funcs::Matrices3D), normalized
+macro_rules! directional_lighting_glsl {
+    () => {
+        r#"
+// the more you are facing the light source, the brighter it is.
+// two sided lights the back of a surface like its front, for surfaces we see from both sides
+vec3 getLighting(vec3 transformedNormal, bool twoSided) {
+    vec3 ambientLight = vec3(0.2,0.2,0.2); // soft white light
+    vec3 directionalLightColor = vec3(1,1,1); // strong directed white light
+    vec3 directionalVector = normalize(vec3(-0.85, 0.8, 0.75)); //"over left shoulder"
+
+    float facing = dot(transformedNormal, directionalVector);
+    float directional = twoSided ? abs(facing) : max(facing, 0.0);
+    return ambientLight + (directionalLightColor * directional);
+}
+
+vec3 getDirectionalLighting(vec3 transformedNormal) {
+    return getLighting(transformedNormal, false);
+}
+
+vec3 getTwoSidedLighting(vec3 transformedNormal) {
+    return getLighting(transformedNormal, true);
+}
+"#
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::Lens;
+    use crate::common_funcs::{
+        apply_model_matrix, get_3d_projection_matrix_and_rotation, Matrices3D,
+    };
+    use crate::math::{Mat4, Vec3};
+
+    // the numbers inside the glsl's vec3(...) after name
+    fn read_vec3(name: &str) -> [f32; 3] {
+        let glsl = directional_lighting_glsl!();
+        let start = glsl.find(name).unwrap();
+        let after = &glsl[start..];
+        let open = after.find("vec3(").unwrap() + "vec3(".len();
+        let close = open + after[open..].find(')').unwrap();
+        let values: Vec<f32> = after[open..close]
+            .split(',')
+            .map(|value| value.trim().parse().unwrap())
+            .collect();
+        [values[0], values[1], values[2]]
+    }
+
+    // what a lit vertex ends up with, for one colour channel: the vertex shader's
+    // uNormalMatrix * vec4(aNormal, 0.), normalized, then getLighting
+    fn get_vertex_lighting(matrices: &Matrices3D, normal: Vec3, two_sided: bool) -> f32 {
+        let normal = matrices.normal_matrix.transform_vector(normal).normalize();
+        let ambient = read_vec3("vec3 ambientLight")[0];
+        let color = read_vec3("vec3 directionalLightColor")[0];
+        let direction = Vec3::from_array(read_vec3("vec3 directionalVector")).normalize();
+        let facing = normal.dot(direction);
+        let directional = if two_sided {
+            facing.abs()
+        } else {
+            facing.max(0.)
+        };
+        ambient + color * directional
+    }
+
+    fn get_matrices(rotation_x: f32, rotation_y: f32) -> Matrices3D {
+        get_3d_projection_matrix_and_rotation(
+            0.,
+            600.,
+            0.,
+            800.,
+            600.,
+            800.,
+            rotation_x,
+            rotation_y,
+            &Lens::new(),
+        )
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    // the reference values are 0.2 + n . (-0.85, 0.8, 0.75) / sqrt(1.925), with n the normal in view space
+    #[test]
+    fn lit_vertices() {
+        let up = Vec3::new(0., 1., 0.);
+        let straight_on = get_matrices(0., 0.);
+        assert_close(get_vertex_lighting(&straight_on, up, false), 0.776600);
+        // the underside is only lit from both sides
+        assert_close(get_vertex_lighting(&straight_on, up * -1., false), 0.2);
+        assert_close(get_vertex_lighting(&straight_on, up * -1., true), 0.776600);
+
+        // turned a quarter about y, the graph's +x side faces the camera (+z in view space)
+        let turned = get_matrices(0., std::f32::consts::FRAC_PI_2);
+        assert_close(
+            get_vertex_lighting(&turned, Vec3::new(1., 0., 0.), false),
+            0.740562,
+        );
+
+        // tilted by 0.5, the top faces (0, cos 0.5, -sin 0.5), away from the light a little
+        let tilted = get_matrices(0.5, 0.);
+        assert_close(get_vertex_lighting(&tilted, up, false), 0.446855);
+
+        // stretched twice as wide, a 45 degree slope leans back towards the vertical: (1, 2, 0) / sqrt(5)
+        let stretched = apply_model_matrix(&straight_on, &Mat4::scaling(Vec3::new(2., 1., 1.)));
+        assert_close(
+            get_vertex_lighting(&stretched, Vec3::new(1., 1., 0.), false),
+            0.441747,
+        );
+    }
+}
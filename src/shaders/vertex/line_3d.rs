@@ -0,0 +1,37 @@
+pub const SHADER: &str = r#"
+attribute vec3 aStart; // the segment this vertex belongs to
+attribute vec3 aEnd;
+attribute vec3 aCorner; // x: 0 at the start, 1 at the end. y: which side (-1 or +1). z: 1 to stick out past the end (square caps)
+attribute vec3 aColor;
+attribute float aDistance; // along the polyline, for the dashes
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform vec2 uViewport; // canvas size in px
+uniform float uWidth; // px
+uniform bool uJoins; // drawing the round joins (GL::POINTS) rather than the segments
+varying lowp vec4 vColor;
+varying float vDistance;
+
+void main() {
+    mat4 modelViewProjection = uProjection * uView * uModel;
+    vec4 start = modelViewProjection * vec4(aStart, 1.);
+    vec4 end = modelViewProjection * vec4(aEnd, 1.);
+    gl_Position = mix(start, end, aCorner.x);
+
+    // webgl won't draw lines wider than 1px, so each segment is a quad instead, pushed out sideways
+    // by half the width on screen. which way is sideways depends on where the segment ends up on screen
+    vec2 screenDirection = (end.xy / end.w - start.xy / start.w) * uViewport;
+    if (length(screenDirection) < 0.0001) {
+        screenDirection = vec2(1., 0.); // pointing straight at us, any side will do
+    }
+    screenDirection = normalize(screenDirection);
+    vec2 screenNormal = vec2(-screenDirection.y, screenDirection.x);
+    vec2 offset = 0.5 * uWidth * (aCorner.y * screenNormal + aCorner.z * (2. * aCorner.x - 1.) * screenDirection);
+    gl_Position.xy += 2. * offset / uViewport * gl_Position.w; // px to clip space
+
+    gl_PointSize = uJoins ? uWidth : 1.;
+    vColor = vec4(aColor, 1.0);
+    vDistance = aDistance;
+}
+"#;
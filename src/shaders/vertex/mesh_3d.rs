@@ -0,0 +1,24 @@
+pub const SHADER: &str = concat!(
+    directional_lighting_glsl!(),
+    r#"
+attribute vec4 aPosition;
+attribute vec3 aVertexNormal;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform mat4 uNormalMatrix;
+uniform vec3 uBaseColor;
+varying lowp vec4 vColor;
+
+void main() {
+    gl_Position = uProjection * uView * uModel * vec4(aPosition.xyz, 1.);
+
+    vec3 transformedNormal = normalize((uNormalMatrix * vec4(aVertexNormal, 0.)).xyz);
+    // two sided: which side of a generated mesh is "outside" depends on where it came from
+    vec3 vLighting = getTwoSidedLighting(transformedNormal);
+
+    vColor = vec4( uBaseColor * vLighting, 1.0 );
+}
+
+"#
+);
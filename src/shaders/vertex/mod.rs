@@ -1,3 +1,14 @@
+#[macro_use]
+mod lighting;
+pub mod bars_3d;
+pub mod billboard;
 pub mod color_2d;
 pub mod color_2d_gradient;
+pub mod glyphs_3d;
 pub mod graph_3d;
+pub mod line_3d;
+pub mod lines;
+pub mod mesh_3d;
+pub mod parametric_3d;
+pub mod pick_id;
+pub mod scatter_3d;
@@ -0,0 +1,27 @@
+pub const SHADER: &str = concat!(
+    directional_lighting_glsl!(),
+    r#"
+attribute vec4 aPosition;
+attribute vec3 aVertexNormal;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+uniform mat4 uNormalMatrix;
+varying lowp vec4 vColor;
+
+void main() {
+    gl_Position = uProjection * uView * uModel * vec4(aPosition.xyz, 1.); // unlike graph_3d, all 3 coords come from the buffer
+
+    // w = 0 so scene nodes' translations leave normals alone, and normalized since the normal matrix
+    // only keeps their direction
+    vec3 transformedNormal = normalize((uNormalMatrix * vec4(aVertexNormal, 0.)).xyz);
+    // two sided: we see the inside of closed surfaces through the transparency,
+    // and mobius strips and klein bottles don't have an outside to begin with
+    vec3 vLighting = getTwoSidedLighting(transformedNormal);
+    vec3 baseColor = vec3( 0.8, 0.55, 0.35 ); // slightly orange, to tell it apart from the height field
+
+    vColor = vec4( baseColor * vLighting, 1.0 );
+}
+
+"#
+);
@@ -0,0 +1,17 @@
+pub const SHADER: &str = r#"
+attribute vec4 aPosition;
+attribute vec4 aId; // the triangle's id, already split into bytes (0 to 1 each)
+attribute vec2 aCorner; // (0, 0), (1, 0) or (0, 1), which corner of its triangle this vertex is
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+varying vec4 vId;
+varying vec2 vCorner;
+
+void main() {
+    gl_Position = uProjection * uView * uModel * vec4(aPosition.xyz, 1.); // the depth test keeps the nearest triangle
+
+    vId = aId;
+    vCorner = aCorner;
+}
+"#;
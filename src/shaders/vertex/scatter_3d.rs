@@ -0,0 +1,16 @@
+pub const SHADER: &str = r#"
+attribute vec4 aPosition;
+attribute vec3 aColor;
+attribute float aSize;
+uniform mat4 uModel;
+uniform mat4 uView;
+uniform mat4 uProjection;
+varying lowp vec4 vColor;
+
+void main() {
+    gl_Position = uProjection * uView * uModel * vec4(aPosition.xyz, 1.);
+
+    gl_PointSize = aSize; // px on screen, whatever the zoom
+    vColor = vec4(aColor, 1.0);
+}
+"#;
@@ -0,0 +1,292 @@
+use super::super::constants::*;
+use super::simulation::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeatScheme {
+    Explicit, // forward euler. cheap, but has to take small sub steps when diffusivity is high
+    Adi,      // peaceman-rachford alternating direction implicit. stable for any step size
+}
+
+// ==== the heat equation, u_t = diffusivity * (u_xx + u_zz) ==== //
+// the temperature itself is used as height.
+// parameters: "diffusivity" (grid units^2 per second, 0 to HEAT_MAX_DIFFUSIVITY), and "max_diffusivity" to read
+pub struct HeatSimulation {
+    points_per_row: usize,
+    scheme: HeatScheme,
+    temperatures: Vec<f32>,
+    scratch: Vec<f32>, // the intermediate half step for adi, the next temperatures for explicit
+    diffusivity: f32,
+    boundary: Boundary,
+
+    // for the tridiagonal solves along one row or column
+    lower: Vec<f32>,
+    diagonal: Vec<f32>,
+    upper: Vec<f32>,
+    line: Vec<f32>,
+    line_scratch: Vec<f32>,
+}
+
+impl HeatSimulation {
+    pub fn new(n: usize, scheme: HeatScheme) -> Self {
+        let points_per_row = n + 1;
+        let mut protag = Self {
+            points_per_row: points_per_row,
+            scheme: scheme,
+            temperatures: vec![0.; points_per_row * points_per_row],
+            scratch: vec![0.; points_per_row * points_per_row],
+            diffusivity: HEAT_DEFAULT_DIFFUSIVITY,
+            boundary: Boundary::Fixed,
+            lower: vec![0.; points_per_row],
+            diagonal: vec![0.; points_per_row],
+            upper: vec![0.; points_per_row],
+            line: vec![0.; points_per_row],
+            line_scratch: vec![0.; points_per_row],
+        };
+        protag.reset();
+        protag
+    }
+
+    // diffusivity * dt / dx^2, the only number the schemes care about
+    fn mesh_ratio(&self, time_step: f32) -> f32 {
+        let dx = cell_size(self.points_per_row);
+        self.diffusivity * time_step / (dx * dx)
+    }
+
+    fn step_explicit(&mut self) {
+        let n = self.points_per_row;
+        // 2d forward euler is only stable for ratio <= 1/4, so split the step up as much as needed
+        let sub_steps = (self.mesh_ratio(HEAT_TIME_STEP) / HEAT_MAX_EXPLICIT_RATIO)
+            .ceil()
+            .max(1.);
+        let ratio = self.mesh_ratio(HEAT_TIME_STEP / sub_steps);
+
+        for _ in 0..sub_steps as usize {
+            for z in 0..n {
+                for x in 0..n {
+                    self.scratch[z * n + x] = self.temperatures[z * n + x]
+                        + ratio * laplacian(&self.temperatures, n, x, z);
+                }
+            }
+            std::mem::swap(&mut self.temperatures, &mut self.scratch);
+            if self.boundary == Boundary::Fixed {
+                set_edges(&mut self.temperatures, n, 0.);
+            }
+        }
+    }
+
+    // two half steps. first implicit along x (explicit along z), then the other way round:
+    //   (1 - r d_xx) u_half = (1 + r d_zz) u
+    //   (1 - r d_zz) u_next = (1 + r d_xx) u_half
+    // with r = diffusivity * dt / (2 dx^2). each line is then a tridiagonal solve
+    fn step_adi(&mut self) {
+        let n = self.points_per_row;
+        let r = 0.5 * self.mesh_ratio(HEAT_TIME_STEP);
+        self.fill_implicit_line_matrix(r);
+
+        // sweep 1: rows (fixed z), implicit along x. result goes into scratch
+        for z in 0..n {
+            for x in 0..n {
+                let up = self.temperatures[mirrored_index(n, x, z, 0, -1)];
+                let down = self.temperatures[mirrored_index(n, x, z, 0, 1)];
+                let center = self.temperatures[z * n + x];
+                self.line[x] = center + r * (up - 2. * center + down);
+            }
+            self.solve_line();
+            for x in 0..n {
+                self.scratch[z * n + x] = self.line[x];
+            }
+        }
+
+        // sweep 2: columns (fixed x), implicit along z. result goes back into temperatures
+        for x in 0..n {
+            for z in 0..n {
+                let left = self.scratch[mirrored_index(n, x, z, -1, 0)];
+                let right = self.scratch[mirrored_index(n, x, z, 1, 0)];
+                let center = self.scratch[z * n + x];
+                self.line[z] = center + r * (left - 2. * center + right);
+            }
+            self.solve_line();
+            for z in 0..n {
+                self.temperatures[z * n + x] = self.line[z];
+            }
+        }
+
+        if self.boundary == Boundary::Fixed {
+            set_edges(&mut self.temperatures, n, 0.);
+        }
+    }
+
+    // the (1 - r d_xx) matrix, same for every row and column
+    fn fill_implicit_line_matrix(&mut self, r: f32) {
+        let n = self.points_per_row;
+        for i in 0..n {
+            self.lower[i] = -r;
+            self.diagonal[i] = 1. + 2. * r;
+            self.upper[i] = -r;
+        }
+        match self.boundary {
+            Boundary::Fixed => {
+                // end points just keep their (pinned) value
+                self.diagonal[0] = 1.;
+                self.upper[0] = 0.;
+                self.diagonal[n - 1] = 1.;
+                self.lower[n - 1] = 0.;
+            }
+            Boundary::Free => {
+                // mirrored neighbour outside the grid is the same as the one inside
+                self.upper[0] = -2. * r;
+                self.lower[n - 1] = -2. * r;
+            }
+        }
+    }
+
+    fn solve_line(&mut self) {
+        let n = self.points_per_row;
+        if self.boundary == Boundary::Fixed {
+            self.line[0] = 0.;
+            self.line[n - 1] = 0.;
+        }
+        solve_tridiagonal(
+            &self.lower,
+            &self.diagonal,
+            &self.upper,
+            &mut self.line,
+            &mut self.line_scratch,
+        );
+    }
+}
+
+impl Simulation for HeatSimulation {
+    fn heights(&self) -> &Vec<f32> {
+        &self.temperatures
+    }
+
+    fn time_step(&self) -> f32 {
+        HEAT_TIME_STEP
+    }
+
+    fn step(&mut self) {
+        match self.scheme {
+            HeatScheme::Explicit => self.step_explicit(),
+            HeatScheme::Adi => self.step_adi(),
+        }
+    }
+
+    // a couple of hot and cold spots, so there is something to watch diffuse
+    fn reset(&mut self) {
+        let n = self.points_per_row;
+        for i in 0..self.temperatures.len() {
+            self.temperatures[i] = 0.;
+        }
+        let radius = n as f32 / 12.;
+        add_gaussian(
+            &mut self.temperatures,
+            n,
+            n / 3,
+            n / 3,
+            radius,
+            HEAT_IMPULSE_AMPLITUDE,
+        );
+        add_gaussian(
+            &mut self.temperatures,
+            n,
+            2 * n / 3,
+            n / 2,
+            radius,
+            -HEAT_IMPULSE_AMPLITUDE,
+        );
+        add_gaussian(
+            &mut self.temperatures,
+            n,
+            n / 2,
+            3 * n / 4,
+            radius,
+            HEAT_IMPULSE_AMPLITUDE,
+        );
+        if self.boundary == Boundary::Fixed {
+            set_edges(&mut self.temperatures, n, 0.);
+        }
+    }
+
+    fn add_impulse(&mut self, x: usize, z: usize) {
+        add_gaussian(
+            &mut self.temperatures,
+            self.points_per_row,
+            x,
+            z,
+            HEAT_IMPULSE_RADIUS,
+            HEAT_IMPULSE_AMPLITUDE,
+        );
+        if self.boundary == Boundary::Fixed {
+            set_edges(&mut self.temperatures, self.points_per_row, 0.);
+        }
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        if boundary == Boundary::Fixed {
+            set_edges(&mut self.temperatures, self.points_per_row, 0.);
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> Result<(), String> {
+        match name {
+            "diffusivity" => {
+                if !value.is_finite() {
+                    return Err(format!("diffusivity has to be a number, not {}", value));
+                }
+                self.diffusivity = value.max(0.).min(HEAT_MAX_DIFFUSIVITY)
+            }
+            _ => return Err(format!("heat simulation has no parameter named {}", name)),
+        }
+        Ok(())
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "diffusivity" => Some(self.diffusivity),
+            "max_diffusivity" => Some(HEAT_MAX_DIFFUSIVITY),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffusivity_is_kept_to_what_the_explicit_scheme_can_take() {
+        let mut heat = HeatSimulation::new(GRID_SIZE, HeatScheme::Explicit);
+        heat.set_parameter("diffusivity", 1e9).unwrap();
+        assert_eq!(
+            heat.get_parameter("diffusivity"),
+            Some(HEAT_MAX_DIFFUSIVITY)
+        );
+        heat.set_parameter("diffusivity", -1.).unwrap();
+        assert_eq!(heat.get_parameter("diffusivity"), Some(0.));
+
+        assert!(heat
+            .set_parameter("diffusivity", std::f32::INFINITY)
+            .is_err());
+        assert!(heat.set_parameter("diffusivity", std::f32::NAN).is_err());
+        assert_eq!(heat.get_parameter("diffusivity"), Some(0.));
+    }
+
+    #[test]
+    fn explicit_scheme_stays_stable_at_the_max_diffusivity() {
+        let mut heat = HeatSimulation::new(GRID_SIZE, HeatScheme::Explicit);
+        heat.set_parameter("diffusivity", HEAT_MAX_DIFFUSIVITY)
+            .unwrap();
+        heat.add_impulse(GRID_SIZE / 2, GRID_SIZE / 2);
+        let peak = heat.heights().iter().cloned().fold(0., f32::max);
+        for _ in 0..10 {
+            heat.step();
+        }
+        // spreading out, never overshooting
+        assert!(heat
+            .heights()
+            .iter()
+            .all(|height| height.is_finite() && height.abs() <= peak));
+    }
+}
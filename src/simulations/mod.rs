@@ -0,0 +1,8 @@
+mod heat;
+pub use heat::*;
+mod reaction_diffusion;
+pub use reaction_diffusion::*;
+mod simulation;
+pub use simulation::*;
+mod wave;
+pub use wave::*;
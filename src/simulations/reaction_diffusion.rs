@@ -0,0 +1,212 @@
+use super::super::constants::*;
+use super::simulation::*;
+
+// ==== gray-scott reaction diffusion ==== //
+// two chemicals, u and v. v eats u to make more of itself (u + 2v -> 3v), u is fed in, v is killed off:
+//   u_t = diffusion_u * lap(u) - u v^2 + feed * (1 - u)
+//   v_t = diffusion_v * lap(v) + u v^2 - (feed + kill) * v
+// everything is in grid cells and steps (dx = dt = 1), which is how the usual parameter maps are drawn.
+// the concentration of v is used as height.
+// parameters: "feed" (0 to GRAY_SCOTT_MAX_FEED), "kill" (0 to GRAY_SCOTT_MAX_KILL), "diffusion_u" and
+// "diffusion_v" (0 to GRAY_SCOTT_MAX_DIFFUSION)
+pub struct ReactionDiffusionSimulation {
+    points_per_row: usize,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    next_u: Vec<f32>,
+    next_v: Vec<f32>,
+    heights: Vec<f32>,
+    feed: f32,
+    kill: f32,
+    diffusion_u: f32,
+    diffusion_v: f32,
+    boundary: Boundary,
+}
+
+impl ReactionDiffusionSimulation {
+    pub fn new(n: usize) -> Self {
+        let points_per_row = n + 1;
+        let point_count = points_per_row * points_per_row;
+        let mut protag = Self {
+            points_per_row: points_per_row,
+            u: vec![1.; point_count],
+            v: vec![0.; point_count],
+            next_u: vec![1.; point_count],
+            next_v: vec![0.; point_count],
+            heights: vec![0.; point_count],
+            feed: GRAY_SCOTT_DEFAULT_FEED,
+            kill: GRAY_SCOTT_DEFAULT_KILL,
+            diffusion_u: GRAY_SCOTT_DEFAULT_DIFFUSION_U,
+            diffusion_v: GRAY_SCOTT_DEFAULT_DIFFUSION_V,
+            boundary: Boundary::Fixed,
+        };
+        protag.reset();
+        protag
+    }
+
+    // a blob of v in a sea of u, this is what gets the pattern going
+    fn seed(&mut self, x: usize, z: usize, radius: usize) {
+        let n = self.points_per_row;
+        let z_start = if z > radius { z - radius } else { 0 };
+        let x_start = if x > radius { x - radius } else { 0 };
+        for cur_z in z_start..(z + radius + 1).min(n) {
+            for cur_x in x_start..(x + radius + 1).min(n) {
+                let dx = cur_x as f32 - x as f32;
+                let dz = cur_z as f32 - z as f32;
+                if dx * dx + dz * dz <= (radius * radius) as f32 {
+                    self.u[cur_z * n + cur_x] = 0.5;
+                    self.v[cur_z * n + cur_x] = 0.25;
+                }
+            }
+        }
+        self.pin_edges();
+        self.update_heights();
+    }
+
+    fn pin_edges(&mut self) {
+        if self.boundary == Boundary::Fixed {
+            set_edges(&mut self.u, self.points_per_row, 1.);
+            set_edges(&mut self.v, self.points_per_row, 0.);
+        }
+    }
+
+    fn update_heights(&mut self) {
+        for i in 0..self.v.len() {
+            self.heights[i] = GRAY_SCOTT_HEIGHT_SCALE * self.v[i];
+        }
+    }
+}
+
+impl Simulation for ReactionDiffusionSimulation {
+    fn heights(&self) -> &Vec<f32> {
+        &self.heights
+    }
+
+    fn time_step(&self) -> f32 {
+        GRAY_SCOTT_TIME_STEP
+    }
+
+    fn step(&mut self) {
+        let n = self.points_per_row;
+        for z in 0..n {
+            for x in 0..n {
+                let i = z * n + x;
+                let u = self.u[i];
+                let v = self.v[i];
+                let reaction = u * v * v;
+                // the equations themselves keep both between 0 and 1, but the explicit step can
+                // overshoot where there's little to kill v off. once v is past 1, u v^2 feeds on itself
+                // and everything goes to infinity, so it's held to 0 to 1
+                self.next_u[i] = (u + self.diffusion_u * laplacian(&self.u, n, x, z) - reaction
+                    + self.feed * (1. - u))
+                    .max(0.)
+                    .min(1.);
+                self.next_v[i] = (v + self.diffusion_v * laplacian(&self.v, n, x, z) + reaction
+                    - (self.feed + self.kill) * v)
+                    .max(0.)
+                    .min(1.);
+            }
+        }
+        std::mem::swap(&mut self.u, &mut self.next_u);
+        std::mem::swap(&mut self.v, &mut self.next_v);
+        self.pin_edges();
+        self.update_heights();
+    }
+
+    fn reset(&mut self) {
+        for i in 0..self.u.len() {
+            self.u[i] = 1.;
+            self.v[i] = 0.;
+        }
+        let n = self.points_per_row;
+        self.seed(n / 2, n / 2, GRAY_SCOTT_SEED_RADIUS);
+        self.seed(n / 4, 2 * n / 3, GRAY_SCOTT_SEED_RADIUS);
+    }
+
+    fn add_impulse(&mut self, x: usize, z: usize) {
+        self.seed(x, z, GRAY_SCOTT_SEED_RADIUS);
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.pin_edges();
+        self.update_heights();
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err(format!("{} has to be a number, not {}", name, value));
+        }
+        // the explicit step is only stable while diffusion stays below 1/4 per step, and a little
+        // less with the feed and kill on top (see GRAY_SCOTT_MAX_DIFFUSION)
+        match name {
+            "feed" => self.feed = value.max(0.).min(GRAY_SCOTT_MAX_FEED),
+            "kill" => self.kill = value.max(0.).min(GRAY_SCOTT_MAX_KILL),
+            "diffusion_u" => self.diffusion_u = value.max(0.).min(GRAY_SCOTT_MAX_DIFFUSION),
+            "diffusion_v" => self.diffusion_v = value.max(0.).min(GRAY_SCOTT_MAX_DIFFUSION),
+            _ => {
+                return Err(format!(
+                    "reaction diffusion simulation has no parameter named {}",
+                    name
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "feed" => Some(self.feed),
+            "kill" => Some(self.kill),
+            "max_feed" => Some(GRAY_SCOTT_MAX_FEED),
+            "max_kill" => Some(GRAY_SCOTT_MAX_KILL),
+            "diffusion_u" => Some(self.diffusion_u),
+            "diffusion_v" => Some(self.diffusion_v),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_and_kill_are_kept_to_a_stable_range() {
+        let mut simulation = ReactionDiffusionSimulation::new(GRID_SIZE);
+        for &(name, max) in [("feed", GRAY_SCOTT_MAX_FEED), ("kill", GRAY_SCOTT_MAX_KILL)].iter() {
+            simulation.set_parameter(name, 1e9).unwrap();
+            assert_eq!(simulation.get_parameter(name), Some(max));
+            simulation.set_parameter(name, -1.).unwrap();
+            assert_eq!(simulation.get_parameter(name), Some(0.));
+
+            assert!(simulation.set_parameter(name, std::f32::INFINITY).is_err());
+            assert!(simulation.set_parameter(name, std::f32::NAN).is_err());
+            assert_eq!(simulation.get_parameter(name), Some(0.));
+        }
+    }
+
+    #[test]
+    fn stays_finite_at_the_limits() {
+        for &(feed, kill) in [
+            (0., 0.),
+            (GRAY_SCOTT_MAX_FEED, GRAY_SCOTT_MAX_KILL),
+            (GRAY_SCOTT_MAX_FEED, 0.),
+            (0., GRAY_SCOTT_MAX_KILL),
+        ]
+        .iter()
+        {
+            let mut simulation = ReactionDiffusionSimulation::new(GRID_SIZE);
+            simulation.set_parameter("feed", feed).unwrap();
+            simulation.set_parameter("kill", kill).unwrap();
+            simulation.set_parameter("diffusion_u", 1e9).unwrap();
+            simulation.set_parameter("diffusion_v", 1e9).unwrap();
+            for _ in 0..500 {
+                simulation.step();
+            }
+            assert!(simulation.heights().iter().all(|height| height.is_finite()
+                && *height >= 0.
+                && *height <= GRAY_SCOTT_HEIGHT_SCALE));
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use super::super::constants::*;
+use wasm_bindgen::prelude::*;
+
+// what happens to the simulated field at the edge of the grid
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Boundary {
+    Fixed, // edges are pinned to the resting value (dirichlet)
+    Free,  // nothing flows across the edges, zero slope (neumann)
+}
+
+// ==== anything that evolves the height grid of the 3d graph over time ==== //
+// heights are laid out exactly like the y values of the 3d graph: index = z * (n + 1) + x
+pub trait Simulation {
+    fn heights(&self) -> &Vec<f32>;
+
+    // in seconds. every call to step advances the simulation by exactly this much
+    fn time_step(&self) -> f32;
+    fn step(&mut self);
+    fn reset(&mut self);
+
+    // poke the simulation at grid point (x, z), e.g. because someone clicked there
+    fn add_impulse(&mut self, x: usize, z: usize);
+    fn set_boundary(&mut self, boundary: Boundary);
+
+    // runtime knobs, looked up by name so js doesn't need to know the concrete simulation
+    fn set_parameter(&mut self, name: &str, value: f32) -> Result<(), String>;
+    fn get_parameter(&self, name: &str) -> Option<f32>;
+}
+
+// ==== turns the time that goes by on the timeline into a whole number of fixed steps ==== //
+// the simulation always steps by its own time_step, however many (or few) steps that takes,
+// so the result does not depend on the frame rate. stalls are dropped by the timeline, not here
+pub struct FixedStepClock {
+    time_accumulator: f32, // in seconds, time we still owe the simulation
+}
+
+impl FixedStepClock {
+    pub fn new() -> Self {
+        Self {
+            time_accumulator: 0.,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.time_accumulator = 0.;
+    }
+
+    // elapsed is in ms, as much as the timeline moved on (see timeline.rs)
+    pub fn advance_by(&mut self, elapsed: f32, simulation: &mut dyn Simulation) {
+        self.time_accumulator += elapsed.max(0.) / 1000.;
+
+        // enough steps for the longest update the timeline hands in while playing, at full speed, plus
+        // the one left over from last time. only stepping through a lot of frames at once gets past
+        // that, and the rest is dropped rather than freezing the page catching up
+        let time_step = simulation.time_step();
+        let max_steps = (SIMULATION_MAX_ELAPSED / 1000. / time_step).ceil() as usize + 1;
+        let mut steps = 0;
+        while self.time_accumulator >= time_step && steps < max_steps {
+            simulation.step();
+            self.time_accumulator -= time_step;
+            steps += 1;
+        }
+
+        if steps == max_steps {
+            self.time_accumulator = 0.;
+        }
+    }
+}
+
+// ---- finite difference helpers shared by the simulations ---- //
+
+// grid spacing, derived from webgl's clip space, which goes from -1 to +1
+pub fn cell_size(points_per_row: usize) -> f32 {
+    2. / (points_per_row - 1) as f32
+}
+
+// index of the neighbour at (x + dx, z + dz). off the grid we mirror back inside,
+// which gives zero slope across the edge (what Boundary::Free wants)
+pub fn mirrored_index(n: usize, x: usize, z: usize, dx: isize, dz: isize) -> usize {
+    let mirror = |i: usize, d: isize| {
+        let moved = i as isize + d;
+        if moved < 0 {
+            (-moved) as usize
+        } else if moved as usize >= n {
+            2 * (n - 1) - moved as usize
+        } else {
+            moved as usize
+        }
+    };
+    mirror(z, dz) * n + mirror(x, dx)
+}
+
+// 5 point laplacian, without dividing by the cell size squared
+pub fn laplacian(values: &Vec<f32>, n: usize, x: usize, z: usize) -> f32 {
+    values[mirrored_index(n, x, z, -1, 0)]
+        + values[mirrored_index(n, x, z, 1, 0)]
+        + values[mirrored_index(n, x, z, 0, -1)]
+        + values[mirrored_index(n, x, z, 0, 1)]
+        - 4. * values[z * n + x]
+}
+
+pub fn set_edges(values: &mut Vec<f32>, n: usize, value: f32) {
+    for i in 0..n {
+        values[i] = value;
+        values[(n - 1) * n + i] = value;
+        values[i * n] = value;
+        values[i * n + n - 1] = value;
+    }
+}
+
+// adds a smooth gaussian bump centered on grid point (x, z). radius is in grid cells
+pub fn add_gaussian(
+    values: &mut Vec<f32>,
+    n: usize,
+    x: usize,
+    z: usize,
+    radius: f32,
+    amplitude: f32,
+) {
+    let reach = (3. * radius).ceil() as usize;
+    let z_start = if z > reach { z - reach } else { 0 };
+    let x_start = if x > reach { x - reach } else { 0 };
+    let two_radius_squared = 2. * radius * radius;
+
+    for cur_z in z_start..(z + reach + 1).min(n) {
+        for cur_x in x_start..(x + reach + 1).min(n) {
+            let dx = cur_x as f32 - x as f32;
+            let dz = cur_z as f32 - z as f32;
+            values[cur_z * n + cur_x] +=
+                amplitude * (-(dx * dx + dz * dz) / two_radius_squared).exp();
+        }
+    }
+}
+
+// solves a tridiagonal system in place with the thomas algorithm.
+// lower[0] and upper[n - 1] are ignored. rhs holds the answer afterwards, scratch just has to be as long as rhs
+pub fn solve_tridiagonal(
+    lower: &[f32],
+    diagonal: &[f32],
+    upper: &[f32],
+    rhs: &mut [f32],
+    scratch: &mut [f32],
+) {
+    let n = rhs.len();
+    scratch[0] = upper[0] / diagonal[0];
+    rhs[0] = rhs[0] / diagonal[0];
+    for i in 1..n {
+        let denominator = diagonal[i] - lower[i] * scratch[i - 1];
+        scratch[i] = upper[i] / denominator;
+        rhs[i] = (rhs[i] - lower[i] * rhs[i - 1]) / denominator;
+    }
+    for i in (0..n - 1).rev() {
+        rhs[i] -= scratch[i] * rhs[i + 1];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::Timeline;
+
+    // only counts its steps
+    struct CountingSimulation {
+        heights: Vec<f32>,
+        time_step: f32,
+        steps: usize,
+    }
+
+    impl Simulation for CountingSimulation {
+        fn heights(&self) -> &Vec<f32> {
+            &self.heights
+        }
+        fn time_step(&self) -> f32 {
+            self.time_step
+        }
+        fn step(&mut self) {
+            self.steps += 1;
+        }
+        fn reset(&mut self) {}
+        fn add_impulse(&mut self, _x: usize, _z: usize) {}
+        fn set_boundary(&mut self, _boundary: Boundary) {}
+        fn set_parameter(&mut self, name: &str, _value: f32) -> Result<(), String> {
+            Err(format!("no parameter called {}", name))
+        }
+        fn get_parameter(&self, _name: &str) -> Option<f32> {
+            None
+        }
+    }
+
+    // plays wall_duration ms of wall clock in frames of frame ms, returns how many steps were taken
+    fn count_steps(time_step: f32, speed: f32, frame: f32, wall_duration: f32) -> usize {
+        let mut timeline = Timeline::new();
+        timeline.set_speed(speed).unwrap();
+        let mut clock = FixedStepClock::new();
+        let mut simulation = CountingSimulation {
+            heights: Vec::new(),
+            time_step: time_step,
+            steps: 0,
+        };
+        let frames = (wall_duration / frame).round() as usize;
+        for i in 0..=frames {
+            let elapsed = timeline.advance_to(i as f32 * frame);
+            clock.advance_by(elapsed, &mut simulation);
+        }
+        simulation.steps
+    }
+
+    #[test]
+    fn steps_dont_depend_on_the_frame_rate() {
+        let wall_duration = 2000.;
+        for &time_step in [WAVE_TIME_STEP, HEAT_TIME_STEP, GRAY_SCOTT_TIME_STEP].iter() {
+            for &speed in [0.5, 1., 4., 8., TIMELINE_MAX_SPEED].iter() {
+                let expected = wall_duration * speed / 1000. / time_step;
+                for &frame in [4., 8., 10., 25., 50., TIMELINE_MAX_WALL_ELAPSED].iter() {
+                    let steps = count_steps(time_step, speed, frame, wall_duration) as f32;
+                    assert!(
+                        (steps - expected).abs() <= 1.,
+                        "{} steps instead of {} at speed {} in {} ms frames",
+                        steps,
+                        expected,
+                        speed,
+                        frame
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stalls_are_dropped() {
+        let mut timeline = Timeline::new();
+        timeline.advance_to(0.);
+        assert_eq!(timeline.advance_to(5000.), TIMELINE_MAX_WALL_ELAPSED);
+        assert_eq!(timeline.get_time(), TIMELINE_MAX_WALL_ELAPSED);
+    }
+}
@@ -0,0 +1,167 @@
+use super::super::constants::*;
+use super::simulation::*;
+
+// ==== finite difference solver for the damped 2d wave equation ==== //
+// u_tt + damping * u_t = c^2 * (u_xx + u_zz)
+// we keep the current and the previous height grid, which is all the leapfrog scheme needs.
+// parameters: "wave_speed" (grid units per second), "damping" (per second, 0 to WAVE_MAX_DAMPING)
+pub struct WaveSimulation {
+    points_per_row: usize,
+    heights: Vec<f32>,
+    previous_heights: Vec<f32>,
+    wave_speed: f32,
+    damping: f32,
+    boundary: Boundary,
+}
+
+impl WaveSimulation {
+    pub fn new(n: usize) -> Self {
+        let points_per_row = n + 1;
+        Self {
+            points_per_row: points_per_row,
+            heights: vec![0.; points_per_row * points_per_row],
+            previous_heights: vec![0.; points_per_row * points_per_row],
+            wave_speed: WAVE_DEFAULT_SPEED,
+            damping: WAVE_DEFAULT_DAMPING,
+            boundary: Boundary::Fixed,
+        }
+    }
+
+    // the largest speed the fixed time step can handle without blowing up
+    pub fn max_stable_wave_speed(&self) -> f32 {
+        WAVE_MAX_COURANT * cell_size(self.points_per_row) / WAVE_TIME_STEP
+    }
+
+    fn pin_edges(&mut self) {
+        set_edges(&mut self.heights, self.points_per_row, 0.);
+        set_edges(&mut self.previous_heights, self.points_per_row, 0.);
+    }
+}
+
+impl Simulation for WaveSimulation {
+    fn heights(&self) -> &Vec<f32> {
+        &self.heights
+    }
+
+    fn time_step(&self) -> f32 {
+        WAVE_TIME_STEP
+    }
+
+    // one leapfrog step:
+    // u_next = (2u - (1 - damping*dt/2) * u_prev + (c*dt/dx)^2 * laplacian(u)) / (1 + damping*dt/2)
+    fn step(&mut self) {
+        let n = self.points_per_row;
+        let courant = self.wave_speed * WAVE_TIME_STEP / cell_size(n);
+        let courant_squared = courant * courant;
+        let half_damping = 0.5 * self.damping * WAVE_TIME_STEP;
+
+        for z in 0..n {
+            for x in 0..n {
+                let i = z * n + x;
+                // we write the next heights over the previous ones, since previous is no longer needed after this
+                self.previous_heights[i] = (2. * self.heights[i]
+                    - (1. - half_damping) * self.previous_heights[i]
+                    + courant_squared * laplacian(&self.heights, n, x, z))
+                    / (1. + half_damping);
+            }
+        }
+
+        std::mem::swap(&mut self.heights, &mut self.previous_heights);
+
+        if self.boundary == Boundary::Fixed {
+            self.pin_edges();
+        }
+    }
+
+    fn reset(&mut self) {
+        for i in 0..self.heights.len() {
+            self.heights[i] = 0.;
+            self.previous_heights[i] = 0.;
+        }
+    }
+
+    // a droplet gives the surface a downward kick (velocity, not displacement) shaped like a gaussian,
+    // so the result is a smooth ring instead of a single spiky vertex.
+    // u - u_prev = velocity * dt, so lowering u_prev is the same as adding velocity
+    fn add_impulse(&mut self, x: usize, z: usize) {
+        add_gaussian(
+            &mut self.previous_heights,
+            self.points_per_row,
+            x,
+            z,
+            WAVE_DROPLET_RADIUS,
+            -WAVE_DROPLET_VELOCITY * WAVE_TIME_STEP,
+        );
+
+        if self.boundary == Boundary::Fixed {
+            self.pin_edges();
+        }
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        if boundary == Boundary::Fixed {
+            self.pin_edges();
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err(format!("{} has to be a number, not {}", name, value));
+        }
+        match name {
+            "wave_speed" => self.wave_speed = value.max(0.).min(self.max_stable_wave_speed()),
+            "damping" => self.damping = value.max(0.).min(WAVE_MAX_DAMPING),
+            _ => return Err(format!("wave simulation has no parameter named {}", name)),
+        }
+        Ok(())
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "wave_speed" => Some(self.wave_speed),
+            "max_wave_speed" => Some(self.max_stable_wave_speed()),
+            "damping" => Some(self.damping),
+            "max_damping" => Some(WAVE_MAX_DAMPING),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damping_is_kept_to_a_stable_range() {
+        let mut wave = WaveSimulation::new(GRID_SIZE);
+        wave.set_parameter("damping", 1e9).unwrap();
+        assert_eq!(wave.get_parameter("damping"), Some(WAVE_MAX_DAMPING));
+        wave.set_parameter("damping", -1.).unwrap();
+        assert_eq!(wave.get_parameter("damping"), Some(0.));
+
+        assert!(wave.set_parameter("damping", std::f32::INFINITY).is_err());
+        assert!(wave.set_parameter("damping", std::f32::NAN).is_err());
+        assert!(wave
+            .set_parameter("wave_speed", std::f32::INFINITY)
+            .is_err());
+        assert_eq!(wave.get_parameter("damping"), Some(0.));
+    }
+
+    #[test]
+    fn stays_finite_at_the_limits() {
+        for &damping in [0., WAVE_MAX_DAMPING].iter() {
+            let mut wave = WaveSimulation::new(GRID_SIZE);
+            wave.set_parameter("wave_speed", 1e9).unwrap();
+            wave.set_parameter("damping", damping).unwrap();
+            wave.add_impulse(GRID_SIZE / 2, GRID_SIZE / 2);
+            for _ in 0..500 {
+                wave.step();
+            }
+            assert!(wave
+                .heights()
+                .iter()
+                .all(|height| height.is_finite() && height.abs() < 1.));
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use super::common_funcs as cf;
+
+// a vertical plane through the graph's box: all points with x cos(angle) + z sin(angle) = offset.
+// along the plane, s runs in the (-sin(angle), cos(angle)) direction, from the point nearest the middle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlicePlane {
+    pub offset: f32,
+    pub angle: f32,
+}
+
+impl SlicePlane {
+    pub fn get_point(&self, s: f32) -> (f32, f32) {
+        let (sin, cos) = self.angle.sin_cos();
+        (self.offset * cos - s * sin, self.offset * sin + s * cos)
+    }
+
+    // the part of the plane over the -1 to +1 square, as a range of s. None if it misses the square
+    pub fn get_s_range(&self) -> Option<(f32, f32)> {
+        let (sin, cos) = self.angle.sin_cos();
+        let mut s_min = std::f32::NEG_INFINITY;
+        let mut s_max = std::f32::INFINITY;
+        // x = offset cos - s sin, z = offset sin + s cos, both have to stay within -1 to +1
+        for &(start, rate) in [(self.offset * cos, -sin), (self.offset * sin, cos)].iter() {
+            if rate.abs() < 1e-6 {
+                if start.abs() > 1. {
+                    return None;
+                }
+                continue;
+            }
+            let s_a = (-1. - start) / rate;
+            let s_b = (1. - start) / rate;
+            s_min = s_min.max(s_a.min(s_b));
+            s_max = s_max.min(s_a.max(s_b));
+        }
+        if s_min < s_max {
+            Some((s_min, s_max))
+        } else {
+            None
+        }
+    }
+}
+
+// ==== where the plane cuts the height field, as (s, point) from one side of the box to the other ==== //
+// the surface is flat within each of the graph's triangles, so the cut is straight between the places
+// where the plane crosses a grid line or a triangle's diagonal. those are the points we return
+pub fn get_slice_profile(heights: &Vec<f32>, n: usize, plane: &SlicePlane) -> Vec<(f32, [f32; 3])> {
+    let (s_min, s_max) = match plane.get_s_range() {
+        Some(range) => range,
+        None => return Vec::new(),
+    };
+    let (sin, cos) = plane.angle.sin_cos();
+    let square_size = 2. / n as f32;
+
+    let mut crossings: Vec<f32> = vec![s_min, s_max];
+    // lines of constant x, of constant z, and the diagonals (x - z constant)
+    let start = plane.get_point(0.);
+    for &(origin, rate, count) in [
+        (start.0, -sin, n + 1),
+        (start.1, cos, n + 1),
+        (start.0 - start.1, -sin - cos, 2 * n + 1),
+    ]
+    .iter()
+    {
+        if rate.abs() < 1e-6 {
+            continue;
+        }
+        let first_line = if count == n + 1 { -1. } else { -2. };
+        for i in 0..count {
+            let s = (first_line + i as f32 * square_size - origin) / rate;
+            if s > s_min && s < s_max {
+                crossings.push(s);
+            }
+        }
+    }
+    crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    crossings.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+
+    crossings
+        .iter()
+        .map(|&s| {
+            let (x, z) = plane.get_point(s);
+            (s, [x, cf::get_height_at(heights, n, x, z), z])
+        })
+        .collect()
+}
+
+// the profile as line segments in the graph's box, for GL::LINES
+pub fn get_profile_segments(profile: &Vec<(f32, [f32; 3])>, y_offset: f32) -> Vec<f32> {
+    let mut protag: Vec<f32> = Vec::new();
+    for pair in profile.windows(2) {
+        for (_, point) in pair.iter() {
+            protag.extend_from_slice(&[point[0], point[1] + y_offset, point[2]]);
+        }
+    }
+    protag
+}
+
+// the outline of the plane over the box, from y_min to y_max
+pub fn get_plane_outline_segments(plane: &SlicePlane, y_min: f32, y_max: f32) -> Vec<f32> {
+    let (s_min, s_max) = match plane.get_s_range() {
+        Some(range) => range,
+        None => return Vec::new(),
+    };
+    let (x_a, z_a) = plane.get_point(s_min);
+    let (x_b, z_b) = plane.get_point(s_max);
+    let corners = [
+        [x_a, y_min, z_a],
+        [x_b, y_min, z_b],
+        [x_b, y_max, z_b],
+        [x_a, y_max, z_a],
+    ];
+    let mut protag: Vec<f32> = Vec::with_capacity(4 * 2 * 3);
+    for i in 0..4 {
+        protag.extend_from_slice(&corners[i]);
+        protag.extend_from_slice(&corners[(i + 1) % 4]);
+    }
+    protag
+}
+
+// ==== the profile as a 2d line chart in the 0 to 1 square (see get_2d_panel_transform) ==== //
+// s goes across the whole width, heights from -y_range to +y_range go up the panel (and are clipped
+// there, so the chart holds still while the surface moves). the y = 0 axis is drawn too
+pub fn get_profile_chart_segments(profile: &Vec<(f32, [f32; 3])>, y_range: f32) -> Vec<f32> {
+    if profile.len() < 2 {
+        return Vec::new();
+    }
+    let s_min = profile[0].0;
+    let s_size = (profile[profile.len() - 1].0 - s_min).max(1e-6);
+    let to_chart = |s: f32, y: f32| -> [f32; 3] {
+        [
+            (s - s_min) / s_size,
+            (0.5 + 0.5 * y / y_range).max(0.).min(1.),
+            0.,
+        ]
+    };
+
+    let mut protag: Vec<f32> = Vec::new();
+    protag.extend_from_slice(&[0., 0.5, 0., 1., 0.5, 0.]);
+    for pair in profile.windows(2) {
+        for (s, point) in pair.iter() {
+            protag.extend_from_slice(&to_chart(*s, point[1]));
+        }
+    }
+    protag
+}
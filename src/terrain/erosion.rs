@@ -0,0 +1,188 @@
+use super::super::constants::*;
+use super::super::random::SeededRandom;
+
+// ==== hydraulic erosion, one rain drop at a time ==== //
+// each drop starts somewhere random, rolls downhill picking up sediment while it speeds up,
+// and drops it again where it slows down or can't carry it any more.
+// positions are in grid cells, heights stay in the same units as the terrain.
+// the random starting points come from the terrain's seed, so erosion is as repeatable as the noise
+pub fn erode_hydraulic(heights: &mut Vec<f32>, n: usize, droplet_count: u32, seed: u32) {
+    let points_per_row = n + 1;
+    let mut random = SeededRandom::new(seed.wrapping_add(TERRAIN_EROSION_SEED_OFFSET));
+    let max_position = n as f32; // drops leave the map once they reach the last row or column
+
+    for _ in 0..droplet_count {
+        let mut position_x = random.range(0., max_position);
+        let mut position_z = random.range(0., max_position);
+        let mut direction_x = 0.;
+        let mut direction_z = 0.;
+        let mut speed = 1.;
+        let mut water = 1.;
+        let mut sediment = 0.;
+
+        for _ in 0..HYDRAULIC_MAX_DROPLET_LIFETIME {
+            let cell_x = position_x as usize;
+            let cell_z = position_z as usize;
+            let offset_x = position_x - cell_x as f32;
+            let offset_z = position_z - cell_z as f32;
+            let (height, gradient_x, gradient_z) =
+                height_and_gradient(heights, points_per_row, position_x, position_z);
+
+            // roll downhill, but keep a little of the old direction
+            direction_x = direction_x * HYDRAULIC_INERTIA - gradient_x * (1. - HYDRAULIC_INERTIA);
+            direction_z = direction_z * HYDRAULIC_INERTIA - gradient_z * (1. - HYDRAULIC_INERTIA);
+            let direction_size = (direction_x * direction_x + direction_z * direction_z).sqrt();
+            if direction_size < 1e-6 {
+                break; // sitting in a perfectly flat spot
+            }
+            direction_x /= direction_size;
+            direction_z /= direction_size;
+            position_x += direction_x;
+            position_z += direction_z;
+
+            if position_x < 0.
+                || position_x >= max_position
+                || position_z < 0.
+                || position_z >= max_position
+            {
+                break; // rolled off the map, sediment and all
+            }
+
+            let height_delta =
+                height_and_gradient(heights, points_per_row, position_x, position_z).0 - height;
+
+            // faster, wetter drops going steeper downhill can carry more
+            let capacity = (-height_delta * speed * water * HYDRAULIC_SEDIMENT_CAPACITY)
+                .max(HYDRAULIC_MIN_SEDIMENT_CAPACITY);
+
+            if sediment > capacity || height_delta > 0. {
+                // going uphill: fill the pit we just left (but not over the top). otherwise drop the excess
+                let deposit = if height_delta > 0. {
+                    height_delta.min(sediment)
+                } else {
+                    (sediment - capacity) * HYDRAULIC_DEPOSIT_SPEED
+                };
+                sediment -= deposit;
+                add_bilinear(
+                    heights,
+                    points_per_row,
+                    cell_x,
+                    cell_z,
+                    offset_x,
+                    offset_z,
+                    deposit,
+                );
+            } else {
+                // never dig deeper than the height we are dropping, or we'd carve holes
+                let erode = ((capacity - sediment) * HYDRAULIC_ERODE_SPEED).min(-height_delta);
+                add_bilinear(
+                    heights,
+                    points_per_row,
+                    cell_x,
+                    cell_z,
+                    offset_x,
+                    offset_z,
+                    -erode,
+                );
+                sediment += erode;
+            }
+
+            speed = (speed * speed - height_delta * HYDRAULIC_GRAVITY)
+                .max(0.)
+                .sqrt();
+            water *= 1. - HYDRAULIC_EVAPORATE_SPEED;
+        }
+    }
+}
+
+// height at a fractional grid position (bilinear), and the slope there
+fn height_and_gradient(
+    heights: &Vec<f32>,
+    points_per_row: usize,
+    x: f32,
+    z: f32,
+) -> (f32, f32, f32) {
+    let cell_x = (x as usize).min(points_per_row - 2);
+    let cell_z = (z as usize).min(points_per_row - 2);
+    let offset_x = x - cell_x as f32;
+    let offset_z = z - cell_z as f32;
+
+    let top_left = heights[cell_z * points_per_row + cell_x];
+    let top_right = heights[cell_z * points_per_row + cell_x + 1];
+    let btm_left = heights[(cell_z + 1) * points_per_row + cell_x];
+    let btm_right = heights[(cell_z + 1) * points_per_row + cell_x + 1];
+
+    let gradient_x = (top_right - top_left) * (1. - offset_z) + (btm_right - btm_left) * offset_z;
+    let gradient_z = (btm_left - top_left) * (1. - offset_x) + (btm_right - top_right) * offset_x;
+    let height = top_left * (1. - offset_x) * (1. - offset_z)
+        + top_right * offset_x * (1. - offset_z)
+        + btm_left * (1. - offset_x) * offset_z
+        + btm_right * offset_x * offset_z;
+
+    (height, gradient_x, gradient_z)
+}
+
+// spread an amount over the 4 corners of a cell, weighted by how close the point is to each
+fn add_bilinear(
+    heights: &mut Vec<f32>,
+    points_per_row: usize,
+    cell_x: usize,
+    cell_z: usize,
+    offset_x: f32,
+    offset_z: f32,
+    amount: f32,
+) {
+    let top_left = cell_z * points_per_row + cell_x;
+    let btm_left = top_left + points_per_row;
+    heights[top_left] += amount * (1. - offset_x) * (1. - offset_z);
+    heights[top_left + 1] += amount * offset_x * (1. - offset_z);
+    heights[btm_left] += amount * (1. - offset_x) * offset_z;
+    heights[btm_left + 1] += amount * offset_x * offset_z;
+}
+
+// ==== thermal erosion ==== //
+// wherever the ground is steeper than the talus angle, material crumbles down to the lower neighbour
+// until the slope is back at the angle of repose. all moves of a pass are worked out first and
+// applied together, so the result doesn't depend on which corner of the grid we started from
+pub fn erode_thermal(heights: &mut Vec<f32>, n: usize, iterations: u32, talus_angle: f32) {
+    let points_per_row = n + 1;
+    let cell_size = 2. / n as f32; // derived from webgl's clip space, which goes from -1 to +1
+    let max_height_difference = talus_angle.tan() * cell_size;
+    let mut changes: Vec<f32> = vec![0.; heights.len()];
+
+    for _ in 0..iterations {
+        for change in changes.iter_mut() {
+            *change = 0.;
+        }
+
+        for z in 0..points_per_row {
+            for x in 0..points_per_row {
+                let i = z * points_per_row + x;
+                // right and down neighbours only, so each pair is looked at once
+                let mut neighbours: [Option<usize>; 2] = [None, None];
+                if x + 1 < points_per_row {
+                    neighbours[0] = Some(i + 1);
+                }
+                if z + 1 < points_per_row {
+                    neighbours[1] = Some(i + points_per_row);
+                }
+
+                for neighbour in neighbours.iter().filter_map(|neighbour| *neighbour) {
+                    let difference = heights[i] - heights[neighbour];
+                    let excess = difference.abs() - max_height_difference;
+                    if excess > 0. {
+                        // moving half the excess evens the pair out, the rate keeps it from overshooting
+                        // when a cell has several lower neighbours
+                        let amount = THERMAL_EROSION_RATE * 0.5 * excess * difference.signum();
+                        changes[i] -= amount;
+                        changes[neighbour] += amount;
+                    }
+                }
+            }
+        }
+
+        for i in 0..heights.len() {
+            heights[i] += changes[i];
+        }
+    }
+}
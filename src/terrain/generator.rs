@@ -0,0 +1,220 @@
+use super::super::common_funcs as cf;
+use super::super::constants::*;
+use super::erosion;
+use super::noise::*;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FractalKind {
+    Fbm,    // fractional brownian motion, rolling hills
+    Ridged, // ridged multifractal, sharp mountain ridges
+}
+
+// everything that goes into a terrain. hand the same settings (seed included) to someone else
+// and they get exactly the same terrain
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainSettings {
+    pub seed: u32,
+    pub noise: NoiseKind,
+    pub fractal: FractalKind,
+    pub octaves: u32,    // 1 to TERRAIN_MAX_OCTAVES
+    pub frequency: f32,  // of the first octave, in bumps per grid (which is 2 units wide)
+    pub lacunarity: f32, // frequency multiplier from one octave to the next
+    pub gain: f32,       // amplitude multiplier from one octave to the next
+    pub height_scale: f32,
+    pub warp_strength: f32, // how far (in grid units) domain warping pushes the sample points around. 0 = off
+    pub warp_frequency: f32,
+    pub hydraulic_droplets: u32, // how many rain drops to let run down the terrain, up to TERRAIN_MAX_HYDRAULIC_DROPLETS. 0 = no hydraulic erosion
+    pub thermal_iterations: u32, // passes of slope collapsing, up to TERRAIN_MAX_THERMAL_ITERATIONS. 0 = no thermal erosion
+    pub talus_angle: f32, // in radians. anything steeper than this crumbles in thermal erosion
+}
+
+#[wasm_bindgen]
+impl TerrainSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            seed: 1,
+            noise: NoiseKind::Simplex,
+            fractal: FractalKind::Fbm,
+            octaves: 6,
+            frequency: 1.5,
+            lacunarity: 2.,
+            gain: 0.5,
+            height_scale: TERRAIN_DEFAULT_HEIGHT_SCALE,
+            warp_strength: 0.,
+            warp_frequency: 1.,
+            hydraulic_droplets: 0,
+            thermal_iterations: 0,
+            talus_angle: TERRAIN_DEFAULT_TALUS_ANGLE,
+        }
+    }
+}
+
+// ==== fills the y values of the n by n grid (the one get_position_grid_n_by_n builds) with terrain ==== //
+// heights come back in the same layout as the y values of the 3d graph: index = z * (n + 1) + x
+pub fn generate_terrain(n: usize, settings: &TerrainSettings) -> Vec<f32> {
+    let noise = Noise::new(settings.noise, settings.seed);
+    // the warp gets its own noise, otherwise it would just push every point along its own gradient
+    let warp_noise = Noise::new(
+        settings.noise,
+        settings.seed.wrapping_add(TERRAIN_WARP_SEED_OFFSET),
+    );
+
+    let (positions, _) = cf::get_position_grid_n_by_n(n);
+    let mut heights: Vec<f32> = Vec::with_capacity(positions.len() / 3);
+
+    for position in positions.chunks(3) {
+        let mut x = position[0];
+        let mut z = position[2];
+
+        if settings.warp_strength != 0. {
+            // domain warping: look the height up somewhere else, where "somewhere else" is itself noise
+            let warp_x = fbm(
+                &warp_noise,
+                settings,
+                x * settings.warp_frequency,
+                z * settings.warp_frequency,
+            );
+            let warp_z = fbm(
+                &warp_noise,
+                settings,
+                x * settings.warp_frequency + TERRAIN_WARP_OFFSET,
+                z * settings.warp_frequency + TERRAIN_WARP_OFFSET,
+            );
+            x += settings.warp_strength * warp_x;
+            z += settings.warp_strength * warp_z;
+        }
+
+        let height = match settings.fractal {
+            FractalKind::Fbm => fbm(
+                &noise,
+                settings,
+                x * settings.frequency,
+                z * settings.frequency,
+            ),
+            FractalKind::Ridged => ridged_multifractal(
+                &noise,
+                settings,
+                x * settings.frequency,
+                z * settings.frequency,
+            ),
+        };
+        heights.push(settings.height_scale * height);
+    }
+
+    // the settings come straight from js, so anything past the limits is cut back to them rather than
+    // left to hang the page
+    let hydraulic_droplets = settings
+        .hydraulic_droplets
+        .min(TERRAIN_MAX_HYDRAULIC_DROPLETS);
+    if hydraulic_droplets > 0 {
+        erosion::erode_hydraulic(&mut heights, n, hydraulic_droplets, settings.seed);
+    }
+    let thermal_iterations = settings
+        .thermal_iterations
+        .min(TERRAIN_MAX_THERMAL_ITERATIONS);
+    if thermal_iterations > 0 {
+        erosion::erode_thermal(&mut heights, n, thermal_iterations, settings.talus_angle);
+    }
+
+    heights
+}
+
+fn get_octave_count(settings: &TerrainSettings) -> u32 {
+    settings.octaves.max(1).min(TERRAIN_MAX_OCTAVES)
+}
+
+// ---- fractional brownian motion: octaves of noise, each one finer and fainter. in [-1, 1] ---- //
+pub fn fbm(noise: &Noise, settings: &TerrainSettings, x: f32, z: f32) -> f32 {
+    let mut protag = 0.;
+    let mut frequency = 1.;
+    let mut amplitude = 1.;
+    let mut amplitude_sum = 0.;
+
+    for _ in 0..get_octave_count(settings) {
+        protag += amplitude * noise.get(x * frequency, z * frequency);
+        amplitude_sum += amplitude;
+        frequency *= settings.lacunarity;
+        amplitude *= settings.gain;
+    }
+
+    protag / amplitude_sum
+}
+
+// ---- musgrave's ridged multifractal. in roughly [-1, 1] ---- //
+// |noise| folded upside down turns the zero crossings of the noise into sharp ridges.
+// each octave is weighted by the one before, so detail piles up on the ridges and the valleys stay smooth
+pub fn ridged_multifractal(noise: &Noise, settings: &TerrainSettings, x: f32, z: f32) -> f32 {
+    let offset = 1.;
+    let ridge_sharpness = 2.;
+
+    let mut protag = 0.;
+    let mut frequency = 1.;
+    let mut amplitude = 1.;
+    let mut amplitude_sum = 0.;
+    let mut weight = 1.;
+
+    for _ in 0..get_octave_count(settings) {
+        let mut signal = offset - noise.get(x * frequency, z * frequency).abs();
+        signal *= signal * weight;
+        weight = (signal * ridge_sharpness).max(0.).min(1.);
+
+        protag += amplitude * signal;
+        amplitude_sum += amplitude;
+        frequency *= settings.lacunarity;
+        amplitude *= settings.gain;
+    }
+
+    2. * protag / amplitude_sum - 1.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn octaves_past_the_limit_are_cut_back() {
+        let mut settings = TerrainSettings::new();
+        settings.octaves = TERRAIN_MAX_OCTAVES;
+        let limited = generate_terrain(8, &settings);
+        settings.octaves = u32::MAX;
+        assert_eq!(generate_terrain(8, &settings), limited);
+        settings.fractal = FractalKind::Ridged;
+        let ridged = generate_terrain(8, &settings);
+        settings.octaves = TERRAIN_MAX_OCTAVES;
+        assert_eq!(generate_terrain(8, &settings), ridged);
+    }
+
+    // bit for bit, so -0 doesn't pass for 0
+    fn get_bits(n: usize, settings: &TerrainSettings) -> Vec<u32> {
+        generate_terrain(n, settings)
+            .iter()
+            .map(|height| height.to_bits())
+            .collect()
+    }
+
+    #[test]
+    fn same_settings_same_terrain() {
+        for &noise in [NoiseKind::Perlin, NoiseKind::Simplex].iter() {
+            for &fractal in [FractalKind::Fbm, FractalKind::Ridged].iter() {
+                let mut settings = TerrainSettings::new();
+                settings.seed = 1234;
+                settings.noise = noise;
+                settings.fractal = fractal;
+                settings.warp_strength = 0.3;
+                let uneroded = get_bits(32, &settings);
+                settings.hydraulic_droplets = 2000;
+                settings.thermal_iterations = 10;
+                let eroded = get_bits(32, &settings);
+                assert_ne!(eroded, uneroded);
+                assert_eq!(get_bits(32, &settings), eroded);
+
+                settings.seed = 1235;
+                assert_ne!(get_bits(32, &settings), eroded);
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod erosion;
+mod generator;
+pub use generator::*;
+mod noise;
+pub use noise::*;
@@ -0,0 +1,160 @@
+use super::super::random::SeededRandom;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+}
+
+// the 8 gradient directions both noises pick from
+const GRADIENTS: [(f32, f32); 8] = [
+    (1., 0.),
+    (-1., 0.),
+    (0., 1.),
+    (0., -1.),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+];
+
+// ==== gradient noise, seeded ==== //
+// both flavours hash the integer lattice through the same shuffled permutation table,
+// and the shuffle is the only place the seed goes in. same seed, same table, same noise.
+// output is roughly in [-1, 1]
+pub struct Noise {
+    kind: NoiseKind,
+    permutation: [u8; 512], // 0..255 shuffled, then repeated so we never have to wrap the index
+}
+
+impl Noise {
+    pub fn new(kind: NoiseKind, seed: u32) -> Self {
+        let mut table = [0u8; 256];
+        for i in 0..256 {
+            table[i] = i as u8;
+        }
+        // fisher-yates
+        let mut random = SeededRandom::new(seed);
+        for i in (1..256).rev() {
+            let j = random.below(i + 1);
+            table.swap(i, j);
+        }
+
+        let mut permutation = [0u8; 512];
+        for i in 0..512 {
+            permutation[i] = table[i & 255];
+        }
+
+        Self {
+            kind: kind,
+            permutation: permutation,
+        }
+    }
+
+    pub fn get(&self, x: f32, z: f32) -> f32 {
+        match self.kind {
+            NoiseKind::Perlin => self.perlin(x, z),
+            NoiseKind::Simplex => self.simplex(x, z),
+        }
+    }
+
+    fn hash(&self, x: i32, z: i32) -> usize {
+        let x = (x & 255) as usize;
+        let z = (z & 255) as usize;
+        self.permutation[self.permutation[x] as usize + z] as usize
+    }
+
+    fn gradient_dot(&self, hash: usize, x: f32, z: f32) -> f32 {
+        let gradient = GRADIENTS[hash & 7];
+        gradient.0 * x + gradient.1 * z
+    }
+
+    // ---- ken perlin's improved noise, in 2d ---- //
+    fn perlin(&self, x: f32, z: f32) -> f32 {
+        let cell_x = x.floor();
+        let cell_z = z.floor();
+        let fraction_x = x - cell_x;
+        let fraction_z = z - cell_z;
+        let cell_x = cell_x as i32;
+        let cell_z = cell_z as i32;
+
+        // smootherstep, 6t^5 - 15t^4 + 10t^3. zero first and second derivative at the lattice points
+        let fade = |t: f32| t * t * t * (t * (t * 6. - 15.) + 10.);
+        let lerp = |a: f32, b: f32, t: f32| a + t * (b - a);
+        let u = fade(fraction_x);
+        let v = fade(fraction_z);
+
+        let top_left = self.gradient_dot(self.hash(cell_x, cell_z), fraction_x, fraction_z);
+        let top_right =
+            self.gradient_dot(self.hash(cell_x + 1, cell_z), fraction_x - 1., fraction_z);
+        let btm_left =
+            self.gradient_dot(self.hash(cell_x, cell_z + 1), fraction_x, fraction_z - 1.);
+        let btm_right = self.gradient_dot(
+            self.hash(cell_x + 1, cell_z + 1),
+            fraction_x - 1.,
+            fraction_z - 1.,
+        );
+
+        // gradients are unit length, so the largest possible value is sqrt(1/2). stretch it back to 1
+        std::f32::consts::SQRT_2
+            * lerp(
+                lerp(top_left, top_right, u),
+                lerp(btm_left, btm_right, u),
+                v,
+            )
+    }
+
+    // ---- simplex noise, in 2d (after stefan gustavson's write up) ---- //
+    // the plane is skewed so that the triangles of the simplex grid line up with a square grid,
+    // then each of the 3 corners of the triangle we are in contributes a radially falling off gradient
+    fn simplex(&self, x: f32, z: f32) -> f32 {
+        let skew = 0.5 * (3f32.sqrt() - 1.);
+        let unskew = (3. - 3f32.sqrt()) / 6.;
+
+        let s = (x + z) * skew;
+        let cell_x = (x + s).floor();
+        let cell_z = (z + s).floor();
+        let t = (cell_x + cell_z) * unskew;
+        let x0 = x - (cell_x - t);
+        let z0 = z - (cell_z - t);
+
+        // which of the two triangles of the skewed square are we in?
+        let (step_x, step_z) = if x0 > z0 { (1, 0) } else { (0, 1) };
+        let x1 = x0 - step_x as f32 + unskew;
+        let z1 = z0 - step_z as f32 + unskew;
+        let x2 = x0 - 1. + 2. * unskew;
+        let z2 = z0 - 1. + 2. * unskew;
+
+        let cell_x = cell_x as i32;
+        let cell_z = cell_z as i32;
+        let corner = |hash: usize, dx: f32, dz: f32| {
+            let falloff = 0.5 - dx * dx - dz * dz;
+            if falloff < 0. {
+                0.
+            } else {
+                let falloff_squared = falloff * falloff;
+                falloff_squared * falloff_squared * self.gradient_dot(hash, dx, dz)
+            }
+        };
+
+        let total = corner(self.hash(cell_x, cell_z), x0, z0)
+            + corner(self.hash(cell_x + step_x, cell_z + step_z), x1, z1)
+            + corner(self.hash(cell_x + 1, cell_z + 1), x2, z2);
+
+        70. * total // scale the result to roughly [-1, 1]
+    }
+}
@@ -0,0 +1,132 @@
+use super::constants::*;
+
+// ==== the clock the graph animates by ==== //
+// Client::update hands in the wall clock, and the timeline turns it into its own time, which can be
+// paused, sped up or slowed down, stepped a frame at a time, looped over a range and scrubbed.
+// everything is in ms. surfaces read the playhead (app_state's time), and the simulations get handed
+// however much timeline time went by, which the FixedStepClock cuts into their fixed steps. so how far
+// a simulation gets depends on the timeline alone, not on how often we render. the one exception is a
+// stall: a gap between frames longer than TIMELINE_MAX_WALL_ELAPSED only counts that much
+#[derive(Clone, Copy, Debug)]
+pub struct Timeline {
+    time: f32, // the playhead
+    playing: bool,
+    speed: f32,                     // timeline ms per wall clock ms
+    loop_range: Option<(f32, f32)>, // from start up to (not including) end
+    last_wall_time: Option<f32>,
+    // time stepped through since the last advance_to, which the simulations still have to catch up on
+    stepped: f32,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            time: 0.,
+            playing: true,
+            speed: 1.,
+            loop_range: None,
+            last_wall_time: None,
+            stepped: 0.,
+        }
+    }
+
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn get_loop_range(&self) -> Option<(f32, f32)> {
+        self.loop_range
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // 0 is as good as paused. going backwards is what seek is for: the simulations can't run in reverse
+    pub fn set_speed(&mut self, speed: f32) -> Result<(), String> {
+        if !speed.is_finite() || speed < 0. || speed > TIMELINE_MAX_SPEED {
+            return Err(format!(
+                "the speed has to be between 0 and {}",
+                TIMELINE_MAX_SPEED
+            ));
+        }
+        self.speed = speed;
+        Ok(())
+    }
+
+    // the playhead jumps back to start whenever it reaches end
+    pub fn set_loop_range(&mut self, start: f32, end: f32) -> Result<(), String> {
+        if !start.is_finite() || !end.is_finite() || start < 0. || end <= start {
+            return Err(String::from(
+                "the loop has to start at 0 or later, and end after it starts",
+            ));
+        }
+        self.loop_range = Some((start, end));
+        self.time = self.wrap(self.time);
+        Ok(())
+    }
+
+    pub fn clear_loop_range(&mut self) {
+        self.loop_range = None;
+    }
+
+    // moves the playhead without the simulations noticing: they carry on from where they are
+    pub fn seek(&mut self, time: f32) -> Result<(), String> {
+        if !time.is_finite() || time < 0. {
+            return Err(String::from("can't seek to before the start"));
+        }
+        self.time = self.wrap(time);
+        Ok(())
+    }
+
+    // pauses, then moves the playhead a whole number of frames (backwards for negative frames).
+    // the simulations only follow it forwards
+    pub fn step(&mut self, frames: i32) {
+        self.playing = false;
+        let distance = frames as f32 * TIMELINE_FRAME;
+        self.time = self.wrap((self.time + distance).max(0.));
+        self.stepped += distance.max(0.);
+    }
+
+    // moves the playhead on to match the wall clock, if playing. returns how much timeline time went by
+    // for the simulations, which keeps counting up through the loop jumping back
+    pub fn advance_to(&mut self, wall_time: f32) -> f32 {
+        let wall_elapsed = match self.last_wall_time {
+            Some(last_wall_time) => (wall_time - last_wall_time)
+                .max(0.)
+                .min(TIMELINE_MAX_WALL_ELAPSED),
+            None => 0.,
+        };
+        self.last_wall_time = Some(wall_time);
+
+        let mut protag = self.stepped;
+        self.stepped = 0.;
+        if self.playing {
+            let elapsed = wall_elapsed * self.speed;
+            self.time = self.wrap(self.time + elapsed);
+            protag += elapsed;
+        }
+        protag
+    }
+
+    fn wrap(&self, time: f32) -> f32 {
+        match self.loop_range {
+            Some((start, end)) if time < start || time >= end => {
+                start + (time - start).rem_euclid(end - start)
+            }
+            _ => time,
+        }
+    }
+}
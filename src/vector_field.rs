@@ -0,0 +1,184 @@
+use super::common_funcs as cf;
+use super::constants::*;
+use super::domain_coloring::hsv_to_rgb;
+use super::expression::Expr;
+
+// offset xyz, direction xyz (its length is the glyph's length), rgb. one per glyph
+pub const FLOATS_PER_GLYPH: usize = 9;
+
+// a 3d vector (u, v, w) at every point of the graph's box, given as expressions of x, y and z
+pub struct VectorField {
+    pub u: Expr,
+    pub v: Expr,
+    pub w: Expr,
+}
+
+impl VectorField {
+    pub fn compile(u: &str, v: &str, w: &str) -> Result<Self, String> {
+        let variable_names = ["x", "y", "z"];
+        Ok(Self {
+            u: Expr::compile(u, &variable_names)?,
+            v: Expr::compile(v, &variable_names)?,
+            w: Expr::compile(w, &variable_names)?,
+        })
+    }
+
+    pub fn get(&self, point: &[f32; 3]) -> [f32; 3] {
+        [self.u.eval(point), self.v.eval(point), self.w.eval(point)]
+    }
+}
+
+// every stride-th point of the 3d graph's lattice (see get_position_grid_n_by_n), which lies flat at y = 0
+pub fn get_sample_points(n: usize, stride: usize) -> Vec<[f32; 3]> {
+    let positions = cf::get_position_grid_n_by_n(n).0;
+    let points_per_row = n + 1;
+    let mut protag: Vec<[f32; 3]> = Vec::new();
+    for z in (0..points_per_row).step_by(stride) {
+        for x in (0..points_per_row).step_by(stride) {
+            let i = 3 * (z * points_per_row + x);
+            protag.push([positions[i], positions[i + 1], positions[i + 2]]);
+        }
+    }
+
+    protag
+}
+
+// blue for the slowest, through green, to red for the fastest
+pub fn get_magnitude_color(magnitude: f32, max_magnitude: f32) -> [f32; 3] {
+    let fraction = if max_magnitude > 0. {
+        (magnitude / max_magnitude).min(1.)
+    } else {
+        0.
+    };
+    hsv_to_rgb((1. - fraction) * 2. / 3., 0.8, 0.95)
+}
+
+// ==== one arrow per sample point, scaled and coloured by the field's magnitude there ==== //
+// the fastest arrow is VECTOR_FIELD_GLYPH_LENGTH long
+pub fn get_glyph_instances(field: &VectorField, sample_points: &Vec<[f32; 3]>) -> Vec<f32> {
+    let vectors: Vec<[f32; 3]> = sample_points.iter().map(|point| field.get(point)).collect();
+    let magnitudes: Vec<f32> = vectors.iter().map(|vector| magnitude(vector)).collect();
+    // skipping the infinite ones (see below), or they'd squash every other arrow to nothing
+    let max_magnitude = magnitudes
+        .iter()
+        .cloned()
+        .filter(|magnitude| magnitude.is_finite())
+        .fold(0., f32::max);
+    let length_scale = if max_magnitude > 0. {
+        VECTOR_FIELD_GLYPH_LENGTH / max_magnitude
+    } else {
+        0.
+    };
+
+    let mut protag: Vec<f32> = Vec::with_capacity(FLOATS_PER_GLYPH * sample_points.len());
+    for ((point, vector), &magnitude) in sample_points
+        .iter()
+        .zip(vectors.iter())
+        .zip(magnitudes.iter())
+    {
+        if !magnitude.is_finite() {
+            continue; // e.g. 1/x at x = 0
+        }
+        let color = get_magnitude_color(magnitude, max_magnitude);
+        protag.extend_from_slice(point);
+        protag.extend_from_slice(&[
+            vector[0] * length_scale,
+            vector[1] * length_scale,
+            vector[2] * length_scale,
+        ]);
+        protag.extend_from_slice(&color);
+    }
+
+    protag
+}
+
+// ==== streamlines, traced from each seed with fixed step rk4 ==== //
+// a line stops when it leaves the -1 to +1 box, runs into a spot where the field is (nearly) zero,
+// or after VECTOR_FIELD_STREAMLINE_STEPS steps. returns the points and an rgb per point,
+// coloured by magnitude like the glyphs
+pub fn get_streamlines(
+    field: &VectorField,
+    seeds: &Vec<[f32; 3]>,
+    step_size: f32,
+) -> Vec<(Vec<f32>, Vec<f32>)> {
+    let max_magnitude = seeds
+        .iter()
+        .map(|seed| magnitude(&field.get(seed)))
+        .filter(|magnitude| magnitude.is_finite())
+        .fold(0., f32::max);
+
+    let mut protag: Vec<(Vec<f32>, Vec<f32>)> = Vec::new();
+    for seed in seeds.iter() {
+        let mut positions: Vec<f32> = Vec::new();
+        let mut colors: Vec<f32> = Vec::new();
+        let mut point = *seed;
+        for _ in 0..VECTOR_FIELD_STREAMLINE_STEPS {
+            let speed = magnitude(&field.get(&point));
+            if !speed.is_finite() || speed < VECTOR_FIELD_MIN_MAGNITUDE {
+                break;
+            }
+            positions.extend_from_slice(&point);
+            colors.extend_from_slice(&get_magnitude_color(speed, max_magnitude));
+
+            point = rk4_step(field, &point, step_size);
+            if point.iter().any(|coord| !(coord.abs() <= 1.)) {
+                break;
+            }
+        }
+        if positions.len() >= 6 {
+            protag.push((positions, colors));
+        }
+    }
+
+    protag
+}
+
+fn rk4_step(field: &VectorField, point: &[f32; 3], h: f32) -> [f32; 3] {
+    let along = |from: &[f32; 3], direction: &[f32; 3], amount: f32| -> [f32; 3] {
+        [
+            from[0] + amount * direction[0],
+            from[1] + amount * direction[1],
+            from[2] + amount * direction[2],
+        ]
+    };
+    let k1 = field.get(point);
+    let k2 = field.get(&along(point, &k1, 0.5 * h));
+    let k3 = field.get(&along(point, &k2, 0.5 * h));
+    let k4 = field.get(&along(point, &k3, h));
+
+    let mut protag = *point;
+    for i in 0..3 {
+        protag[i] += h / 6. * (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]);
+    }
+    protag
+}
+
+fn magnitude(vector: &[f32; 3]) -> f32 {
+    (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn singular_points_dont_squash_the_other_glyphs() {
+        // infinite at x = 0, which is one of the sample points
+        let field = VectorField::compile("1/x", "0", "0").unwrap();
+        let sample_points = get_sample_points(GRID_SIZE, VECTOR_FIELD_GLYPH_STRIDE);
+        assert!(sample_points.iter().any(|point| point[0] == 0.));
+
+        let instances = get_glyph_instances(&field, &sample_points);
+        let lengths: Vec<f32> = instances
+            .chunks(FLOATS_PER_GLYPH)
+            .map(|glyph| magnitude(&[glyph[3], glyph[4], glyph[5]]))
+            .collect();
+        // every point but the ones on x = 0 gets an arrow, and none of them is empty
+        assert!(lengths.len() > 0 && lengths.len() < sample_points.len());
+        assert!(lengths
+            .iter()
+            .all(|&length| length.is_finite() && length > 0.));
+        let longest = lengths.iter().cloned().fold(0., f32::max);
+        assert!((longest - VECTOR_FIELD_GLYPH_LENGTH).abs() < 1e-5);
+    }
+}